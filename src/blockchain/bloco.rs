@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
-use crate::consenso::tipos::{Transacao, calcular_hash};
+use crate::consenso::tipos::{No, NodeId, Transacao, calcular_hash};
+use crate::consenso::cripto::{BytesCanonicos, ParChaves, verificar_assinatura};
+use crate::consenso::merkle::{hash_folha, ArvoreMerkle, ProvaEstado, RAIZ_VAZIA};
+use crate::consenso::ordenacao::{CertificadoFila, DerivacaoSeed};
+use crate::consenso::processamento::EvidenciaSalto;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 
 // Estrutura otimizada para serialização binária
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,8 +19,25 @@ pub struct Bloco {
     pub transacoes: Vec<Transacao>,
     pub hash_bloco: Vec<u8>,
     pub assinatura_minerador: Vec<u8>,
-    pub minerador_id: String,
+    pub minerador_id: NodeId,
+    pub chave_publica_minerador: Vec<u8>,
     pub dificuldade: u32,
+    /// Como o seed da rodada que produziu o bloco foi derivado (ausente no genesis)
+    #[serde(default)]
+    pub derivacao_seed: Option<DerivacaoSeed>,
+    /// Fila de processadores da rodada, assinada pelo quorum da própria fila
+    #[serde(default)]
+    pub certificado_fila: Option<CertificadoFila>,
+    /// Raiz da árvore esparsa de contas depois das transações do bloco
+    #[serde(default = "raiz_estado_vazia")]
+    pub raiz_estado: Vec<u8>,
+    /// Processadores pulados por perderem o prazo do slot, com os votos da fila
+    #[serde(default)]
+    pub saltos: Vec<EvidenciaSalto>,
+}
+
+fn raiz_estado_vazia() -> Vec<u8> {
+    RAIZ_VAZIA.to_vec()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +48,63 @@ pub struct CabecalhoBloco {
     pub timestamp: DateTime<Utc>,
     pub nonce: u64,
     pub dificuldade: u32,
+    pub derivacao_seed: Option<DerivacaoSeed>,
+    pub certificado_fila: Option<CertificadoFila>,
+    pub raiz_estado: Vec<u8>,
+    pub saltos: Vec<EvidenciaSalto>,
+}
+
+impl CabecalhoBloco {
+    /// Codificação canônica do cabeçalho, na ordem dos campos da struct
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut bytes = BytesCanonicos::new("NIMBOS_CABECALHO_V2")
+            .inteiro(self.numero)
+            .campo(&self.hash_anterior)
+            .campo(&self.merkle_root)
+            .timestamp(&self.timestamp)
+            .inteiro(self.nonce)
+            .inteiro(u64::from(self.dificuldade))
+            .opcional(self.derivacao_seed.as_ref().map(DerivacaoSeed::codificar).as_deref())
+            .opcional(self.certificado_fila.as_ref().map(CertificadoFila::codificar).as_deref())
+            .campo(&self.raiz_estado)
+            .inteiro(self.saltos.len() as u64);
+        
+        for salto in &self.saltos {
+            bytes = bytes.campo(&salto.codificar());
+        }
+        
+        bytes.finalizar()
+    }
+    
+    /// Mesmo hash gravado em `Bloco::hash_bloco`; basta o cabeçalho para conferi-lo
+    pub fn calcular_hash(&self) -> Result<Vec<u8>> {
+        Ok(calcular_hash(&self.codificar()))
+    }
+    
+    /// Confere saldo e nonce de uma conta (ou sua ausência) contra o estado deste bloco
+    pub fn verificar_prova_estado(&self, prova: &ProvaEstado) -> bool {
+        prova.verificar(&self.raiz_estado)
+    }
+}
+
+/// Campos do cabeçalho que vêm do consenso e do processamento, não da cadeia
+#[derive(Debug, Clone)]
+pub struct ExtrasCabecalho {
+    pub derivacao_seed: Option<DerivacaoSeed>,
+    pub certificado_fila: Option<CertificadoFila>,
+    pub raiz_estado: Vec<u8>,
+    pub saltos: Vec<EvidenciaSalto>,
+}
+
+impl Default for ExtrasCabecalho {
+    fn default() -> Self {
+        Self {
+            derivacao_seed: None,
+            certificado_fila: None,
+            raiz_estado: raiz_estado_vazia(),
+            saltos: Vec::new(),
+        }
+    }
 }
 
 // Implementação de serialização otimizada
@@ -34,9 +113,11 @@ impl Bloco {
         numero: u64,
         hash_anterior: Vec<u8>,
         transacoes: Vec<Transacao>,
-        minerador_id: String,
         dificuldade: u32,
+        extras: ExtrasCabecalho,
+        chaves: &ParChaves,
     ) -> Result<Self> {
+        let ExtrasCabecalho { derivacao_seed, certificado_fila, raiz_estado, saltos } = extras;
         let timestamp = Utc::now();
         let merkle_root = Self::calcular_merkle_root(&transacoes)?;
         
@@ -49,96 +130,121 @@ impl Bloco {
             transacoes,
             hash_bloco: vec![],
             assinatura_minerador: vec![],
-            minerador_id,
+            minerador_id: chaves.no_id(),
+            chave_publica_minerador: chaves.chave_publica(),
             dificuldade,
+            derivacao_seed,
+            certificado_fila,
+            raiz_estado,
+            saltos,
         };
         
         bloco.hash_bloco = bloco.calcular_hash()?;
-        bloco.assinatura_minerador = bloco.assinar_bloco()?;
+        bloco.assinar_bloco(chaves);
         
         Ok(bloco)
     }
     
+    /// O bloco genesis não tem minerador, portanto não é assinado.
+    ///
+    /// Todos os campos são fixos, para que todos os nós partam do mesmo hash.
     pub fn genesis() -> Result<Self> {
         let transacoes = vec![];
         let mut bloco = Self {
             numero: 0,
             hash_anterior: vec![0; 32], // Hash zero para bloco genesis
             merkle_root: vec![0; 32],
-            timestamp: Utc::now(),
+            timestamp: DateTime::UNIX_EPOCH,
             nonce: 0,
             transacoes,
             hash_bloco: vec![],
             assinatura_minerador: vec![],
-            minerador_id: "genesis".to_string(),
-            dificuldade: 1,
+            minerador_id: NodeId::default(), // Genesis não tem minerador
+            chave_publica_minerador: vec![],
+            dificuldade: 0, // Genesis não é minerado
+            derivacao_seed: None,
+            certificado_fila: None,
+            raiz_estado: raiz_estado_vazia(),
+            saltos: Vec::new(),
         };
         
         bloco.hash_bloco = bloco.calcular_hash()?;
-        bloco.assinatura_minerador = bloco.assinar_bloco()?;
         
         Ok(bloco)
     }
     
-    pub fn calcular_hash(&self) -> Result<Vec<u8>> {
-        let cabecalho = CabecalhoBloco {
+    pub fn cabecalho(&self) -> CabecalhoBloco {
+        CabecalhoBloco {
             numero: self.numero,
             hash_anterior: self.hash_anterior.clone(),
             merkle_root: self.merkle_root.clone(),
             timestamp: self.timestamp,
             nonce: self.nonce,
             dificuldade: self.dificuldade,
-        };
-        
-        let dados = serde_json::to_vec(&cabecalho)?;
-        Ok(calcular_hash(&dados))
+            derivacao_seed: self.derivacao_seed.clone(),
+            certificado_fila: self.certificado_fila.clone(),
+            raiz_estado: self.raiz_estado.clone(),
+            saltos: self.saltos.clone(),
+        }
+    }
+    
+    pub fn calcular_hash(&self) -> Result<Vec<u8>> {
+        self.cabecalho().calcular_hash()
+    }
+    
+    /// Hash de folha de uma transação na árvore do bloco
+    pub fn hash_transacao(transacao: &Transacao) -> Result<Vec<u8>> {
+        Ok(hash_folha(&transacao.codificar()))
     }
     
+    /// Raiz da árvore de Merkle compartilhada com o consenso; cada folha é uma transação codificada
     pub fn calcular_merkle_root(transacoes: &[Transacao]) -> Result<Vec<u8>> {
-        if transacoes.is_empty() {
-            return Ok(vec![0; 32]);
-        }
-        
-        let mut hashes: Vec<Vec<u8>> = transacoes
+        Ok(Self::arvore_transacoes(transacoes)?.obter_root())
+    }
+    
+    pub fn arvore_transacoes(transacoes: &[Transacao]) -> Result<ArvoreMerkle> {
+        let folhas = transacoes
             .iter()
-            .map(|tx| {
-                let dados = serde_json::to_vec(tx).unwrap();
-                calcular_hash(&dados)
-            })
-            .collect();
-        
-        // Construir árvore Merkle
-        while hashes.len() > 1 {
-            let mut proxima_camada = Vec::new();
-            
-            for chunk in hashes.chunks(2) {
-                let hash_combinado = if chunk.len() == 2 {
-                    let mut dados = Vec::new();
-                    dados.extend_from_slice(&chunk[0]);
-                    dados.extend_from_slice(&chunk[1]);
-                    calcular_hash(&dados)
-                } else {
-                    // Se número ímpar, duplicar o último hash
-                    let mut dados = Vec::new();
-                    dados.extend_from_slice(&chunk[0]);
-                    dados.extend_from_slice(&chunk[0]);
-                    calcular_hash(&dados)
-                };
-                
-                proxima_camada.push(hash_combinado);
-            }
-            
-            hashes = proxima_camada;
+            .map(Self::hash_transacao)
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(ArvoreMerkle::de_hashes_folha(folhas))
+    }
+    
+    /// Bytes assinados pelo minerador: hash do bloco e identidade de quem o produziu
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_BLOCO_V1")
+            .campo(&self.hash_bloco)
+            .campo(self.minerador_id.como_bytes())
+            .campo(&self.chave_publica_minerador)
+            .finalizar()
+    }
+    
+    fn assinar_bloco(&mut self, chaves: &ParChaves) {
+        self.minerador_id = chaves.no_id();
+        self.chave_publica_minerador = chaves.chave_publica();
+        self.assinatura_minerador = chaves.assinar(&self.bytes_para_assinatura());
+    }
+    
+    /// Verifica a assinatura contra a chave pública registrada no bloco
+    pub fn verificar_assinatura(&self) -> bool {
+        if self.numero == 0 {
+            return self.assinatura_minerador.is_empty();
         }
         
-        Ok(hashes.into_iter().next().unwrap_or(vec![0; 32]))
+        self.minerador_id.corresponde(&self.chave_publica_minerador)
+            && verificar_assinatura(
+                &self.chave_publica_minerador,
+                &self.bytes_para_assinatura(),
+                &self.assinatura_minerador,
+            )
     }
     
-    fn assinar_bloco(&self) -> Result<Vec<u8>> {
-        let mut dados = Vec::new();
-        dados.extend_from_slice(&self.hash_bloco);
-        dados.extend_from_slice(self.minerador_id.as_bytes());
-        Ok(calcular_hash(&dados))
+    /// Verifica que o bloco foi assinado pelo nó informado
+    pub fn verificar_minerador(&self, minerador: &No) -> bool {
+        self.minerador_id == minerador.id
+            && self.chave_publica_minerador == minerador.chave_publica
+            && self.verificar_assinatura()
     }
     
     pub fn validar_estrutura(&self) -> Result<bool> {
@@ -155,11 +261,53 @@ impl Bloco {
         }
         
         // Validar assinatura
-        let assinatura_calculada = self.assinar_bloco()?;
-        if assinatura_calculada != self.assinatura_minerador {
+        if !self.verificar_assinatura() {
             return Ok(false);
         }
         
+        // Validar derivação do seed
+        if let Some(derivacao) = &self.derivacao_seed {
+            if !derivacao.verificar() {
+                return Ok(false);
+            }
+        }
+        
+        // Raiz do estado é sempre um hash de 32 bytes (zero se não há contas)
+        if self.raiz_estado.len() != RAIZ_VAZIA.len() {
+            return Ok(false);
+        }
+        
+        // Validar certificado da fila: merkle root da fila listada, quorum e mesmo
+        // seed da derivação registrada. O quorum do certificado anterior é
+        // conferido pela cadeia, que conhece os blocos já aceitos
+        if let Some(certificado) = &self.certificado_fila {
+            if !certificado.fila_confere() || !certificado.tem_quorum() {
+                return Ok(false);
+            }
+            
+            if let Some(derivacao) = &self.derivacao_seed {
+                if certificado.rodada != derivacao.rodada || certificado.seed != derivacao.seed {
+                    return Ok(false);
+                }
+            }
+        }
+        
+        // Saltos da rodada certificada: um por slot, contra o processador daquele
+        // slot na fila e com o quorum dela
+        let mut slots = HashSet::new();
+        for salto in &self.saltos {
+            let Some(certificado) = self.certificado_fila.as_ref().filter(|c| c.rodada == salto.rodada) else {
+                return Ok(false);
+            };
+            
+            if !slots.insert(salto.slot)
+                || !salto.confere_com_fila(&certificado.nos)
+                || salto.processador == self.minerador_id
+            {
+                return Ok(false);
+            }
+        }
+        
         Ok(true)
     }
     
@@ -173,13 +321,13 @@ impl Bloco {
         hash_hex.starts_with(&"0".repeat(zeros_necessarios))
     }
     
-    pub fn minerar(&mut self, max_tentativas: u64) -> Result<bool> {
+    pub fn minerar(&mut self, max_tentativas: u64, chaves: &ParChaves) -> Result<bool> {
         for nonce in 0..max_tentativas {
             self.nonce = nonce;
             self.hash_bloco = self.calcular_hash()?;
             
             if self.validar_dificuldade() {
-                self.assinatura_minerador = self.assinar_bloco()?;
+                self.assinar_bloco(chaves);
                 return Ok(true);
             }
         }
@@ -230,4 +378,75 @@ impl Bloco {
         
         Ok((json_size, binary_size, reducao_percentual))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    #[test]
+    fn hash_cabecalho_vetor_de_referencia() {
+        let cabecalho = CabecalhoBloco {
+            numero: 1,
+            hash_anterior: vec![0; 32],
+            merkle_root: vec![1; 32],
+            timestamp: DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+            nonce: 0,
+            dificuldade: 1,
+            derivacao_seed: None,
+            certificado_fila: None,
+            raiz_estado: vec![2; 32],
+            saltos: Vec::new(),
+        };
+
+        assert_eq!(
+            hex::encode(cabecalho.calcular_hash().unwrap()),
+            "52dd66eaf05fdbb46c8848cd89542619b47f33c3ecf39d85b441279c4b2ae3d8"
+        );
+    }
+
+    #[test]
+    fn hash_folha_transacao_vetor_de_referencia() {
+        let transacao = Transacao {
+            id: "tx1".to_string(),
+            dados: b"dados".to_vec(),
+            estado_anterior: b"a".to_vec(),
+            estado_final: b"b".to_vec(),
+            timestamp: DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+            nonce: 3,
+            assinatura: vec![9; 4],
+            transferencia: None,
+        };
+
+        assert_eq!(
+            hex::encode(Bloco::hash_transacao(&transacao).unwrap()),
+            "b3f862ec6d4254f7620d0c3a4ee4db7540545e4a6f1d9839fabd050c096fa741"
+        );
+    }
+
+    #[test]
+    fn bloco_so_confere_com_a_assinatura_do_minerador() {
+        let chaves = ParChaves::gerar();
+        let outra = ParChaves::gerar();
+        let bloco = Bloco::new(1, vec![0; 32], Vec::new(), 0, ExtrasCabecalho::default(), &chaves).unwrap();
+        assert!(bloco.verificar_assinatura());
+        assert!(bloco.verificar_minerador(&No::novo(chaves.chave_publica(), String::new(), 0)));
+        assert!(!bloco.verificar_minerador(&No::novo(outra.chave_publica(), String::new(), 0)));
+
+        // Outro nó se apresenta como minerador sem assinar de novo
+        let mut outro_minerador = bloco.clone();
+        outro_minerador.minerador_id = outra.no_id();
+        outro_minerador.chave_publica_minerador = outra.chave_publica();
+        assert!(!outro_minerador.verificar_assinatura());
+
+        // Id de um, chave e assinatura de outro
+        let mut id_trocado = bloco.clone();
+        id_trocado.assinar_bloco(&outra);
+        id_trocado.minerador_id = chaves.no_id();
+        assert!(!id_trocado.verificar_assinatura());
+
+        // A assinatura cobre o hash do cabeçalho
+        let mut adulterado = bloco;
+        adulterado.hash_bloco = vec![1; 32];
+        assert!(!adulterado.verificar_assinatura());
+    }
+}
@@ -1,5 +1,8 @@
 use super::bloco::*;
+use super::prova_transacao::{ProvaTransacao, ProvaTransacoes};
 use crate::consenso::tipos::Transacao;
+use crate::consenso::cripto::ParChaves;
+use crate::consenso::ordenacao::CertificadoFila;
 use anyhow::Result;
 use std::collections::HashMap;
 use tokio::sync::RwLock;
@@ -117,16 +120,19 @@ impl CadeiaBlockchain {
         // Persistir no banco antes de adicionar à memória
         self.persistir_bloco(&bloco).await?;
         
-        let mut blocos = self.blocos.write().await;
-        let mut indice = self.indice_hash.write().await;
-        let mut altura = self.altura_atual.write().await;
-        
-        let posicao = blocos.len();
-        indice.insert(bloco.hash_bloco.clone(), posicao);
-        blocos.push(bloco.clone());
-        *altura = bloco.numero;
-        
-        info!("Bloco {} adicionado à cadeia na posição {} e persistido", bloco.numero, posicao);
+        // Os locks são soltos antes de ajustar a dificuldade, que volta a ler os blocos
+        {
+            let mut blocos = self.blocos.write().await;
+            let mut indice = self.indice_hash.write().await;
+            let mut altura = self.altura_atual.write().await;
+            
+            let posicao = blocos.len();
+            indice.insert(bloco.hash_bloco.clone(), posicao);
+            blocos.push(bloco.clone());
+            *altura = bloco.numero;
+            
+            info!("Bloco {} adicionado à cadeia na posição {} e persistido", bloco.numero, posicao);
+        }
         
         // Ajustar dificuldade se necessário
         self.ajustar_dificuldade().await?;
@@ -134,7 +140,12 @@ impl CadeiaBlockchain {
         Ok(())
     }
     
-    pub async fn criar_proximo_bloco(&self, transacoes: Vec<Transacao>) -> Result<Bloco> {
+    pub async fn criar_proximo_bloco(
+        &self,
+        transacoes: Vec<Transacao>,
+        extras: ExtrasCabecalho,
+        chaves: &ParChaves,
+    ) -> Result<Bloco> {
         let blocos = self.blocos.read().await;
         let dificuldade = *self.dificuldade_atual.read().await;
         
@@ -146,16 +157,10 @@ impl CadeiaBlockchain {
         
         drop(blocos);
         
-        let mut bloco = Bloco::new(
-            numero,
-            hash_anterior,
-            transacoes,
-            "minerador_atual".to_string(),
-            dificuldade,
-        )?;
+        let mut bloco = Bloco::new(numero, hash_anterior, transacoes, dificuldade, extras, chaves)?;
         
         // Minerar o bloco
-        if !bloco.minerar(1000000)? {
+        if !bloco.minerar(1000000, chaves)? {
             warn!("Falha ao minerar bloco após 1M tentativas");
         }
         
@@ -183,6 +188,48 @@ impl CadeiaBlockchain {
         blocos.last().cloned()
     }
     
+    /// Certificado de fila gravado no bloco produzido pela rodada informada
+    pub async fn obter_certificado_fila(&self, rodada: u64) -> Option<CertificadoFila> {
+        let blocos = self.blocos.read().await;
+        blocos
+            .iter()
+            .rev()
+            .filter_map(|bloco| bloco.certificado_fila.as_ref())
+            .find(|certificado| certificado.rodada == rodada)
+            .cloned()
+    }
+    
+    /// Procura a transação do bloco mais recente ao mais antigo e gera a prova de inclusão
+    pub async fn gerar_prova_transacao(&self, id_transacao: &str) -> Result<Option<ProvaTransacao>> {
+        let blocos = self.blocos.read().await;
+        
+        for bloco in blocos.iter().rev() {
+            if let Some(posicao) = bloco.transacoes.iter().position(|tx| tx.id == id_transacao) {
+                return ProvaTransacao::gerar(bloco, posicao);
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// Prova conjunta de várias transações de um bloco; `None` se o bloco ou alguma transação não existir
+    pub async fn gerar_prova_transacoes(&self, numero_bloco: u64, ids_transacoes: &[&str]) -> Result<Option<ProvaTransacoes>> {
+        let blocos = self.blocos.read().await;
+        let Some(bloco) = blocos.get(numero_bloco as usize) else {
+            return Ok(None);
+        };
+        
+        let posicoes = ids_transacoes
+            .iter()
+            .map(|id| bloco.transacoes.iter().position(|tx| tx.id == *id))
+            .collect::<Option<Vec<_>>>();
+        
+        match posicoes {
+            Some(posicoes) => Ok(Some(ProvaTransacoes::gerar(bloco, &posicoes)?)),
+            None => Ok(None),
+        }
+    }
+    
     pub async fn obter_altura(&self) -> u64 {
         *self.altura_atual.read().await
     }
@@ -217,6 +264,26 @@ impl CadeiaBlockchain {
                 error!("Hash anterior inválido");
                 return Ok(false);
             }
+            
+            // O seed do bloco precisa continuar o do último bloco que registrou um
+            if let Some(derivacao) = &bloco.derivacao_seed {
+                let anterior = blocos.iter().rev().find_map(|b| b.derivacao_seed.as_ref());
+                if let Some(anterior) = anterior {
+                    if !derivacao.continua(anterior) {
+                        error!("Derivação do seed não continua a do bloco anterior");
+                        return Ok(false);
+                    }
+                }
+            }
+            
+            // A fila certificada precisa do aval de mais de 2/3 da última fila certificada
+            if let Some(certificado) = &bloco.certificado_fila {
+                let anterior = blocos.iter().rev().find_map(|b| b.certificado_fila.as_ref());
+                if !certificado.sucede(anterior) {
+                    error!("Certificado da fila da rodada {} sem o quorum da fila certificada anterior", certificado.rodada);
+                    return Ok(false);
+                }
+            }
         } else if bloco.numero != 0 {
             error!("Primeiro bloco deve ser genesis (número 0)");
             return Ok(false);
@@ -261,6 +328,14 @@ impl CadeiaBlockchain {
                     return Ok(false);
                 }
             }
+            
+            if let Some(certificado) = &bloco.certificado_fila {
+                let anterior = blocos[..i].iter().rev().find_map(|b| b.certificado_fila.as_ref());
+                if !certificado.sucede(anterior) {
+                    error!("Bloco {} tem certificado de fila sem o quorum da fila anterior", i);
+                    return Ok(false);
+                }
+            }
         }
         
         info!("Validação completa da cadeia: SUCESSO");
@@ -1,7 +1,10 @@
 use super::{bloco::Bloco, cadeia::CadeiaBlockchain};
+use crate::consenso::cripto::BytesCanonicos;
+use crate::consenso::processamento::{Conta, FabricaMaquinaEstado, MaquinaEstado, MaquinaTransferencias};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 use chrono::{DateTime, Utc};
@@ -24,11 +27,42 @@ pub struct EstadoCheckpoint {
     pub metadata: HashMap<String, String>,
 }
 
+impl EstadoCheckpoint {
+    /// Codificação canônica das contas e contratos, em ordem de chave.
+    ///
+    /// `metadata` é informação local do nó e fica de fora.
+    pub fn codificar(&self) -> Vec<u8> {
+        let balances: BTreeMap<_, _> = self.balances.iter().collect();
+        let nonces: BTreeMap<_, _> = self.nonces.iter().collect();
+        let contratos: BTreeMap<_, _> = self.contratos.iter().collect();
+        
+        let mut bytes = BytesCanonicos::new("NIMBOS_ESTADO_CHECKPOINT_V1")
+            .inteiro(balances.len() as u64);
+        for (id, saldo) in balances {
+            bytes = bytes.campo(id.as_bytes()).inteiro(*saldo);
+        }
+        
+        bytes = bytes.inteiro(nonces.len() as u64);
+        for (id, nonce) in nonces {
+            bytes = bytes.campo(id.as_bytes()).inteiro(*nonce);
+        }
+        
+        bytes = bytes.inteiro(contratos.len() as u64);
+        for (id, codigo) in contratos {
+            bytes = bytes.campo(id.as_bytes()).campo(codigo);
+        }
+        
+        bytes.finalizar()
+    }
+}
+
 pub struct SistemaCheckpoint {
     checkpoints: RwLock<Vec<Checkpoint>>,
     estados: RwLock<HashMap<u64, EstadoCheckpoint>>,
     intervalo_checkpoint: u64,
     ultimo_checkpoint: RwLock<Option<u64>>,
+    /// Máquina de estado no genesis, sobre a qual a cadeia é reexecutada
+    fabrica_maquina: RwLock<FabricaMaquinaEstado>,
 }
 
 impl SistemaCheckpoint {
@@ -38,6 +72,7 @@ impl SistemaCheckpoint {
             estados: RwLock::new(HashMap::new()),
             intervalo_checkpoint: 100, // Checkpoint a cada 100 blocos
             ultimo_checkpoint: RwLock::new(None),
+            fabrica_maquina: RwLock::new(Arc::new(|| Box::new(MaquinaTransferencias::new()))),
         }
     }
     
@@ -47,9 +82,15 @@ impl SistemaCheckpoint {
             estados: RwLock::new(HashMap::new()),
             intervalo_checkpoint: intervalo,
             ultimo_checkpoint: RwLock::new(None),
+            fabrica_maquina: RwLock::new(Arc::new(|| Box::new(MaquinaTransferencias::new()))),
         }
     }
     
+    /// Máquina de estado que o processamento usa, já no estado do genesis
+    pub async fn definir_maquina_estado(&self, fabrica: FabricaMaquinaEstado) {
+        *self.fabrica_maquina.write().await = fabrica;
+    }
+    
     pub async fn deve_criar_checkpoint(&self, cadeia: &CadeiaBlockchain) -> bool {
         let altura_atual = cadeia.obter_altura().await;
         let ultimo = *self.ultimo_checkpoint.read().await;
@@ -72,13 +113,13 @@ impl SistemaCheckpoint {
             .ok_or_else(|| anyhow::anyhow!("Bloco não encontrado para checkpoint"))?;
         
         // Calcular estado atual
-        let estado = self.calcular_estado_atual(cadeia, altura_atual).await?;
+        let (estado, raiz_estado) = self.calcular_estado_atual(cadeia, altura_atual).await?;
         
         // Criar checkpoint
         let checkpoint = Checkpoint {
             numero_bloco: altura_atual,
             hash_bloco: bloco.hash_bloco.clone(),
-            merkle_root_estado: self.calcular_merkle_root_estado(&estado)?,
+            merkle_root_estado: raiz_estado,
             timestamp: Utc::now(),
             assinatura: self.assinar_checkpoint(&bloco, &estado)?,
             validadores: vec!["validador_principal".to_string()], // Em implementação real, seria dinâmico
@@ -97,6 +138,16 @@ impl SistemaCheckpoint {
         Ok(())
     }
     
+    /// Cria um checkpoint se o bloco recém-anexado fechou um intervalo
+    pub async fn processar_bloco(&self, bloco: &Bloco, cadeia: &CadeiaBlockchain) -> Result<()> {
+        if self.deve_criar_checkpoint(cadeia).await {
+            info!("Bloco {} fecha um intervalo de checkpoint", bloco.numero);
+            self.criar_checkpoint(cadeia).await?;
+        }
+        
+        Ok(())
+    }
+    
     pub async fn validar_checkpoint(&self, checkpoint: &Checkpoint, cadeia: &CadeiaBlockchain) -> Result<bool> {
         // Verificar se o bloco existe
         let bloco = cadeia.obter_bloco_por_numero(checkpoint.numero_bloco).await
@@ -109,11 +160,10 @@ impl SistemaCheckpoint {
         }
         
         // Recalcular estado
-        let estado = self.calcular_estado_atual(cadeia, checkpoint.numero_bloco).await?;
-        let merkle_calculado = self.calcular_merkle_root_estado(&estado)?;
+        let (estado, raiz_estado) = self.calcular_estado_atual(cadeia, checkpoint.numero_bloco).await?;
         
         // Verificar Merkle root do estado
-        if merkle_calculado != checkpoint.merkle_root_estado {
+        if raiz_estado != checkpoint.merkle_root_estado {
             warn!("Merkle root do estado no checkpoint não confere");
             return Ok(false);
         }
@@ -146,7 +196,17 @@ impl SistemaCheckpoint {
             .ok_or_else(|| anyhow::anyhow!("Estado do checkpoint {} não encontrado", numero_bloco))
     }
     
-    async fn calcular_estado_atual(&self, cadeia: &CadeiaBlockchain, ate_bloco: u64) -> Result<EstadoCheckpoint> {
+    /// Reexecuta a cadeia até `ate_bloco` numa máquina nova; devolve o estado e a sua raiz
+    async fn calcular_estado_atual(&self, cadeia: &CadeiaBlockchain, ate_bloco: u64) -> Result<(EstadoCheckpoint, Vec<u8>)> {
+        let mut maquina = (self.fabrica_maquina.read().await)();
+        
+        // Processar todos os blocos até o número especificado
+        for numero in 0..=ate_bloco {
+            if let Some(bloco) = cadeia.obter_bloco_por_numero(numero).await {
+                self.aplicar_transacoes_ao_estado(maquina.as_mut(), &bloco)?;
+            }
+        }
+        
         let mut estado = EstadoCheckpoint {
             balances: HashMap::new(),
             contratos: HashMap::new(),
@@ -154,52 +214,49 @@ impl SistemaCheckpoint {
             metadata: HashMap::new(),
         };
         
-        // Processar todos os blocos até o número especificado
-        for numero in 0..=ate_bloco {
-            if let Some(bloco) = cadeia.obter_bloco_por_numero(numero).await {
-                self.aplicar_transacoes_ao_estado(&mut estado, &bloco).await?;
+        // Contas vão para saldos e nonces; o resto do estado, pela chave em hex
+        for (chave, valor) in maquina.entradas() {
+            match (String::from_utf8(chave.clone()), Conta::decodificar(&valor)) {
+                (Ok(id), Ok(conta)) => {
+                    if conta.saldo > 0 {
+                        estado.balances.insert(id.clone(), conta.saldo);
+                    }
+                    if conta.nonce > 0 {
+                        estado.nonces.insert(id, conta.nonce);
+                    }
+                }
+                _ => {
+                    estado.contratos.insert(hex::encode(&chave), valor);
+                }
             }
         }
         
         // Adicionar metadata
         estado.metadata.insert("ultimo_bloco".to_string(), ate_bloco.to_string());
         estado.metadata.insert("timestamp".to_string(), Utc::now().to_rfc3339());
+        estado.metadata.insert("maquina_estado".to_string(), maquina.nome().to_string());
         
-        Ok(estado)
+        Ok((estado, maquina.raiz_estado()))
     }
     
-    async fn aplicar_transacoes_ao_estado(&self, estado: &mut EstadoCheckpoint, bloco: &Bloco) -> Result<()> {
-        for transacao in &bloco.transacoes {
-            // Simular aplicação de transação ao estado
-            // Em implementação real, isso seria mais complexo
-            
-            // Atualizar nonce
-            let nonce_atual = estado.nonces.get(&transacao.id).unwrap_or(&0);
-            estado.nonces.insert(transacao.id.clone(), nonce_atual + 1);
-            
-            // Simular mudança de balance (exemplo)
-            let balance_atual = estado.balances.get(&transacao.id).unwrap_or(&1000);
-            estado.balances.insert(transacao.id.clone(), balance_atual.saturating_sub(1));
+    /// Mesma máquina de estado usada pelo processamento; as taxas vão para o minerador do bloco
+    fn aplicar_transacoes_ao_estado(&self, maquina: &mut dyn MaquinaEstado, bloco: &Bloco) -> Result<()> {
+        maquina.iniciar_bloco(bloco.numero);
+        for resultado in maquina.aplicar_lote(&bloco.transacoes, &bloco.minerador_id.to_string()) {
+            resultado.map_err(|e| anyhow::anyhow!("Bloco {}: {}", bloco.numero, e))?;
         }
         
+        maquina.confirmar();
         Ok(())
     }
     
-    fn calcular_merkle_root_estado(&self, estado: &EstadoCheckpoint) -> Result<Vec<u8>> {
-        use crate::consenso::tipos::calcular_hash;
-        
-        let dados_estado = serde_json::to_vec(estado)?;
-        Ok(calcular_hash(&dados_estado))
-    }
-    
     fn assinar_checkpoint(&self, bloco: &Bloco, estado: &EstadoCheckpoint) -> Result<Vec<u8>> {
         use crate::consenso::tipos::calcular_hash;
         
-        let mut dados = Vec::new();
-        dados.extend_from_slice(&bloco.hash_bloco);
-        
-        let estado_serializado = serde_json::to_vec(estado)?;
-        dados.extend_from_slice(&estado_serializado);
+        let dados = BytesCanonicos::new("NIMBOS_CHECKPOINT_V1")
+            .campo(&bloco.hash_bloco)
+            .campo(&estado.codificar())
+            .finalizar();
         
         Ok(calcular_hash(&dados))
     }
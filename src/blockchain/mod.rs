@@ -3,7 +3,9 @@ mod cadeia;
 mod checkpoint;
 mod validador_cadeia;
 mod migrador;
+mod prova_transacao;
 
+use crate::consenso::cripto::Assinador;
 use anyhow::Result;
 use tracing::info;
 use std::path::Path;
@@ -13,26 +15,29 @@ pub use cadeia::*;
 pub use checkpoint::*;
 pub use validador_cadeia::*;
 pub use migrador::*;
+pub use prova_transacao::*;
 
 pub struct CamadaBlockchain {
     cadeia: CadeiaBlockchain,
     checkpoint: SistemaCheckpoint,
     validador: ValidadorCadeia,
     persistencia_ativa: bool,
+    assinador: Assinador,
 }
 
 impl CamadaBlockchain {
-    pub fn new() -> Self {
+    pub fn new(assinador: Assinador) -> Self {
         Self {
             cadeia: CadeiaBlockchain::new(),
             checkpoint: SistemaCheckpoint::new(),
             validador: ValidadorCadeia::new(),
             persistencia_ativa: false,
+            assinador,
         }
     }
     
     /// Cria nova camada com persistência otimizada
-    pub fn new_com_persistencia<P: AsRef<Path>>(caminho_db: P) -> Result<Self> {
+    pub fn new_com_persistencia<P: AsRef<Path>>(caminho_db: P, assinador: Assinador) -> Result<Self> {
         info!("Inicializando blockchain com persistência em: {:?}", caminho_db.as_ref());
         
         Ok(Self {
@@ -40,6 +45,7 @@ impl CamadaBlockchain {
             checkpoint: SistemaCheckpoint::new(),
             validador: ValidadorCadeia::new(),
             persistencia_ativa: true,
+            assinador,
         })
     }
     
@@ -62,15 +68,40 @@ impl CamadaBlockchain {
         Ok(())
     }
 
-    pub async fn adicionar_bloco(&mut self, transacoes: Vec<crate::consenso::tipos::Transacao>) -> Result<()> {
+    /// Grava o genesis se a cadeia estiver vazia
+    pub async fn inicializar(&self) -> Result<()> {
+        if self.cadeia.obter_tamanho_cadeia().await == 0 {
+            self.cadeia.inicializar_com_genesis().await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Monta, assina e anexa o bloco deste nó; devolve o bloco para ser transmitido
+    pub async fn adicionar_bloco(
+        &self,
+        transacoes: Vec<crate::consenso::tipos::Transacao>,
+        extras: ExtrasCabecalho,
+    ) -> Result<Bloco> {
         info!("Adicionando novo bloco à cadeia (persistência: {})", self.persistencia_ativa);
         
         // Criar novo bloco
-        let bloco = self.cadeia.criar_proximo_bloco(transacoes).await?;
+        let chaves = self.assinador.chaves_atuais();
+        let bloco = self.cadeia.criar_proximo_bloco(transacoes, extras, &chaves).await?;
         
-        // Validar bloco
-        if !self.validador.validar_bloco(&bloco, &self.cadeia).await? {
-            return Err(anyhow::anyhow!("Bloco inválido"));
+        self.anexar_bloco(bloco.clone()).await?;
+        Ok(bloco)
+    }
+    
+    /// Valida um bloco contra a ponta atual da cadeia, sem anexá-lo
+    pub async fn validar_bloco(&self, bloco: &Bloco) -> Result<bool> {
+        self.validador.validar_bloco(bloco, &self.cadeia).await
+    }
+    
+    /// Valida e anexa um bloco (próprio ou recebido de outro nó)
+    pub async fn anexar_bloco(&self, bloco: Bloco) -> Result<()> {
+        if !self.validar_bloco(&bloco).await? {
+            return Err(anyhow::anyhow!("Bloco {} inválido", bloco.numero));
         }
         
         // Adicionar à cadeia (com persistência automática se ativa)
@@ -82,4 +113,23 @@ impl CamadaBlockchain {
         info!("Bloco {} adicionado com sucesso", bloco.numero);
         Ok(())
     }
+    
+    /// Outra máquina de estado para reexecutar a cadeia nos checkpoints; a mesma do processamento
+    pub async fn definir_maquina_estado(&self, fabrica: crate::consenso::processamento::FabricaMaquinaEstado) {
+        self.checkpoint.definir_maquina_estado(fabrica).await;
+    }
+    
+    /// Certificado da fila de uma rodada, para nós que entraram depois dela
+    pub async fn obter_certificado_fila(&self, rodada: u64) -> Option<crate::consenso::ordenacao::CertificadoFila> {
+        self.cadeia.obter_certificado_fila(rodada).await
+    }
+    
+    /// Prova de inclusão da transação, a partir do bloco mais recente que a contém
+    pub async fn gerar_prova_transacao(&self, id_transacao: &str) -> Result<Option<ProvaTransacao>> {
+        self.cadeia.gerar_prova_transacao(id_transacao).await
+    }
+    
+    pub async fn gerar_prova_transacoes(&self, numero_bloco: u64, ids_transacoes: &[&str]) -> Result<Option<ProvaTransacoes>> {
+        self.cadeia.gerar_prova_transacoes(numero_bloco, ids_transacoes).await
+    }
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use super::bloco::{Bloco, CabecalhoBloco};
+use crate::consenso::merkle::{verificar_hash_folha, verificar_multipla_hash_folha, MultiProva, ProvaInclusao};
+use crate::consenso::tipos::Transacao;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Prova de que uma transação está num bloco, conferível só com o cabeçalho.
+///
+/// Permite a uma carteira confirmar um pagamento sem baixar o bloco inteiro.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvaTransacao {
+    /// Hash de folha da transação (`Bloco::hash_transacao`)
+    pub hash_transacao: Vec<u8>,
+    pub prova: ProvaInclusao,
+    pub numero_bloco: u64,
+    pub hash_bloco: Vec<u8>,
+}
+
+impl ProvaTransacao {
+    /// Gera a prova para a transação de índice `posicao` no bloco
+    pub fn gerar(bloco: &Bloco, posicao: usize) -> Result<Option<Self>> {
+        let Some(transacao) = bloco.transacoes.get(posicao) else {
+            return Ok(None);
+        };
+        
+        let arvore = Bloco::arvore_transacoes(&bloco.transacoes)?;
+        
+        Ok(Some(Self {
+            hash_transacao: Bloco::hash_transacao(transacao)?,
+            prova: ProvaInclusao {
+                posicao: posicao as u64,
+                caminho: arvore.obter_caminho_prova(posicao),
+            },
+            numero_bloco: bloco.numero,
+            hash_bloco: bloco.hash_bloco.clone(),
+        }))
+    }
+    
+    /// Confere a prova contra o cabeçalho: bloco certo e caminho até a merkle root dele
+    pub fn verificar(&self, cabecalho: &CabecalhoBloco) -> Result<bool> {
+        if cabecalho.numero != self.numero_bloco || cabecalho.calcular_hash()? != self.hash_bloco {
+            return Ok(false);
+        }
+        
+        Ok(verificar_hash_folha(&cabecalho.merkle_root, &self.hash_transacao, &self.prova))
+    }
+    
+    /// Como `verificar`, conferindo também que a prova é da transação informada
+    pub fn verificar_transacao(&self, transacao: &Transacao, cabecalho: &CabecalhoBloco) -> Result<bool> {
+        Ok(Bloco::hash_transacao(transacao)? == self.hash_transacao && self.verificar(cabecalho)?)
+    }
+}
+
+/// Prova de várias transações do mesmo bloco, com os irmãos compartilhados uma vez só
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvaTransacoes {
+    /// Hashes de folha na ordem de `prova.indices`
+    pub hashes_transacoes: Vec<Vec<u8>>,
+    pub prova: MultiProva,
+    pub numero_bloco: u64,
+    pub hash_bloco: Vec<u8>,
+}
+
+impl ProvaTransacoes {
+    pub fn gerar(bloco: &Bloco, posicoes: &[usize]) -> Result<Self> {
+        let arvore = Bloco::arvore_transacoes(&bloco.transacoes)?;
+        let prova = MultiProva::gerar(&arvore, posicoes)?;
+        
+        let hashes_transacoes = prova
+            .indices
+            .iter()
+            .map(|&i| Bloco::hash_transacao(&bloco.transacoes[i as usize]))
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(Self {
+            hashes_transacoes,
+            prova,
+            numero_bloco: bloco.numero,
+            hash_bloco: bloco.hash_bloco.clone(),
+        })
+    }
+    
+    pub fn verificar(&self, cabecalho: &CabecalhoBloco) -> Result<bool> {
+        if cabecalho.numero != self.numero_bloco || cabecalho.calcular_hash()? != self.hash_bloco {
+            return Ok(false);
+        }
+        
+        Ok(verificar_multipla_hash_folha(&cabecalho.merkle_root, &self.hashes_transacoes, &self.prova))
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::cripto::ParChaves;
+    use crate::blockchain::ExtrasCabecalho;
+
+    fn transacao(i: usize) -> Transacao {
+        Transacao {
+            id: format!("tx{}", i),
+            dados: format!("dados {}", i).into_bytes(),
+            estado_anterior: vec![],
+            estado_final: vec![i as u8],
+            timestamp: chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap(),
+            nonce: i as u64,
+            assinatura: vec![],
+            transferencia: None,
+        }
+    }
+
+    fn bloco(quantidade: usize) -> Bloco {
+        let transacoes = (0..quantidade).map(transacao).collect();
+        Bloco::new(7, vec![0; 32], transacoes, 0, ExtrasCabecalho::default(), &ParChaves::gerar()).unwrap()
+    }
+
+    #[test]
+    fn prova_de_cada_transacao_confere_com_o_cabecalho() {
+        for quantidade in 1..=7 {
+            let bloco = bloco(quantidade);
+            let cabecalho = bloco.cabecalho();
+
+            for (posicao, transacao) in bloco.transacoes.iter().enumerate() {
+                let prova = ProvaTransacao::gerar(&bloco, posicao).unwrap().unwrap();
+                assert!(prova.verificar(&cabecalho).unwrap());
+                assert!(prova.verificar_transacao(transacao, &cabecalho).unwrap());
+
+                let outra = &bloco.transacoes[(posicao + 1) % quantidade];
+                assert_eq!(prova.verificar_transacao(outra, &cabecalho).unwrap(), quantidade == 1);
+            }
+
+            assert!(ProvaTransacao::gerar(&bloco, quantidade).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn prova_nao_confere_com_outro_cabecalho() {
+        let bloco = bloco(5);
+        let prova = ProvaTransacao::gerar(&bloco, 3).unwrap().unwrap();
+
+        // Outro bloco com as mesmas transações: a merkle root bate, o hash não
+        let mut outro = bloco.cabecalho();
+        outro.nonce += 1;
+        assert!(!prova.verificar(&outro).unwrap());
+
+        let mut outro_numero = bloco.cabecalho();
+        outro_numero.numero += 1;
+        assert!(!prova.verificar(&outro_numero).unwrap());
+
+        // Caminho adulterado não chega na merkle root
+        let mut adulterada = prova.clone();
+        adulterada.prova.caminho[0].hash[0] ^= 1;
+        assert!(!adulterada.verificar(&bloco.cabecalho()).unwrap());
+    }
+
+    #[test]
+    fn prova_de_varias_transacoes_confere_com_o_cabecalho() {
+        let bloco = bloco(6);
+        let cabecalho = bloco.cabecalho();
+
+        let prova = ProvaTransacoes::gerar(&bloco, &[4, 0, 5]).unwrap();
+        assert!(prova.verificar(&cabecalho).unwrap());
+
+        let mut trocada = prova.clone();
+        trocada.hashes_transacoes[0] = Bloco::hash_transacao(&bloco.transacoes[1]).unwrap();
+        assert!(!trocada.verificar(&cabecalho).unwrap());
+
+        let mut outro = cabecalho.clone();
+        outro.nonce += 1;
+        assert!(!prova.verificar(&outro).unwrap());
+    }
+}
@@ -87,8 +87,10 @@ impl ValidadorCadeia {
     pub async fn validar_bloco(&self, bloco: &Bloco, cadeia: &CadeiaBlockchain) -> Result<bool> {
         let inicio = Utc::now();
         
-        // Verificar cache primeiro
-        if let Some(resultado_cache) = self.obter_do_cache(&bloco.hash_bloco).await {
+        // Verificar cache primeiro, pelo hash recalculado: um cabeçalho adulterado
+        // que mantém o `hash_bloco` original não pode herdar o resultado
+        let hash_cabecalho = bloco.calcular_hash()?;
+        if let Some(resultado_cache) = self.obter_do_cache(&hash_cabecalho).await {
             info!("Resultado de validação obtido do cache");
             return Ok(resultado_cache.valido);
         }
@@ -121,7 +123,7 @@ impl ValidadorCadeia {
         self.atualizar_estatisticas(&resultado, bloco.numero).await;
         
         // Armazenar no cache
-        self.armazenar_no_cache(bloco.hash_bloco.clone(), resultado.clone()).await;
+        self.armazenar_no_cache(hash_cabecalho, resultado.clone()).await;
         
         // Log do resultado
         if resultado.valido {
@@ -157,11 +159,9 @@ impl ValidadorCadeia {
         }
         
         // Validar assinatura se configurado
-        if self.configuracao.validar_assinaturas {
-            // Em implementação real, validaria assinatura criptográfica
-            if bloco.assinatura_minerador.is_empty() {
-                resultado.avisos.push("Assinatura do minerador vazia".to_string());
-            }
+        if self.configuracao.validar_assinaturas && !bloco.verificar_assinatura() {
+            resultado.valido = false;
+            resultado.erros.push("Assinatura do minerador inválida".to_string());
         }
         
         Ok(())
@@ -354,4 +354,47 @@ impl ValidadorCadeia {
         
         Ok(todos_validos)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::blockchain::ExtrasCabecalho;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::ordenacao::DerivacaoSeed;
+
+    #[tokio::test]
+    async fn bloco_com_derivacao_do_seed_adulterada_e_invalido() {
+        let cadeia = CadeiaBlockchain::new();
+        cadeia.inicializar_com_genesis().await.unwrap();
+        let chaves = ParChaves::gerar();
+        let derivacao = DerivacaoSeed::derivar(1, vec![0; 32], vec![vec![1; 32], vec![2; 32]]);
+
+        let bloco_com = |derivacao: DerivacaoSeed| {
+            let extras = ExtrasCabecalho { derivacao_seed: Some(derivacao), ..ExtrasCabecalho::default() };
+            cadeia.criar_proximo_bloco(Vec::new(), extras, &chaves)
+        };
+
+        let validador = ValidadorCadeia::new();
+        let valido = bloco_com(derivacao.clone()).await.unwrap();
+        assert!(validador.validar_bloco(&valido, &cadeia).await.unwrap());
+
+        // Seed que não sai dos nonces, mesmo com o bloco assinado por cima
+        let mut outro_seed = derivacao.clone();
+        outro_seed.seed = vec![7; 32];
+        assert!(!validador.validar_bloco(&bloco_com(outro_seed.clone()).await.unwrap(), &cadeia).await.unwrap());
+
+        // Nonce trocado ou fora de ordem
+        let mut outro_nonce = derivacao.clone();
+        outro_nonce.nonces[1] = vec![3; 32];
+        assert!(!validador.validar_bloco(&bloco_com(outro_nonce).await.unwrap(), &cadeia).await.unwrap());
+        let mut fora_de_ordem = derivacao.clone();
+        fora_de_ordem.nonces.reverse();
+        assert!(!validador.validar_bloco(&bloco_com(fora_de_ordem).await.unwrap(), &cadeia).await.unwrap());
+
+        // Derivação trocada depois de assinado: o hash já validado não vale para o novo cabeçalho
+        let mut adulterado = valido.clone();
+        adulterado.derivacao_seed = Some(outro_seed);
+        assert!(!validador.validar_bloco(&adulterado, &cadeia).await.unwrap());
+    }
+}
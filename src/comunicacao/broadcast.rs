@@ -5,15 +5,7 @@ use tokio::sync::RwLock;
 use tokio::time::{Duration, timeout};
 use tracing::{info, warn, error};
 use crate::comunicacao::protocolo::{Mensagem, RespostaMensagem};
-
-#[derive(Debug, Clone)]
-pub struct No {
-    pub id: String,
-    pub endereco: String,
-    pub porta: u16,
-    pub ativo: bool,
-    pub ultima_resposta: chrono::DateTime<chrono::Utc>,
-}
+use crate::consenso::tipos::No;
 
 pub struct SistemaBroadcast {
     timeout_padrao: Duration,
@@ -3,9 +3,16 @@ mod retry;
 mod protocolo;
 mod rede;
 
+use crate::blockchain::{Bloco, CamadaBlockchain, ExtrasCabecalho};
+use crate::consenso::cripto::Assinador;
+use crate::consenso::ordenacao::{BilheteVrf, CertificadoFila};
+use crate::consenso::tipos::{Commitment, Reveal, Transacao};
+use crate::consenso::processamento::{CamadaProcessamento, ResultadoInsercao, VotoSalto};
+use crate::consenso::{EntradaConsenso, SaidaConsenso};
 use anyhow::Result;
+use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{info, error};
+use tracing::{info, warn, error};
 
 pub use broadcast::*;
 pub use retry::*;
@@ -17,10 +24,16 @@ pub struct CamadaComunicacao {
     retry: MecanismoRetry,
     rede: GerenciadorRede,
     canal_mensagens: mpsc::UnboundedSender<Mensagem>,
+    assinador: Assinador,
+    blockchain: Arc<CamadaBlockchain>,
+    /// Confirma o estado do bloco deste nó depois de anexado
+    processamento: Arc<CamadaProcessamento>,
 }
 
 impl CamadaComunicacao {
-    pub async fn new() -> Result<Self> {
+    /// `entrada` recebe os commitments e reveals que chegam da rede; `blockchain`
+    /// monta os blocos deste nó e anexa os recebidos
+    pub async fn new(assinador: Assinador, entrada: EntradaConsenso, blockchain: Arc<CamadaBlockchain>) -> Result<Self> {
         let (tx, mut rx) = mpsc::unbounded_channel();
         
         let sistema = Self {
@@ -28,12 +41,15 @@ impl CamadaComunicacao {
             retry: MecanismoRetry::new(),
             rede: GerenciadorRede::new(),
             canal_mensagens: tx,
+            assinador,
+            blockchain: blockchain.clone(),
+            processamento: entrada.processamento.clone(),
         };
         
         // Iniciar loop de processamento de mensagens
         tokio::spawn(async move {
             while let Some(mensagem) = rx.recv().await {
-                if let Err(e) = Self::processar_mensagem(mensagem).await {
+                if let Err(e) = Self::processar_mensagem(mensagem, &entrada, &blockchain).await {
                     error!("Erro ao processar mensagem: {}", e);
                 }
             }
@@ -42,6 +58,11 @@ impl CamadaComunicacao {
         Ok(sistema)
     }
     
+    /// Cria uma mensagem assinada com a chave deste nó
+    pub fn criar_mensagem(&self, tipo: TipoMensagem, dados: Vec<u8>) -> Mensagem {
+        Mensagem::nova(tipo, dados, &self.assinador.chaves_atuais())
+    }
+    
     pub async fn enviar_broadcast(&self, mensagem: Mensagem) -> Result<()> {
         info!("Enviando broadcast: {:?}", mensagem.tipo);
         
@@ -51,21 +72,217 @@ impl CamadaComunicacao {
         Ok(())
     }
     
-    async fn processar_mensagem(mensagem: Mensagem) -> Result<()> {
+    /// Serializa e envia a todos os nós ativos o que o consenso produziu
+    pub async fn transmitir_saida_consenso(&self, saida: SaidaConsenso) -> Result<()> {
+        let mensagem = match saida {
+            SaidaConsenso::Commitment(commitment) => {
+                self.criar_mensagem(TipoMensagem::Commitment, serde_json::to_vec(&commitment)?)
+            }
+            SaidaConsenso::Reveal(reveal) => {
+                self.criar_mensagem(TipoMensagem::Reveal, serde_json::to_vec(&reveal)?)
+            }
+            SaidaConsenso::BilheteVrf(bilhete) => {
+                self.criar_mensagem(TipoMensagem::BilheteVrf, serde_json::to_vec(&bilhete)?)
+            }
+            SaidaConsenso::CertificadoFila(certificado) => {
+                self.criar_mensagem(TipoMensagem::CertificadoFila, serde_json::to_vec(&certificado)?)
+            }
+            SaidaConsenso::Bloco(proposta) => {
+                let extras = ExtrasCabecalho {
+                    derivacao_seed: proposta.derivacao_seed,
+                    certificado_fila: proposta.certificado_fila,
+                    raiz_estado: proposta.raiz_estado,
+                    saltos: proposta.saltos,
+                };
+                let adicionado = self.blockchain.adicionar_bloco(proposta.transacoes, extras).await;
+                
+                // O estado do lote só vale se o bloco entrou na cadeia
+                let bloco = match adicionado {
+                    Ok(bloco) => bloco,
+                    Err(e) => {
+                        self.processamento.descartar_lote().await;
+                        return Err(e);
+                    }
+                };
+                self.processamento.confirmar_lote(bloco.numero, &bloco.transacoes, &bloco.saltos).await;
+                
+                self.criar_mensagem(TipoMensagem::Bloco, serde_json::to_vec(&bloco)?)
+            }
+            SaidaConsenso::VotoSalto(voto) => {
+                self.criar_mensagem(TipoMensagem::VotoSalto, serde_json::to_vec(&voto)?)
+            }
+        };
+        
+        self.enviar_broadcast(mensagem).await
+    }
+    
+    /// Entrega uma mensagem recebida da rede ao despachante
+    pub fn receber_mensagem(&self, mensagem: Mensagem) -> Result<()> {
+        self.canal_mensagens
+            .send(mensagem)
+            .map_err(|_| anyhow::anyhow!("Loop de processamento de mensagens encerrado"))
+    }
+    
+    async fn processar_mensagem(mensagem: Mensagem, entrada: &EntradaConsenso, blockchain: &CamadaBlockchain) -> Result<()> {
+        // Rejeitar mensagens cujo id declarado não confere com a chave que assinou
+        if !mensagem.verificar_assinatura() {
+            warn!("Mensagem {} rejeitada: remetente {} não confere com a assinatura", mensagem.id, mensagem.remetente);
+            return Err(anyhow::anyhow!("Assinatura ou identidade do remetente inválida"));
+        }
+        
         match mensagem.tipo {
             TipoMensagem::Commitment => {
-                info!("Processando commitment recebido");
+                let commitment: Commitment = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Commitment mal formado em {}: {}", mensagem.id, e))?;
+                
+                // O commitment não é assinado: quem responde por ele é o remetente da mensagem
+                if commitment.no_id != mensagem.remetente {
+                    warn!("Commitment de {} enviado por {} rejeitado", commitment.no_id, mensagem.remetente);
+                    return Err(anyhow::anyhow!("Commitment não pertence ao remetente"));
+                }
+                
+                if entrada.registro.adicionar_commitment(commitment).await? {
+                    info!("Commitment de {} registrado", mensagem.remetente);
+                }
             },
             TipoMensagem::Reveal => {
-                info!("Processando reveal recebido");
+                let reveal: Reveal = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Reveal mal formado em {}: {}", mensagem.id, e))?;
+                
+                if reveal.no_id != mensagem.remetente {
+                    warn!("Reveal de {} enviado por {} rejeitado", reveal.no_id, mensagem.remetente);
+                    return Err(anyhow::anyhow!("Reveal não pertence ao remetente"));
+                }
+                
+                let commitments = entrada.registro.obter_commitments().await;
+                if entrada.reveal.adicionar_reveal(reveal, &commitments).await? {
+                    info!("Reveal de {} verificado", mensagem.remetente);
+                }
+            },
+            TipoMensagem::BilheteVrf => {
+                let bilhete: BilheteVrf = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Bilhete VRF mal formado em {}: {}", mensagem.id, e))?;
+                
+                if bilhete.no_id != mensagem.remetente {
+                    warn!("Bilhete VRF de {} enviado por {} rejeitado", bilhete.no_id, mensagem.remetente);
+                    return Err(anyhow::anyhow!("Bilhete VRF não pertence ao remetente"));
+                }
+                
+                if entrada.ordenacao.adicionar_bilhete(bilhete).await? {
+                    info!("Bilhete VRF de {} verificado", mensagem.remetente);
+                }
+            },
+            TipoMensagem::CertificadoFila => {
+                let certificado: CertificadoFila = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Certificado de fila mal formado em {}: {}", mensagem.id, e))?;
+                
+                // Cada nó envia a própria assinatura; as dos outros chegam pelas mensagens deles
+                if !certificado.assinaturas.iter().any(|a| a.no_id == mensagem.remetente) {
+                    warn!("Certificado de fila enviado por {} sem a assinatura dele", mensagem.remetente);
+                    return Err(anyhow::anyhow!("Certificado de fila não assinado pelo remetente"));
+                }
+                
+                if entrada.ordenacao.adicionar_certificado(certificado).await? {
+                    info!("Assinatura da fila de {} registrada", mensagem.remetente);
+                }
+            },
+            TipoMensagem::Bloco => {
+                let bloco: Bloco = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Bloco mal formado em {}: {}", mensagem.id, e))?;
+                
+                if bloco.minerador_id != mensagem.remetente {
+                    warn!("Bloco de {} enviado por {} rejeitado", bloco.minerador_id, mensagem.remetente);
+                    return Err(anyhow::anyhow!("Bloco não pertence ao remetente"));
+                }
+                
+                if !blockchain.validar_bloco(&bloco).await? {
+                    return Err(anyhow::anyhow!("Bloco {} de {} inválido", bloco.numero, mensagem.remetente));
+                }
+                
+                // O estado só avança se a reexecução chegar à raiz gravada no bloco
+                // e o bloco for anexado; saltos e rotação, só depois de anexado
+                entrada.processamento
+                    .aplicar_bloco_remoto(&bloco.transacoes, bloco.numero, bloco.minerador_id, &bloco.raiz_estado, &bloco.saltos)
+                    .await?;
+                
+                let numero = bloco.numero;
+                let transacoes = bloco.transacoes.clone();
+                let saltos = bloco.saltos.clone();
+                if let Err(e) = blockchain.anexar_bloco(bloco).await {
+                    entrada.processamento.descartar_lote().await;
+                    return Err(e);
+                }
+                entrada.processamento.confirmar_lote(numero, &transacoes, &saltos).await;
+                info!("Bloco {} de {} anexado", numero, mensagem.remetente);
+            },
+            TipoMensagem::VotoSalto => {
+                let voto: VotoSalto = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Voto de salto mal formado em {}: {}", mensagem.id, e))?;
+                
+                if voto.no_id != mensagem.remetente {
+                    warn!("Voto de salto de {} enviado por {} rejeitado", voto.no_id, mensagem.remetente);
+                    return Err(anyhow::anyhow!("Voto de salto não pertence ao remetente"));
+                }
+                
+                if entrada.processamento.adicionar_voto_salto(voto).await? {
+                    info!("Processador pulado com o voto de {}", mensagem.remetente);
+                }
             },
             TipoMensagem::Validacao => {
                 info!("Processando validação recebida");
             },
             TipoMensagem::Transacao => {
-                info!("Processando transação recebida");
+                let transacao: Transacao = serde_json::from_slice(&mensagem.dados)
+                    .map_err(|e| anyhow::anyhow!("Transação mal formada em {}: {}", mensagem.id, e))?;
+                
+                // Quem responde pela transferência é a assinatura do dono da conta, não o remetente
+                let id = transacao.id.clone();
+                match entrada.processamento.receber_transacao(transacao).await? {
+                    ResultadoInsercao::Duplicada => {}
+                    resultado => info!("Transação {} aceita no mempool: {:?}", id, resultado),
+                }
             },
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::registro::GeradorCommitment;
+    use crate::consenso::{SistemaConsenso, ID_CADEIA_PADRAO};
+
+    fn mensagem_commitment(chaves: &ParChaves, commitment: &Commitment) -> Mensagem {
+        Mensagem::nova(TipoMensagem::Commitment, serde_json::to_vec(commitment).unwrap(), chaves)
+    }
+
+    #[tokio::test]
+    async fn commitment_recebido_da_rede_chega_ao_registro() {
+        let assinador = Assinador::new(ParChaves::gerar());
+        let (saida, _rx) = mpsc::unbounded_channel();
+        let entrada = SistemaConsenso::new(assinador.clone(), saida).await.unwrap().entrada();
+        let blockchain = CamadaBlockchain::new(assinador);
+
+        let par = ParChaves::gerar();
+        let nonce = GeradorCommitment::gerar_nonce();
+        let commitment = GeradorCommitment::criar_commitment(ID_CADEIA_PADRAO, 1, &par.chave_publica(), &nonce, par.no_id());
+
+        // Reencaminhado por outro nó: ninguém responde pelo commitment alheio
+        let intermediario = ParChaves::gerar();
+        let reencaminhado = mensagem_commitment(&intermediario, &commitment);
+        assert!(CamadaComunicacao::processar_mensagem(reencaminhado, &entrada, &blockchain).await.is_err());
+        assert!(entrada.registro.obter_commitments().await.is_empty());
+
+        let mensagem = mensagem_commitment(&par, &commitment);
+        CamadaComunicacao::processar_mensagem(mensagem, &entrada, &blockchain).await.unwrap();
+        let registrados = entrada.registro.obter_commitments().await;
+        assert_eq!(registrados.get(&par.no_id()).map(|c| &c.hash), Some(&commitment.hash));
+
+        // Mensagem com os dados trocados depois de assinada
+        let mut adulterada = mensagem_commitment(&par, &commitment);
+        adulterada.dados = b"{}".to_vec();
+        assert!(CamadaComunicacao::processar_mensagem(adulterada, &entrada, &blockchain).await.is_err());
+    }
+}
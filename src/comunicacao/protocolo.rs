@@ -1,11 +1,13 @@
 use serde::{Deserialize, Serialize};
-use crate::consenso::tipos::*;
+use crate::consenso::cripto::{BytesCanonicos, ParChaves, verificar_assinatura};
+use crate::consenso::tipos::NodeId;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mensagem {
     pub id: String,
     pub tipo: TipoMensagem,
-    pub remetente: String,
+    pub remetente: NodeId,
+    pub chave_publica_remetente: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub dados: Vec<u8>,
     pub assinatura: Vec<u8>,
@@ -17,6 +19,26 @@ pub enum TipoMensagem {
     Reveal,
     Validacao,
     Transacao,
+    BilheteVrf,
+    CertificadoFila,
+    Bloco,
+    VotoSalto,
+}
+
+impl TipoMensagem {
+    /// Código estável usado nos bytes assinados
+    pub fn codigo(&self) -> u8 {
+        match self {
+            TipoMensagem::Commitment => 1,
+            TipoMensagem::Reveal => 2,
+            TipoMensagem::Validacao => 3,
+            TipoMensagem::Transacao => 4,
+            TipoMensagem::BilheteVrf => 5,
+            TipoMensagem::CertificadoFila => 6,
+            TipoMensagem::Bloco => 7,
+            TipoMensagem::VotoSalto => 8,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,28 +50,38 @@ pub struct RespostaMensagem {
 }
 
 impl Mensagem {
-    pub fn nova(tipo: TipoMensagem, remetente: String, dados: Vec<u8>) -> Self {
+    pub fn nova(tipo: TipoMensagem, dados: Vec<u8>, chaves: &ParChaves) -> Self {
         let id = format!("msg_{}_{}", chrono::Utc::now().timestamp_nanos(), rand::random::<u32>());
         
-        Self {
+        let mut mensagem = Self {
             id,
             tipo,
-            remetente: remetente.clone(),
+            remetente: chaves.no_id(),
+            chave_publica_remetente: chaves.chave_publica(),
             timestamp: chrono::Utc::now(),
-            dados: dados.clone(),
-            assinatura: Self::calcular_assinatura(&remetente, &dados),
-        }
+            dados,
+            assinatura: Vec::new(),
+        };
+        mensagem.assinatura = chaves.assinar(&mensagem.bytes_para_assinatura());
+        
+        mensagem
     }
     
-    fn calcular_assinatura(remetente: &str, dados: &[u8]) -> Vec<u8> {
-        let mut conteudo = Vec::new();
-        conteudo.extend_from_slice(remetente.as_bytes());
-        conteudo.extend_from_slice(dados);
-        calcular_hash(&conteudo)
+    /// Bytes assinados pelo remetente (a própria assinatura fica de fora)
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_MENSAGEM_V1")
+            .campo(self.id.as_bytes())
+            .campo(&[self.tipo.codigo()])
+            .campo(self.remetente.como_bytes())
+            .campo(&self.chave_publica_remetente)
+            .timestamp(&self.timestamp)
+            .campo(&self.dados)
+            .finalizar()
     }
     
+    /// Verifica que o remetente declarado é dono da chave e que ela assinou a mensagem
     pub fn verificar_assinatura(&self) -> bool {
-        let assinatura_esperada = Self::calcular_assinatura(&self.remetente, &self.dados);
-        assinatura_esperada == self.assinatura
+        self.remetente.corresponde(&self.chave_publica_remetente)
+            && verificar_assinatura(&self.chave_publica_remetente, &self.bytes_para_assinatura(), &self.assinatura)
     }
 }
\ No newline at end of file
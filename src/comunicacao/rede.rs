@@ -4,7 +4,9 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{Duration, Instant, interval};
 use tracing::{info, warn, error, debug};
-use crate::comunicacao::broadcast::No;
+use crate::consenso::tipos::No;
+use crate::consenso::cripto::ParChaves;
+use crate::consenso::tipos::NodeId;
 
 #[derive(Debug, Clone)]
 pub struct ConfiguracaoRede {
@@ -36,7 +38,7 @@ pub struct StatusNo {
 
 pub struct GerenciadorRede {
     configuracao: ConfiguracaoRede,
-    nos_conhecidos: Arc<RwLock<HashMap<String, StatusNo>>>,
+    nos_conhecidos: Arc<RwLock<HashMap<NodeId, StatusNo>>>,
     nos_seeds: Arc<RwLock<Vec<String>>>, // Nós iniciais para bootstrap
     estatisticas: Arc<RwLock<EstatisticasRede>>,
 }
@@ -101,6 +103,10 @@ impl GerenciadorRede {
     pub async fn adicionar_no(&self, no: No) -> Result<()> {
         info!("Adicionando nó {} à rede", no.id);
         
+        if !no.id.corresponde(&no.chave_publica) {
+            return Err(anyhow!("Id do nó {} não corresponde à sua chave pública", no.id));
+        }
+        
         let status = StatusNo {
             no: no.clone(),
             ultima_atividade: Instant::now(),
@@ -111,7 +117,7 @@ impl GerenciadorRede {
         
         {
             let mut nos = self.nos_conhecidos.write().await;
-            nos.insert(no.id.clone(), status);
+            nos.insert(no.id, status);
         }
         
         // Testar conectividade inicial
@@ -121,7 +127,7 @@ impl GerenciadorRede {
         Ok(())
     }
     
-    pub async fn remover_no(&self, id_no: &str) -> Result<()> {
+    pub async fn remover_no(&self, id_no: &NodeId) -> Result<()> {
         info!("Removendo nó {} da rede", id_no);
         
         {
@@ -176,13 +182,8 @@ impl GerenciadorRede {
         let mut nos_descobertos = Vec::new();
         
         for i in 0..num_nos {
-            let no = No {
-                id: format!("no_descoberto_{}_{}", endereco_seed.replace(".", "_"), i),
-                endereco: format!("192.168.1.{}", 100 + i),
-                porta: 8080 + i as u16,
-                ativo: rand::random::<f32>() > 0.1, // 90% de chance de estar ativo
-                ultima_resposta: chrono::Utc::now(),
-            };
+            let mut no = Self::no_simulado(format!("192.168.1.{}", 100 + i), 8080 + i as u16);
+            no.ativo = rand::random::<f32>() > 0.1; // 90% de chance de estar ativo
             nos_descobertos.push(no);
         }
         
@@ -197,7 +198,7 @@ impl GerenciadorRede {
         Ok(nos_descobertos)
     }
     
-    async fn testar_conectividade_no(&self, id_no: &str) -> Result<Duration> {
+    async fn testar_conectividade_no(&self, id_no: &NodeId) -> Result<Duration> {
         let inicio = Instant::now();
         
         // Simulação de teste de conectividade
@@ -244,7 +245,7 @@ impl GerenciadorRede {
     }
     
     async fn task_heartbeat(
-        nos: Arc<RwLock<HashMap<String, StatusNo>>>,
+        nos: Arc<RwLock<HashMap<NodeId, StatusNo>>>,
         configuracao: ConfiguracaoRede,
         estatisticas: Arc<RwLock<EstatisticasRede>>,
     ) {
@@ -262,7 +263,7 @@ impl GerenciadorRede {
                 for (id, status) in nos_read.iter() {
                     let tempo_desde_atividade = agora.duration_since(status.ultima_atividade);
                     if tempo_desde_atividade > configuracao.timeout_heartbeat / 2 {
-                        nos_para_testar.push(id.clone());
+                        nos_para_testar.push(*id);
                     }
                 }
             }
@@ -286,7 +287,7 @@ impl GerenciadorRede {
     }
     
     async fn task_descoberta_nos(
-        nos: Arc<RwLock<HashMap<String, StatusNo>>>,
+        nos: Arc<RwLock<HashMap<NodeId, StatusNo>>>,
         seeds: Arc<RwLock<Vec<String>>>,
         configuracao: ConfiguracaoRede,
     ) {
@@ -323,7 +324,7 @@ impl GerenciadorRede {
     }
     
     async fn task_limpeza_nos(
-        nos: Arc<RwLock<HashMap<String, StatusNo>>>,
+        nos: Arc<RwLock<HashMap<NodeId, StatusNo>>>,
         configuracao: ConfiguracaoRede,
     ) {
         let mut intervalo = interval(Duration::from_secs(300)); // A cada 5 minutos
@@ -341,12 +342,12 @@ impl GerenciadorRede {
                     
                     // Remover nós inativos há muito tempo
                     if !status.no.ativo && tempo_inativo > Duration::from_secs(3600) { // 1 hora
-                        nos_para_remover.push(id.clone());
+                        nos_para_remover.push(*id);
                     }
                     
                     // Remover nós com muitas falhas consecutivas
                     if status.tentativas_conexao > 10 {
-                        nos_para_remover.push(id.clone());
+                        nos_para_remover.push(*id);
                     }
                 }
             }
@@ -363,8 +364,8 @@ impl GerenciadorRede {
     }
     
     async fn testar_conectividade_individual(
-        nos: &Arc<RwLock<HashMap<String, StatusNo>>>,
-        id_no: &str,
+        nos: &Arc<RwLock<HashMap<NodeId, StatusNo>>>,
+        id_no: &NodeId,
     ) -> Result<Duration> {
         let inicio = Instant::now();
         
@@ -391,8 +392,8 @@ impl GerenciadorRede {
     }
     
     async fn marcar_no_inativo(
-        nos: &Arc<RwLock<HashMap<String, StatusNo>>>,
-        id_no: &str,
+        nos: &Arc<RwLock<HashMap<NodeId, StatusNo>>>,
+        id_no: &NodeId,
     ) {
         let mut nos_write = nos.write().await;
         if let Some(status) = nos_write.get_mut(id_no) {
@@ -408,6 +409,7 @@ impl GerenciadorRede {
     
     async fn descobrir_nos_simulado(seed: &str) -> Result<Vec<No>> {
         // Simulação de descoberta
+        debug!("Consultando seed {}", seed);
         tokio::time::sleep(Duration::from_millis(50)).await;
         
         if rand::random::<f32>() < 0.1 {
@@ -417,14 +419,11 @@ impl GerenciadorRede {
         let num_nos = rand::random::<usize>() % 3; // 0-2 novos nós
         let mut nos = Vec::new();
         
-        for i in 0..num_nos {
-            let no = No {
-                id: format!("descoberto_{}_{}", seed.replace(".", "_"), i),
-                endereco: format!("10.0.{}.{}", rand::random::<u8>(), rand::random::<u8>()),
-                porta: 8080,
-                ativo: true,
-                ultima_resposta: chrono::Utc::now(),
-            };
+        for _ in 0..num_nos {
+            let no = Self::no_simulado(
+                format!("10.0.{}.{}", rand::random::<u8>(), rand::random::<u8>()),
+                8080,
+            );
             nos.push(no);
         }
         
@@ -432,9 +431,14 @@ impl GerenciadorRede {
     }
     
     async fn adicionar_no_descoberto(
-        nos: &Arc<RwLock<HashMap<String, StatusNo>>>,
+        nos: &Arc<RwLock<HashMap<NodeId, StatusNo>>>,
         no: No,
     ) {
+        if !no.id.corresponde(&no.chave_publica) {
+            warn!("Nó descoberto {} ignorado: id não corresponde à chave pública", no.id);
+            return;
+        }
+        
         let mut nos_write = nos.write().await;
         
         if !nos_write.contains_key(&no.id) {
@@ -446,18 +450,18 @@ impl GerenciadorRede {
                 historico_disponibilidade: Vec::new(),
             };
             
-            nos_write.insert(no.id.clone(), status);
+            nos_write.insert(no.id, status);
             info!("Novo nó descoberto e adicionado: {}", no.id);
         }
     }
     
-    async fn descobrir_via_gossip(nos: &Arc<RwLock<HashMap<String, StatusNo>>>) {
+    async fn descobrir_via_gossip(nos: &Arc<RwLock<HashMap<NodeId, StatusNo>>>) {
         // Simulação de descoberta via gossip protocol
-        let nos_ativos: Vec<String> = {
+        let nos_ativos: Vec<NodeId> = {
             let nos_read = nos.read().await;
             nos_read.iter()
                 .filter(|(_, status)| status.no.ativo)
-                .map(|(id, _)| id.clone())
+                .map(|(id, _)| *id)
                 .collect()
         };
         
@@ -468,21 +472,23 @@ impl GerenciadorRede {
         // Simular troca de informações entre nós
         for _ in 0..3 { // Até 3 novos nós via gossip
             if rand::random::<f32>() < 0.3 {
-                let no = No {
-                    id: format!("gossip_{}", rand::random::<u32>()),
-                    endereco: format!("172.16.{}.{}", rand::random::<u8>(), rand::random::<u8>()),
-                    porta: 8080,
-                    ativo: true,
-                    ultima_resposta: chrono::Utc::now(),
-                };
+                let no = Self::no_simulado(
+                    format!("172.16.{}.{}", rand::random::<u8>(), rand::random::<u8>()),
+                    8080,
+                );
                 
                 Self::adicionar_no_descoberto(nos, no).await;
             }
         }
     }
     
+    /// Nó fictício com chave própria, para que seu id seja derivado dela
+    fn no_simulado(endereco: String, porta: u16) -> No {
+        No::novo(ParChaves::gerar().chave_publica(), endereco, porta)
+    }
+    
     async fn atualizar_estatisticas_task(
-        nos: &Arc<RwLock<HashMap<String, StatusNo>>>,
+        nos: &Arc<RwLock<HashMap<NodeId, StatusNo>>>,
         estatisticas: &Arc<RwLock<EstatisticasRede>>,
     ) {
         let (total, ativos, latencia_total, disponibilidade_total) = {
@@ -539,7 +545,7 @@ impl GerenciadorRede {
         self.estatisticas.read().await.clone()
     }
     
-    pub async fn obter_status_detalhado(&self) -> HashMap<String, StatusNo> {
+    pub async fn obter_status_detalhado(&self) -> HashMap<NodeId, StatusNo> {
         self.nos_conhecidos.read().await.clone()
     }
     
@@ -583,4 +589,38 @@ impl EstatisticasRede {
             _ => "Crítica".to_string(),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes;
+
+    #[tokio::test]
+    async fn nos_da_fila_do_consenso_entram_na_rede_como_estao() {
+        let chaves = apoio_testes::chaves(3);
+        let fila = apoio_testes::fila(&chaves);
+        let rede = GerenciadorRede::new();
+
+        for no in fila.nos.iter().cloned() {
+            // A conectividade é simulada e falha ao acaso; o nó fica conhecido mesmo assim
+            let _ = rede.adicionar_no(no).await;
+        }
+
+        let mut ativos: Vec<(NodeId, Vec<u8>)> = rede.obter_nos_ativos().await
+            .into_iter()
+            .map(|no| (no.id, no.chave_publica))
+            .collect();
+        let mut esperados: Vec<(NodeId, Vec<u8>)> = fila.nos.iter().map(|no| (no.id, no.chave_publica.clone())).collect();
+        ativos.sort();
+        esperados.sort();
+        assert_eq!(ativos, esperados);
+
+        // Mesmo modelo, mas com o id de outra chave: recusado
+        let mut forjado = apoio_testes::no(&ParChaves::gerar());
+        forjado.id = fila.nos[0].id;
+        rede.remover_no(&forjado.id).await.unwrap();
+        assert!(rede.adicionar_no(forjado).await.is_err());
+        assert_eq!(rede.obter_nos_ativos().await.len(), 2);
+    }
+}
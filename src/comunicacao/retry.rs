@@ -5,7 +5,7 @@ use tokio::sync::RwLock;
 use tokio::time::{Duration, sleep, Instant};
 use tracing::{info, warn, error, debug};
 use crate::comunicacao::protocolo::{Mensagem, RespostaMensagem};
-use crate::comunicacao::broadcast::No;
+use crate::consenso::tipos::No;
 
 #[derive(Debug, Clone)]
 pub struct ConfiguracaoRetry {
@@ -0,0 +1,62 @@
+//! Fixtures compartilhadas pelos testes das camadas do consenso
+
+use crate::consenso::cripto::ParChaves;
+use crate::consenso::merkle::raiz_merkle;
+use crate::consenso::ordenacao::CertificadoFila;
+use crate::consenso::processamento::{EvidenciaSalto, VotoSalto};
+use crate::consenso::tipos::{FilaOrdenada, No, NodeId};
+use std::borrow::Borrow;
+
+/// `n` pares de chaves novos
+pub fn chaves(n: usize) -> Vec<ParChaves> {
+    (0..n).map(|_| ParChaves::gerar()).collect()
+}
+
+/// As mesmas chaves por referência, para montar filas com subconjuntos e intrusos
+pub fn referencias(chaves: &[ParChaves]) -> Vec<&ParChaves> {
+    chaves.iter().collect()
+}
+
+/// Ids dos nós, na ordem das chaves
+pub fn ids(chaves: &[ParChaves]) -> Vec<NodeId> {
+    chaves.iter().map(ParChaves::no_id).collect()
+}
+
+pub fn no(chaves: &ParChaves) -> No {
+    No::novo(chaves.chave_publica(), "127.0.0.1".to_string(), 0)
+}
+
+/// Fila com os nós das chaves, nesta ordem
+pub fn fila<C: Borrow<ParChaves>>(chaves: &[C]) -> FilaOrdenada {
+    FilaOrdenada {
+        nos: chaves.iter().map(|c| no(c.borrow())).collect(),
+        seed_global: vec![0; 32],
+        timestamp: chrono::Utc::now(),
+    }
+}
+
+/// Certificado da fila das chaves na rodada, ainda sem assinaturas
+pub fn certificado(rodada: u64, chaves: &[&ParChaves]) -> CertificadoFila {
+    let mut fila = fila(chaves);
+    fila.seed_global = vec![rodada as u8; 32];
+    let folhas: Vec<&[u8]> = fila.nos.iter().map(|no| no.id.como_bytes()).collect();
+    CertificadoFila::novo(rodada, &fila, raiz_merkle(&folhas))
+}
+
+pub fn certificado_assinado(rodada: u64, fila: &[&ParChaves], assinantes: &[&ParChaves]) -> CertificadoFila {
+    let mut certificado = certificado(rodada, fila);
+    for chaves in assinantes {
+        certificado.assinar(chaves);
+    }
+    certificado
+}
+
+/// Evidência de salto com um voto de cada votante
+pub fn evidencia(rodada: u64, slot: u64, processador: NodeId, votantes: &[ParChaves]) -> EvidenciaSalto {
+    EvidenciaSalto {
+        rodada,
+        slot,
+        processador,
+        votos: votantes.iter().map(|c| VotoSalto::novo(c, rodada, slot, processador)).collect(),
+    }
+}
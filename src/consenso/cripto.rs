@@ -0,0 +1,271 @@
+use crate::consenso::tipos::NodeId;
+use anyhow::Result;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::rngs::OsRng;
+use std::sync::{Arc, RwLock};
+
+pub const TAMANHO_CHAVE_PUBLICA: usize = 32;
+pub const TAMANHO_CHAVE_PRIVADA: usize = 32;
+pub const TAMANHO_ASSINATURA: usize = 64;
+
+/// Par de chaves Ed25519 usado por um nó para assinar o que produz
+pub struct ParChaves {
+    chave_privada: SigningKey,
+}
+
+impl ParChaves {
+    pub fn gerar() -> Self {
+        Self {
+            chave_privada: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    pub fn de_bytes(chave_privada: &[u8]) -> Result<Self> {
+        let bytes: [u8; TAMANHO_CHAVE_PRIVADA] = chave_privada
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Chave privada deve ter {} bytes", TAMANHO_CHAVE_PRIVADA))?;
+
+        Ok(Self {
+            chave_privada: SigningKey::from_bytes(&bytes),
+        })
+    }
+
+    pub fn chave_publica(&self) -> Vec<u8> {
+        self.chave_privada.verifying_key().to_bytes().to_vec()
+    }
+    
+    /// Bytes da chave privada, usados apenas pelo keystore (cifragem e exportação)
+    pub fn exportar_chave_privada(&self) -> Vec<u8> {
+        self.chave_privada.to_bytes().to_vec()
+    }
+
+    pub fn no_id(&self) -> NodeId {
+        NodeId::da_chave_publica(&self.chave_publica())
+    }
+    
+    /// Escalar secreto do Ed25519 (o mesmo que gera a chave pública), usado pela VRF
+    pub(crate) fn escalar_secreto(&self) -> curve25519_dalek::Scalar {
+        self.chave_privada.to_scalar()
+    }
+    
+    /// Segunda metade do `SHA-512` da chave privada, que o Ed25519 (e a VRF) usam para derivar nonces
+    pub(crate) fn prefixo_nonce(&self) -> zeroize::Zeroizing<[u8; 32]> {
+        use sha2::{Digest, Sha512};
+        
+        let mut hash: [u8; 64] = Sha512::digest(self.chave_privada.to_bytes()).into();
+        let mut prefixo = zeroize::Zeroizing::new([0u8; 32]);
+        prefixo.copy_from_slice(&hash[32..]);
+        zeroize::Zeroize::zeroize(&mut hash);
+        prefixo
+    }
+    
+    pub fn assinar(&self, dados: &[u8]) -> Vec<u8> {
+        self.chave_privada.sign(dados).to_bytes().to_vec()
+    }
+}
+
+impl std::fmt::Debug for ParChaves {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Nunca expor a chave privada em logs
+        f.debug_struct("ParChaves")
+            .field("chave_publica", &hex::encode(self.chave_publica()))
+            .finish()
+    }
+}
+
+/// Handle compartilhado para as chaves do nó.
+///
+/// As camadas de consenso, processamento e comunicação recebem um clone deste
+/// handle; quando o keystore rotaciona a chave, todas passam a assinar com a nova.
+#[derive(Clone)]
+pub struct Assinador {
+    chaves: Arc<RwLock<Arc<ParChaves>>>,
+}
+
+impl Assinador {
+    pub fn new(chaves: ParChaves) -> Self {
+        Self {
+            chaves: Arc::new(RwLock::new(Arc::new(chaves))),
+        }
+    }
+    
+    /// Retrato das chaves atuais; use quando chave pública e assinatura precisam ser do mesmo par
+    pub fn chaves_atuais(&self) -> Arc<ParChaves> {
+        match self.chaves.read() {
+            Ok(chaves) => chaves.clone(),
+            Err(envenenado) => envenenado.into_inner().clone(),
+        }
+    }
+    
+    pub fn assinar(&self, dados: &[u8]) -> Vec<u8> {
+        self.chaves_atuais().assinar(dados)
+    }
+    
+    pub fn chave_publica(&self) -> Vec<u8> {
+        self.chaves_atuais().chave_publica()
+    }
+    
+    pub fn no_id(&self) -> NodeId {
+        self.chaves_atuais().no_id()
+    }
+    
+    pub(crate) fn substituir(&self, novas: ParChaves) {
+        let novas = Arc::new(novas);
+        match self.chaves.write() {
+            Ok(mut chaves) => *chaves = novas,
+            Err(envenenado) => *envenenado.into_inner() = novas,
+        }
+    }
+}
+
+impl std::fmt::Debug for Assinador {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Assinador")
+            .field("no_id", &self.no_id().to_string())
+            .finish()
+    }
+}
+
+pub fn verificar_assinatura(chave_publica: &[u8], dados: &[u8], assinatura: &[u8]) -> bool {
+    let Ok(bytes_chave) = <[u8; TAMANHO_CHAVE_PUBLICA]>::try_from(chave_publica) else {
+        return false;
+    };
+    let Ok(chave) = VerifyingKey::from_bytes(&bytes_chave) else {
+        return false;
+    };
+    let Ok(assinatura) = Signature::from_slice(assinatura) else {
+        return false;
+    };
+
+    // verify_strict rejeita chaves fracas e assinaturas maleáveis
+    chave.verify_strict(dados, &assinatura).is_ok()
+}
+
+/// Monta os bytes canônicos de uma estrutura antes de assiná-la ou de calcular seu hash.
+///
+/// Cada campo é prefixado pelo seu tamanho e o conjunto começa com uma tag de
+/// domínio, para que assinaturas de tipos diferentes nunca colidam. A tag leva a
+/// versão do formato (`_V1`); mudar a ordem ou o tipo de um campo exige nova tag.
+///
+/// Formato, sempre big-endian:
+/// - campo: tamanho `u32` seguido dos bytes
+/// - inteiro: `u64`
+/// - timestamp: milissegundos desde a época, `i64`
+/// - opcional: `0x00` se ausente, `0x01` seguido do campo se presente
+pub struct BytesCanonicos {
+    dados: Vec<u8>,
+}
+
+impl BytesCanonicos {
+    pub fn new(dominio: &str) -> Self {
+        Self { dados: Vec::new() }.campo(dominio.as_bytes())
+    }
+
+    pub fn campo(mut self, valor: &[u8]) -> Self {
+        self.dados.extend_from_slice(&(valor.len() as u32).to_be_bytes());
+        self.dados.extend_from_slice(valor);
+        self
+    }
+
+    pub fn inteiro(mut self, valor: u64) -> Self {
+        self.dados.extend_from_slice(&valor.to_be_bytes());
+        self
+    }
+
+    pub fn timestamp(mut self, valor: &chrono::DateTime<chrono::Utc>) -> Self {
+        self.dados.extend_from_slice(&valor.timestamp_millis().to_be_bytes());
+        self
+    }
+
+    pub fn opcional(mut self, valor: Option<&[u8]>) -> Self {
+        match valor {
+            Some(valor) => {
+                self.dados.push(1);
+                self.campo(valor)
+            }
+            None => {
+                self.dados.push(0);
+                self
+            }
+        }
+    }
+
+    pub fn finalizar(self) -> Vec<u8> {
+        self.dados
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::tipos::{calcular_hash, calcular_hash_cadeia, Transacao};
+
+    #[test]
+    fn assinatura_so_confere_com_a_chave_e_os_dados_assinados() {
+        let chaves = ParChaves::gerar();
+        let outra = ParChaves::gerar();
+        let assinatura = chaves.assinar(b"dados");
+
+        assert!(verificar_assinatura(&chaves.chave_publica(), b"dados", &assinatura));
+        assert!(!verificar_assinatura(&chaves.chave_publica(), b"dadoz", &assinatura));
+        assert!(!verificar_assinatura(&outra.chave_publica(), b"dados", &assinatura));
+
+        // O hash dos dados, como era antes, não passa por assinatura
+        assert!(!verificar_assinatura(&chaves.chave_publica(), b"dados", &calcular_hash(b"dados")));
+        assert!(!verificar_assinatura(&chaves.chave_publica()[..31], b"dados", &assinatura));
+
+        // A chave exportada volta a assinar pelo mesmo id
+        let recuperada = ParChaves::de_bytes(&chaves.exportar_chave_privada()).unwrap();
+        assert_eq!(recuperada.no_id(), chaves.no_id());
+        assert!(verificar_assinatura(&chaves.chave_publica(), b"outros", &recuperada.assinar(b"outros")));
+    }
+
+    // Vetores de referência: se algum mudar, o formato mudou e a tag de versão também precisa mudar
+
+    fn instante() -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()
+    }
+
+    #[test]
+    fn bytes_canonicos_vetor_de_referencia() {
+        let bytes = BytesCanonicos::new("NIMBOS_TESTE_V1")
+            .campo(b"abc")
+            .inteiro(7)
+            .timestamp(&instante())
+            .opcional(None)
+            .opcional(Some(&[1, 2]))
+            .finalizar();
+
+        assert_eq!(
+            hex::encode(bytes),
+            "0000000f4e494d424f535f54455354455f56310000000361626300000000000000070000018bcfe568000001000000020102"
+        );
+    }
+
+    #[test]
+    fn hash_transacao_vetor_de_referencia() {
+        let transacao = Transacao {
+            id: "tx1".to_string(),
+            dados: b"dados".to_vec(),
+            estado_anterior: b"a".to_vec(),
+            estado_final: b"b".to_vec(),
+            timestamp: instante(),
+            nonce: 3,
+            assinatura: vec![9; 4],
+            transferencia: None,
+        };
+
+        assert_eq!(
+            hex::encode(transacao.hash()),
+            "11684231fc4ca6c1f80b7f1416fb23cf91c4784c242e905db4e08817403cf0eb"
+        );
+    }
+
+    #[test]
+    fn hash_cadeia_vetor_de_referencia() {
+        assert_eq!(
+            hex::encode(calcular_hash_cadeia(&[1; 32], &[2; 32])),
+            "9dbc6446366c003ff97bd4dfe770a0b47c4026a43602e73fb1531e3fed282dd9"
+        );
+    }
+}
@@ -1,94 +1,142 @@
-use crate::consenso::tipos::*;
-use anyhow::Result;
-use std::collections::HashMap;
+use super::prova::{LadoIrmao, PassoProva};
+use crate::consenso::tipos::calcular_hash;
 
+/// Prefixo do hash de folha; impede que uma folha se passe por nó interno
+pub const PREFIXO_FOLHA: u8 = 0x00;
+/// Prefixo do hash de nó interno
+pub const PREFIXO_NO: u8 = 0x01;
+/// Raiz de uma árvore sem folhas
+pub const RAIZ_VAZIA: [u8; 32] = [0; 32];
+
+/// `H(0x00 || dados)`
+pub fn hash_folha(dados: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(dados.len() + 1);
+    bytes.push(PREFIXO_FOLHA);
+    bytes.extend_from_slice(dados);
+    calcular_hash(&bytes)
+}
+
+/// `H(0x01 || esquerda || direita)`
+pub fn hash_no(esquerda: &[u8], direita: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(esquerda.len() + direita.len() + 1);
+    bytes.push(PREFIXO_NO);
+    bytes.extend_from_slice(esquerda);
+    bytes.extend_from_slice(direita);
+    calcular_hash(&bytes)
+}
+
+/// Raiz sem guardar os níveis, para quem só precisa comparar com um cabeçalho
+pub fn raiz_merkle<T: AsRef<[u8]>>(folhas: &[T]) -> Vec<u8> {
+    ArvoreMerkle::construir(folhas).obter_root()
+}
+
+/// Árvore de Merkle binária usada pela fila do consenso e pelas transações dos blocos.
+///
+/// Folhas e nós internos têm prefixos diferentes (`PREFIXO_FOLHA`, `PREFIXO_NO`),
+/// o que fecha o ataque de segunda pré-imagem em que um nó interno é apresentado
+/// como folha. Num nível de tamanho ímpar o último nó sobe sem alteração para o
+/// nível seguinte; ele não é duplicado, então `[a, b, c]` e `[a, b, c, c]` têm
+/// raízes diferentes.
 #[derive(Debug, Clone)]
 pub struct ArvoreMerkle {
-    root: Vec<u8>,
-    folhas: Vec<Vec<u8>>,
+    /// `niveis[0]` são os hashes das folhas; o último nível tem só a raiz
     niveis: Vec<Vec<Vec<u8>>>,
-    mapa_nos: HashMap<String, usize>,
 }
 
 impl ArvoreMerkle {
-    pub fn construir(nos: &[No]) -> Result<Self> {
-        if nos.is_empty() {
-            return Err(anyhow::anyhow!("Lista de nós não pode estar vazia"));
-        }
-        
-        // Criar folhas (hash dos IDs dos nós)
-        let folhas: Vec<Vec<u8>> = nos
-            .iter()
-            .map(|no| calcular_hash(no.id.as_bytes()))
-            .collect();
-        
-        // Criar mapa de nós para posições
-        let mapa_nos: HashMap<String, usize> = nos
-            .iter()
-            .enumerate()
-            .map(|(i, no)| (no.id.clone(), i))
-            .collect();
-        
-        // Construir árvore
-        let mut niveis = vec![folhas.clone()];
-        let mut nivel_atual = folhas.clone();
-        
-        while nivel_atual.len() > 1 {
-            let mut proximo_nivel = Vec::new();
-            
-            for chunk in nivel_atual.chunks(2) {
-                let hash_combinado = if chunk.len() == 2 {
-                    let mut dados = Vec::new();
-                    dados.extend_from_slice(&chunk[0]);
-                    dados.extend_from_slice(&chunk[1]);
-                    calcular_hash(&dados)
-                } else {
-                    chunk[0].clone() // Nó ímpar, duplicar
-                };
-                
-                proximo_nivel.push(hash_combinado);
-            }
-            
-            niveis.push(proximo_nivel.clone());
-            nivel_atual = proximo_nivel;
+    /// Constrói a árvore a partir dos dados das folhas, na ordem dada
+    pub fn construir<T: AsRef<[u8]>>(folhas: &[T]) -> Self {
+        let folhas: Vec<Vec<u8>> = folhas.iter().map(|dados| hash_folha(dados.as_ref())).collect();
+        Self::de_hashes_folha(folhas)
+    }
+
+    /// Constrói a árvore a partir de folhas já com `hash_folha` aplicado
+    pub fn de_hashes_folha(folhas: Vec<Vec<u8>>) -> Self {
+        let mut niveis = vec![folhas];
+
+        while niveis.last().is_some_and(|nivel| nivel.len() > 1) {
+            let proximo_nivel = niveis
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|par| match par {
+                    [esquerda, direita] => hash_no(esquerda, direita),
+                    // Nó ímpar sobe sem alteração
+                    [sozinho] => sozinho.clone(),
+                    _ => unreachable!(),
+                })
+                .collect();
+
+            niveis.push(proximo_nivel);
         }
-        
-        let root = nivel_atual[0].clone();
-        
-        Ok(Self {
-            root,
-            folhas,
-            niveis,
-            mapa_nos,
-        })
+
+        Self { niveis }
     }
-    
+
     pub fn obter_root(&self) -> Vec<u8> {
-        self.root.clone()
+        self.niveis
+            .last()
+            .and_then(|nivel| nivel.first())
+            .cloned()
+            .unwrap_or_else(|| RAIZ_VAZIA.to_vec())
+    }
+
+    pub fn quantidade_folhas(&self) -> usize {
+        self.niveis[0].len()
     }
-    
-    pub fn obter_posicao_no(&self, no_id: &str) -> Option<usize> {
-        self.mapa_nos.get(no_id).copied()
+
+    pub fn hash_folha_em(&self, posicao: usize) -> Option<&[u8]> {
+        self.niveis[0].get(posicao).map(Vec::as_slice)
+    }
+
+    pub fn niveis(&self) -> &[Vec<Vec<u8>>] {
+        &self.niveis
     }
-    
-    pub fn obter_caminho_prova(&self, posicao: usize) -> Vec<Vec<u8>> {
+
+    /// Irmãos da folha, do nível mais baixo ao mais alto, com o lado de cada um.
+    ///
+    /// Níveis em que o nó subiu sozinho não geram passo.
+    pub fn obter_caminho_prova(&self, posicao: usize) -> Vec<PassoProva> {
         let mut caminho = Vec::new();
         let mut pos_atual = posicao;
-        
+
         for nivel in &self.niveis[..self.niveis.len() - 1] {
-            let pos_irmao = if pos_atual % 2 == 0 {
-                pos_atual + 1
-            } else {
-                pos_atual - 1
-            };
-            
-            if pos_irmao < nivel.len() {
-                caminho.push(nivel[pos_irmao].clone());
+            let pos_irmao = pos_atual ^ 1;
+
+            if let Some(hash) = nivel.get(pos_irmao) {
+                let lado = if pos_atual.is_multiple_of(2) { LadoIrmao::Direita } else { LadoIrmao::Esquerda };
+                caminho.push(PassoProva { lado, hash: hash.clone() });
             }
-            
+
             pos_atual /= 2;
         }
-        
+
         caminho
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    // Os mesmos vetores da tabela do README
+    const VETORES: [(&[&str], &str); 5] = [
+        (&[], "0000000000000000000000000000000000000000000000000000000000000000"),
+        (&["a"], "022a6979e6dab7aa5ae4c3e5e45f7e977112a7e63593820dbec1ec738a24f93c"),
+        (&["a", "b"], "b137985ff484fb600db93107c77b0365c80d78f5b429ded0fd97361d077999eb"),
+        (&["a", "b", "c"], "36642e73c2540ab121e3a6bf9545b0a24982cd830eb13d3cd19de3ce6c021ec1"),
+        (&["a", "b", "c", "d", "e"], "fe14a5426fbd70c0fa73f52342afed0da0bd23c4838662ccf6b88a3070ead97b"),
+    ];
+
+    #[test]
+    fn raizes_dos_vetores_de_referencia() {
+        for (folhas, raiz) in VETORES {
+            assert_eq!(hex::encode(raiz_merkle(folhas)), raiz, "folhas {:?}", folhas);
+        }
+    }
+
+    #[test]
+    fn no_impar_sobe_sem_ser_duplicado() {
+        assert_ne!(raiz_merkle(&["a", "b", "c"]), raiz_merkle(&["a", "b", "c", "c"]));
+    }
+}
@@ -0,0 +1,339 @@
+use super::arvore::{hash_no, PREFIXO_FOLHA, RAIZ_VAZIA};
+use crate::consenso::tipos::calcular_hash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Profundidade da árvore esparsa: um nível por bit do caminho (SHA-256 da chave)
+pub const PROFUNDIDADE_ESPARSA: usize = 256;
+
+type Caminho = [u8; 32];
+
+/// Caminho de uma chave na árvore
+pub fn caminho_chave(chave: &[u8]) -> Caminho {
+    let mut caminho = [0u8; 32];
+    caminho.copy_from_slice(&calcular_hash(chave));
+    caminho
+}
+
+/// `H(0x00 || caminho || H(valor))`; o caminho na folha impede mover um valor para outra chave
+fn hash_folha_esparsa(caminho: &Caminho, valor: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(65);
+    bytes.push(PREFIXO_FOLHA);
+    bytes.extend_from_slice(caminho);
+    bytes.extend_from_slice(&calcular_hash(valor));
+    calcular_hash(&bytes)
+}
+
+/// Subárvores vazias valem `RAIZ_VAZIA` em qualquer altura, então só os nós ocupados são guardados
+fn combinar(esquerda: &[u8], direita: &[u8]) -> Vec<u8> {
+    if esquerda == RAIZ_VAZIA && direita == RAIZ_VAZIA {
+        RAIZ_VAZIA.to_vec()
+    } else {
+        hash_no(esquerda, direita)
+    }
+}
+
+fn bit(caminho: &Caminho, indice: usize) -> bool {
+    caminho[indice / 8] & (0x80 >> (indice % 8)) != 0
+}
+
+/// Primeiros `profundidade` bits do caminho, com os demais zerados
+fn prefixo(caminho: &Caminho, profundidade: usize) -> Caminho {
+    let mut resultado = [0u8; 32];
+    let bytes_inteiros = profundidade / 8;
+    resultado[..bytes_inteiros].copy_from_slice(&caminho[..bytes_inteiros]);
+    if !profundidade.is_multiple_of(8) {
+        resultado[bytes_inteiros] = caminho[bytes_inteiros] & (0xFFu8 << (8 - profundidade % 8));
+    }
+    resultado
+}
+
+fn irmao(caminho: &Caminho, profundidade: usize) -> Caminho {
+    let mut resultado = prefixo(caminho, profundidade);
+    let indice = profundidade - 1;
+    resultado[indice / 8] ^= 0x80 >> (indice % 8);
+    resultado
+}
+
+/// Árvore de Merkle esparsa sobre chave/valor, usada para o estado das contas.
+///
+/// Cada chave ocupa a folha no caminho `H(chave)` de uma árvore de 256 níveis.
+/// Apenas nós não vazios são armazenados, e cada atualização recalcula só os
+/// 256 nós do caminho. Permite provar tanto que uma chave tem certo valor
+/// quanto que ela não existe.
+#[derive(Debug, Clone, Default)]
+pub struct ArvoreEstado {
+    valores: HashMap<Caminho, Vec<u8>>,
+    /// Nós não vazios por (profundidade, prefixo do caminho)
+    nos: HashMap<(usize, Caminho), Vec<u8>>,
+}
+
+impl ArvoreEstado {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inserir(&mut self, chave: &[u8], valor: Vec<u8>) {
+        let caminho = caminho_chave(chave);
+        let folha = hash_folha_esparsa(&caminho, &valor);
+        self.valores.insert(caminho, valor);
+        self.atualizar_caminho(&caminho, folha);
+    }
+
+    pub fn remover(&mut self, chave: &[u8]) -> Option<Vec<u8>> {
+        let caminho = caminho_chave(chave);
+        let valor = self.valores.remove(&caminho)?;
+        self.atualizar_caminho(&caminho, RAIZ_VAZIA.to_vec());
+        Some(valor)
+    }
+
+    pub fn obter(&self, chave: &[u8]) -> Option<&[u8]> {
+        self.valores.get(&caminho_chave(chave)).map(Vec::as_slice)
+    }
+
+    pub fn quantidade(&self) -> usize {
+        self.valores.len()
+    }
+
+    pub fn raiz(&self) -> Vec<u8> {
+        self.no(0, &[0u8; 32])
+    }
+
+    /// Prova de inclusão (chave presente) ou de não inclusão (chave ausente)
+    pub fn provar(&self, chave: &[u8]) -> ProvaEstado {
+        let caminho = caminho_chave(chave);
+        let mut ocupados = [0u8; 32];
+        let mut irmaos = Vec::new();
+
+        // Da raiz para a folha; o bit i do mapa diz se o irmão na profundidade i+1 não é vazio
+        for profundidade in 1..=PROFUNDIDADE_ESPARSA {
+            if let Some(hash) = self.nos.get(&(profundidade, irmao(&caminho, profundidade))) {
+                let indice = profundidade - 1;
+                ocupados[indice / 8] |= 0x80 >> (indice % 8);
+                irmaos.push(hash.clone());
+            }
+        }
+
+        ProvaEstado {
+            chave: chave.to_vec(),
+            valor: self.valores.get(&caminho).cloned(),
+            irmaos_ocupados: ocupados.to_vec(),
+            irmaos,
+        }
+    }
+
+    fn no(&self, profundidade: usize, prefixo: &Caminho) -> Vec<u8> {
+        self.nos
+            .get(&(profundidade, *prefixo))
+            .cloned()
+            .unwrap_or_else(|| RAIZ_VAZIA.to_vec())
+    }
+
+    fn atualizar_caminho(&mut self, caminho: &Caminho, folha: Vec<u8>) {
+        let mut hash_atual = folha;
+
+        for profundidade in (1..=PROFUNDIDADE_ESPARSA).rev() {
+            self.guardar(profundidade, prefixo(caminho, profundidade), hash_atual.clone());
+
+            let hash_irmao = self.no(profundidade, &irmao(caminho, profundidade));
+            hash_atual = if bit(caminho, profundidade - 1) {
+                combinar(&hash_irmao, &hash_atual)
+            } else {
+                combinar(&hash_atual, &hash_irmao)
+            };
+        }
+
+        self.guardar(0, [0u8; 32], hash_atual);
+    }
+
+    fn guardar(&mut self, profundidade: usize, prefixo: Caminho, hash: Vec<u8>) {
+        if hash == RAIZ_VAZIA {
+            self.nos.remove(&(profundidade, prefixo));
+        } else {
+            self.nos.insert((profundidade, prefixo), hash);
+        }
+    }
+}
+
+/// Prova sobre uma chave da `ArvoreEstado`, conferível só com a raiz.
+///
+/// Com `valor` preenchido prova que a chave tem esse valor; com `valor = None`
+/// prova que a chave não existe. Irmãos vazios não são enviados: o mapa
+/// `irmaos_ocupados` (256 bits, da raiz para a folha) diz quais vêm na lista.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvaEstado {
+    pub chave: Vec<u8>,
+    pub valor: Option<Vec<u8>>,
+    pub irmaos_ocupados: Vec<u8>,
+    /// Irmãos não vazios, da raiz para a folha
+    pub irmaos: Vec<Vec<u8>>,
+}
+
+impl ProvaEstado {
+    pub fn verificar(&self, raiz: &[u8]) -> bool {
+        if self.irmaos_ocupados.len() != 32 {
+            return false;
+        }
+
+        let ocupados: usize = self.irmaos_ocupados.iter().map(|b| b.count_ones() as usize).sum();
+        if ocupados != self.irmaos.len() {
+            return false;
+        }
+
+        // Irmão marcado como ocupado mas vazio daria uma segunda prova válida para a mesma chave
+        if self.irmaos.iter().any(|hash| *hash == RAIZ_VAZIA) {
+            return false;
+        }
+
+        let caminho = caminho_chave(&self.chave);
+        let mut hash_atual = match &self.valor {
+            Some(valor) => hash_folha_esparsa(&caminho, valor),
+            None => RAIZ_VAZIA.to_vec(),
+        };
+
+        // Sobe da folha para a raiz, consumindo os irmãos do fim da lista
+        let mut irmaos = self.irmaos.iter().rev();
+        for profundidade in (1..=PROFUNDIDADE_ESPARSA).rev() {
+            let indice = profundidade - 1;
+            let hash_irmao = if self.irmaos_ocupados[indice / 8] & (0x80 >> (indice % 8)) != 0 {
+                match irmaos.next() {
+                    Some(hash) => hash.clone(),
+                    None => return false,
+                }
+            } else {
+                RAIZ_VAZIA.to_vec()
+            };
+
+            hash_atual = if bit(&caminho, indice) {
+                combinar(&hash_irmao, &hash_atual)
+            } else {
+                combinar(&hash_atual, &hash_irmao)
+            };
+        }
+
+        hash_atual == raiz
+    }
+
+    /// A prova mostra que a chave não existe na árvore de raiz `raiz`
+    pub fn prova_ausencia(&self, raiz: &[u8]) -> bool {
+        self.valor.is_none() && self.verificar(raiz)
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    fn arvore(chaves: &[&str]) -> ArvoreEstado {
+        let mut arvore = ArvoreEstado::new();
+        for chave in chaves {
+            arvore.inserir(chave.as_bytes(), format!("valor de {}", chave).into_bytes());
+        }
+        arvore
+    }
+
+    #[test]
+    fn provas_de_inclusao_e_de_ausencia_conferem() {
+        let arvore = arvore(&["alice", "bob", "carol", "dave"]);
+        let raiz = arvore.raiz();
+
+        for chave in ["alice", "bob", "carol", "dave"] {
+            let prova = arvore.provar(chave.as_bytes());
+            assert_eq!(prova.valor.as_deref(), Some(format!("valor de {}", chave).as_bytes()));
+            assert!(prova.verificar(&raiz));
+            assert!(!prova.prova_ausencia(&raiz));
+
+            let mut outro_valor = prova.clone();
+            outro_valor.valor = Some(b"outro".to_vec());
+            assert!(!outro_valor.verificar(&raiz));
+
+            // A mesma prova não serve para dizer que a chave não existe
+            let mut como_ausencia = prova.clone();
+            como_ausencia.valor = None;
+            assert!(!como_ausencia.verificar(&raiz));
+        }
+
+        let ausente = arvore.provar(b"eve");
+        assert!(ausente.prova_ausencia(&raiz));
+
+        let mut outra_chave = arvore.provar(b"alice");
+        outra_chave.chave = b"eve".to_vec();
+        assert!(!outra_chave.verificar(&raiz));
+    }
+
+    #[test]
+    fn raiz_nao_depende_da_ordem_de_insercao() {
+        let chaves = ["a", "b", "c", "d", "e", "f"];
+        let raiz = arvore(&chaves).raiz();
+
+        let mut invertidas = chaves;
+        invertidas.reverse();
+        assert_eq!(arvore(&invertidas).raiz(), raiz);
+
+        // Sobrescrever um valor e voltar ao original também volta à mesma raiz
+        let mut alterada = arvore(&chaves);
+        alterada.inserir(b"c", b"temporario".to_vec());
+        assert_ne!(alterada.raiz(), raiz);
+        alterada.inserir(b"c", b"valor de c".to_vec());
+        assert_eq!(alterada.raiz(), raiz);
+    }
+
+    #[test]
+    fn remover_tudo_volta_a_raiz_vazia() {
+        let mut arvore = arvore(&["x", "y", "z"]);
+        let so_x = self::arvore(&["x"]).raiz();
+
+        assert_eq!(arvore.remover(b"y"), Some(b"valor de y".to_vec()));
+        assert_eq!(arvore.remover(b"y"), None);
+        assert_eq!(arvore.remover(b"z").map(|_| ()), Some(()));
+        assert_eq!(arvore.raiz(), so_x);
+
+        arvore.remover(b"x");
+        assert_eq!(arvore.quantidade(), 0);
+        assert_eq!(arvore.raiz(), RAIZ_VAZIA.to_vec());
+        assert!(arvore.nos.is_empty());
+        assert!(arvore.provar(b"x").prova_ausencia(&RAIZ_VAZIA));
+    }
+
+    #[test]
+    fn mapa_de_irmaos_malformado_e_recusado() {
+        let arvore = arvore(&["alice", "bob", "carol"]);
+        let raiz = arvore.raiz();
+        let prova = arvore.provar(b"alice");
+        assert!(prova.verificar(&raiz));
+
+        let mut curto = prova.clone();
+        curto.irmaos_ocupados.pop();
+        assert!(!curto.verificar(&raiz));
+
+        let mut longo = prova.clone();
+        longo.irmaos_ocupados.push(0);
+        assert!(!longo.verificar(&raiz));
+
+        let mut irmao_a_mais = prova.clone();
+        irmao_a_mais.irmaos.push(vec![1; 32]);
+        assert!(!irmao_a_mais.verificar(&raiz));
+
+        // Marcar como ocupado um irmão vazio, mandando o hash vazio no lugar
+        let ultimo_bit_livre = (0..PROFUNDIDADE_ESPARSA)
+            .rev()
+            .find(|&i| prova.irmaos_ocupados[i / 8] & (0x80 >> (i % 8)) == 0)
+            .unwrap();
+        let mut vazio_como_ocupado = prova.clone();
+        vazio_como_ocupado.irmaos_ocupados[ultimo_bit_livre / 8] |= 0x80 >> (ultimo_bit_livre % 8);
+        let posicao = (0..ultimo_bit_livre)
+            .filter(|&i| prova.irmaos_ocupados[i / 8] & (0x80 >> (i % 8)) != 0)
+            .count();
+        vazio_como_ocupado.irmaos.insert(posicao, RAIZ_VAZIA.to_vec());
+        assert!(!vazio_como_ocupado.verificar(&raiz));
+
+        // Bit de um irmão ocupado apagado
+        let primeiro_ocupado = (0..PROFUNDIDADE_ESPARSA)
+            .find(|&i| prova.irmaos_ocupados[i / 8] & (0x80 >> (i % 8)) != 0)
+            .unwrap();
+        let mut sem_bit = prova.clone();
+        sem_bit.irmaos_ocupados[primeiro_ocupado / 8] &= !(0x80 >> (primeiro_ocupado % 8));
+        sem_bit.irmaos.remove(0);
+        assert!(!sem_bit.verificar(&raiz));
+    }
+}
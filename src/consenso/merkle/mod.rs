@@ -1,16 +1,23 @@
 mod arvore;
+mod esparsa;
+mod multiprova;
 mod prova;
 
 use crate::consenso::tipos::*;
 use anyhow::Result;
+use std::collections::HashMap;
 use tokio::sync::RwLock;
 use tracing::info;
 
 pub use arvore::*;
+pub use esparsa::*;
+pub use multiprova::*;
 pub use prova::*;
 
 pub struct CamadaMerkle {
     arvore: RwLock<Option<ArvoreMerkle>>,
+    /// Posição de cada nó da fila entre as folhas
+    posicoes: RwLock<HashMap<NodeId, usize>>,
     gerador_prova: GeradorProva,
 }
 
@@ -18,34 +25,59 @@ impl CamadaMerkle {
     pub fn new() -> Self {
         Self {
             arvore: RwLock::new(None),
+            posicoes: RwLock::new(HashMap::new()),
             gerador_prova: GeradorProva::new(),
         }
     }
     
+    /// Folhas da fila: os ids dos nós, na ordem da fila
     pub async fn gerar_arvore(&self, fila: &FilaOrdenada) -> Result<Vec<u8>> {
         info!("Gerando Merkle Tree para fila ordenada");
         
-        let arvore = ArvoreMerkle::construir(&fila.nos)?;
+        if fila.nos.is_empty() {
+            return Err(anyhow::anyhow!("Lista de nós não pode estar vazia"));
+        }
+        
+        let folhas: Vec<&[u8]> = fila.nos.iter().map(|no| no.id.como_bytes()).collect();
+        let arvore = ArvoreMerkle::construir(&folhas);
         let merkle_root = arvore.obter_root();
         
+        *self.posicoes.write().await = fila.nos.iter().enumerate().map(|(i, no)| (no.id, i)).collect();
         *self.arvore.write().await = Some(arvore);
         
         Ok(merkle_root)
     }
     
-    pub async fn gerar_prova_inclusao(&self, no_id: &str) -> Result<Option<ProvaInclusao>> {
+    pub async fn gerar_prova_inclusao(&self, no_id: &NodeId) -> Result<Option<ProvaInclusao>> {
+        let Some(posicao) = self.posicoes.read().await.get(no_id).copied() else {
+            return Ok(None);
+        };
+        
         if let Some(arvore) = self.arvore.read().await.as_ref() {
-            self.gerador_prova.gerar_prova(arvore, no_id)
+            self.gerador_prova.gerar_prova(arvore, posicao)
         } else {
             Ok(None)
         }
     }
     
-    pub async fn verificar_prova(&self, prova: &ProvaInclusao, no_id: &str) -> Result<bool> {
+    /// Uma prova só para vários nós da fila; `None` se algum não estiver nela
+    pub async fn gerar_multiprova(&self, nos: &[NodeId]) -> Result<Option<MultiProva>> {
+        let posicoes = self.posicoes.read().await;
+        let Some(indices) = nos.iter().map(|no_id| posicoes.get(no_id).copied()).collect::<Option<Vec<_>>>() else {
+            return Ok(None);
+        };
+        
+        match self.arvore.read().await.as_ref() {
+            Some(arvore) => Ok(Some(MultiProva::gerar(arvore, &indices)?)),
+            None => Ok(None),
+        }
+    }
+    
+    pub async fn verificar_prova(&self, prova: &ProvaInclusao, no_id: &NodeId) -> Result<bool> {
         if let Some(arvore) = self.arvore.read().await.as_ref() {
-            self.gerador_prova.verificar_prova(arvore, prova, no_id)
+            self.gerador_prova.verificar_prova(arvore, prova, no_id.como_bytes())
         } else {
             Ok(false)
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,244 @@
+use super::arvore::{hash_folha, hash_no, ArvoreMerkle};
+use super::prova::{verificar_hash_folha, ProvaInclusao};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Prova de inclusão de várias folhas da mesma árvore de uma só vez.
+///
+/// Irmãos que seriam repetidos em provas individuais aparecem uma vez só, e
+/// nós que o verificador consegue calcular a partir das próprias folhas não
+/// aparecem. A forma da árvore sai de `quantidade_folhas`, pela mesma regra de
+/// nó ímpar de `ArvoreMerkle`. A raiz não compromete essa quantidade: uma
+/// quantidade errada só é recusada quando muda o caminho das folhas provadas.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiProva {
+    pub quantidade_folhas: u64,
+    /// Índices provados, em ordem crescente e sem repetição
+    pub indices: Vec<u64>,
+    /// Irmãos na ordem em que a verificação os consome (nível a nível, da esquerda para a direita)
+    pub irmaos: Vec<Vec<u8>>,
+}
+
+impl MultiProva {
+    pub fn gerar(arvore: &ArvoreMerkle, posicoes: &[usize]) -> Result<Self> {
+        let mut indices: Vec<usize> = posicoes.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+
+        if indices.is_empty() {
+            return Err(anyhow::anyhow!("Multi-prova precisa de ao menos uma folha"));
+        }
+        if indices.last().copied().unwrap_or(0) >= arvore.quantidade_folhas() {
+            return Err(anyhow::anyhow!("Índice fora da árvore de {} folhas", arvore.quantidade_folhas()));
+        }
+
+        let mut irmaos = Vec::new();
+        let mut conhecidos = indices.clone();
+
+        for nivel in &arvore.niveis()[..arvore.niveis().len() - 1] {
+            let mut i = 0;
+            while i < conhecidos.len() {
+                let posicao = conhecidos[i];
+                let irmao = posicao ^ 1;
+
+                if posicao.is_multiple_of(2) && conhecidos.get(i + 1) == Some(&irmao) {
+                    // O par inteiro é conhecido: nada a enviar
+                    i += 2;
+                    continue;
+                }
+                if irmao < nivel.len() {
+                    irmaos.push(nivel[irmao].clone());
+                }
+                i += 1;
+            }
+
+            conhecidos = Self::pais(&conhecidos);
+        }
+
+        Ok(Self {
+            quantidade_folhas: arvore.quantidade_folhas() as u64,
+            indices: indices.into_iter().map(|i| i as u64).collect(),
+            irmaos,
+        })
+    }
+
+    /// Raiz a partir dos hashes de folha, na ordem de `indices`; `None` se a prova não fecha
+    pub fn calcular_raiz(&self, hashes_folha: &[Vec<u8>]) -> Option<Vec<u8>> {
+        if hashes_folha.len() != self.indices.len()
+            || self.indices.is_empty()
+            || self.indices.windows(2).any(|par| par[0] >= par[1])
+            || *self.indices.last()? >= self.quantidade_folhas
+        {
+            return None;
+        }
+
+        let mut nivel: Vec<(u64, Vec<u8>)> = self.indices.iter().copied().zip(hashes_folha.iter().cloned()).collect();
+        let mut largura = self.quantidade_folhas;
+        let mut irmaos = self.irmaos.iter();
+
+        while largura > 1 {
+            let mut proximo = Vec::with_capacity(nivel.len());
+            let mut i = 0;
+
+            while i < nivel.len() {
+                let (posicao, hash) = &nivel[i];
+                let irmao = posicao ^ 1;
+
+                let pai = if posicao.is_multiple_of(2) && nivel.get(i + 1).map(|(p, _)| *p) == Some(irmao) {
+                    i += 1;
+                    hash_no(hash, &nivel[i].1)
+                } else if irmao >= largura {
+                    // Nó ímpar sobe sem alteração
+                    hash.clone()
+                } else if posicao.is_multiple_of(2) {
+                    hash_no(hash, irmaos.next()?)
+                } else {
+                    hash_no(irmaos.next()?, hash)
+                };
+
+                proximo.push((posicao / 2, pai));
+                i += 1;
+            }
+
+            nivel = proximo;
+            largura = largura.div_ceil(2);
+        }
+
+        // Irmãos sobrando tornariam a prova ambígua
+        if irmaos.next().is_some() {
+            return None;
+        }
+
+        nivel.into_iter().next().map(|(_, hash)| hash)
+    }
+
+    fn pais(posicoes: &[usize]) -> Vec<usize> {
+        let mut pais: Vec<usize> = posicoes.iter().map(|p| p / 2).collect();
+        pais.dedup();
+        pais
+    }
+}
+
+/// Confere uma multi-prova com os dados das folhas, na ordem de `prova.indices`
+pub fn verificar_multipla<T: AsRef<[u8]>>(root: &[u8], dados_folhas: &[T], prova: &MultiProva) -> bool {
+    let hashes: Vec<Vec<u8>> = dados_folhas.iter().map(|dados| hash_folha(dados.as_ref())).collect();
+    verificar_multipla_hash_folha(root, &hashes, prova)
+}
+
+pub fn verificar_multipla_hash_folha(root: &[u8], hashes_folha: &[Vec<u8>], prova: &MultiProva) -> bool {
+    prova.calcular_raiz(hashes_folha).is_some_and(|raiz| raiz == root)
+}
+
+/// Verifica em lote provas individuais contra a mesma raiz; falha se qualquer uma falhar
+pub fn verificar_lote(root: &[u8], itens: &[(Vec<u8>, ProvaInclusao)]) -> bool {
+    itens
+        .iter()
+        .all(|(hash_folha, prova)| verificar_hash_folha(root, hash_folha, prova))
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    fn folhas(quantidade: usize) -> Vec<Vec<u8>> {
+        (0..quantidade).map(|i| format!("folha {}", i).into_bytes()).collect()
+    }
+
+    fn subconjunto(folhas: &[Vec<u8>], mascara: u32) -> (Vec<usize>, Vec<Vec<u8>>) {
+        (0..folhas.len())
+            .filter(|i| mascara & (1 << i) != 0)
+            .map(|i| (i, folhas[i].clone()))
+            .unzip()
+    }
+
+    #[test]
+    fn todo_subconjunto_de_arvores_pequenas_confere() {
+        // De 1 a 9 folhas cobre nós ímpares subindo em um e em vários níveis
+        for quantidade in 1..=9 {
+            let folhas = folhas(quantidade);
+            let arvore = ArvoreMerkle::construir(&folhas);
+            let root = arvore.obter_root();
+
+            for mascara in 1..(1u32 << quantidade) {
+                let (indices, dados) = subconjunto(&folhas, mascara);
+                let prova = MultiProva::gerar(&arvore, &indices).unwrap();
+
+                assert!(verificar_multipla(&root, &dados, &prova), "{} folhas, máscara {:b}", quantidade, mascara);
+
+                let mut trocados = dados.clone();
+                trocados[0] = b"outra folha".to_vec();
+                assert!(!verificar_multipla(&root, &trocados, &prova));
+            }
+        }
+    }
+
+    #[test]
+    fn irmaos_compartilhados_aparecem_uma_vez() {
+        let arvore = ArvoreMerkle::construir(&folhas(8));
+
+        // Par completo e vizinho: só falta a metade direita da árvore
+        assert_eq!(MultiProva::gerar(&arvore, &[0, 1, 2, 3]).unwrap().irmaos.len(), 1);
+        assert_eq!(MultiProva::gerar(&arvore, &(0..8).collect::<Vec<_>>()).unwrap().irmaos.len(), 0);
+        assert_eq!(MultiProva::gerar(&arvore, &[0, 2]).unwrap().irmaos.len(), 3);
+    }
+
+    #[test]
+    fn quantidade_de_folhas_que_muda_a_forma_e_recusada() {
+        for quantidade in 2..=9 {
+            let folhas = folhas(quantidade);
+            let arvore = ArvoreMerkle::construir(&folhas);
+            let root = arvore.obter_root();
+
+            for mascara in 1..(1u32 << quantidade) {
+                let (indices, dados) = subconjunto(&folhas, mascara);
+                let prova = MultiProva::gerar(&arvore, &indices).unwrap();
+
+                for outra in [quantidade - 1, quantidade + 1] {
+                    let mut alterada = prova.clone();
+                    alterada.quantidade_folhas = outra as u64;
+
+                    // A raiz não compromete a quantidade; ela só é recusada quando
+                    // muda a forma do caminho (índice fora ou outro número de irmãos)
+                    let forma_muda = indices.iter().any(|&i| i >= outra)
+                        || MultiProva::gerar(&ArvoreMerkle::construir(&self::folhas(outra)), &indices)
+                            .unwrap()
+                            .irmaos
+                            .len()
+                            != prova.irmaos.len();
+
+                    if forma_muda {
+                        assert!(!verificar_multipla(&root, &dados, &alterada), "{} em vez de {} folhas", outra, quantidade);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn indices_ou_irmaos_malformados_sao_recusados() {
+        let folhas = folhas(6);
+        let arvore = ArvoreMerkle::construir(&folhas);
+        let root = arvore.obter_root();
+        let dados = vec![folhas[1].clone(), folhas[4].clone()];
+        let prova = MultiProva::gerar(&arvore, &[4, 1, 4]).unwrap();
+
+        assert_eq!(prova.indices, vec![1, 4]);
+        assert!(verificar_multipla(&root, &dados, &prova));
+
+        let mut fora_de_ordem = prova.clone();
+        fora_de_ordem.indices.reverse();
+        assert!(!verificar_multipla(&root, &[&folhas[4], &folhas[1]], &fora_de_ordem));
+
+        let mut irmao_a_mais = prova.clone();
+        irmao_a_mais.irmaos.push(vec![0; 32]);
+        assert!(!verificar_multipla(&root, &dados, &irmao_a_mais));
+
+        let mut irmao_a_menos = prova.clone();
+        irmao_a_menos.irmaos.pop();
+        assert!(!verificar_multipla(&root, &dados, &irmao_a_menos));
+
+        assert!(!verificar_multipla(&root, &dados[..1], &prova));
+        assert!(MultiProva::gerar(&arvore, &[]).is_err());
+        assert!(MultiProva::gerar(&arvore, &[6]).is_err());
+    }
+}
@@ -1,6 +1,113 @@
-use crate::consenso::tipos::*;
-use super::arvore::ArvoreMerkle;
+use super::arvore::{hash_folha, hash_no, ArvoreMerkle};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Tamanho dos hashes da árvore (SHA-256)
+pub const TAMANHO_HASH_MERKLE: usize = 32;
+
+/// De que lado do nó atual fica o irmão em um passo da prova
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LadoIrmao {
+    Esquerda,
+    Direita,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PassoProva {
+    pub lado: LadoIrmao,
+    pub hash: Vec<u8>,
+}
+
+/// Prova de inclusão de uma folha, verificável só com a raiz.
+///
+/// Cada passo diz explicitamente o lado do irmão, então a verificação não
+/// depende da posição nem do tamanho dos níveis (que muda quando um nó ímpar
+/// sobe sem par).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvaInclusao {
+    /// Índice da folha; informativo, a verificação usa só os passos
+    pub posicao: u64,
+    pub caminho: Vec<PassoProva>,
+}
+
+impl ProvaInclusao {
+    /// Raiz obtida subindo da folha (já com `hash_folha`) pelos passos da prova
+    pub fn calcular_raiz(&self, hash_folha: &[u8]) -> Vec<u8> {
+        self.caminho.iter().fold(hash_folha.to_vec(), |hash_atual, passo| match passo.lado {
+            LadoIrmao::Esquerda => hash_no(&passo.hash, &hash_atual),
+            LadoIrmao::Direita => hash_no(&hash_atual, &passo.hash),
+        })
+    }
+
+    /// Formato binário compacto:
+    /// `posicao (u64 BE) || n (u8) || lados (ceil(n/8) bytes, bit i = 1 se o irmão i está à direita) || n hashes de 32 bytes`
+    pub fn codificar(&self) -> Result<Vec<u8>> {
+        let quantidade = u8::try_from(self.caminho.len())
+            .map_err(|_| anyhow::anyhow!("Prova com {} passos excede o formato", self.caminho.len()))?;
+
+        let mut lados = vec![0u8; self.caminho.len().div_ceil(8)];
+        let mut bytes = Vec::with_capacity(9 + lados.len() + self.caminho.len() * TAMANHO_HASH_MERKLE);
+        bytes.extend_from_slice(&self.posicao.to_be_bytes());
+        bytes.push(quantidade);
+
+        for (i, passo) in self.caminho.iter().enumerate() {
+            if passo.hash.len() != TAMANHO_HASH_MERKLE {
+                return Err(anyhow::anyhow!("Hash do passo {} com {} bytes", i, passo.hash.len()));
+            }
+            if passo.lado == LadoIrmao::Direita {
+                lados[i / 8] |= 1 << (i % 8);
+            }
+        }
+
+        bytes.extend_from_slice(&lados);
+        for passo in &self.caminho {
+            bytes.extend_from_slice(&passo.hash);
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn decodificar(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 9 {
+            return Err(anyhow::anyhow!("Prova truncada"));
+        }
+
+        let posicao = u64::from_be_bytes(bytes[..8].try_into()?);
+        let quantidade = bytes[8] as usize;
+        let tamanho_lados = quantidade.div_ceil(8);
+
+        if bytes.len() != 9 + tamanho_lados + quantidade * TAMANHO_HASH_MERKLE {
+            return Err(anyhow::anyhow!("Tamanho da prova não confere com {} passos", quantidade));
+        }
+
+        let lados = &bytes[9..9 + tamanho_lados];
+        // Bits além do último passo precisam ser zero, para a codificação ser única
+        if !quantidade.is_multiple_of(8) && lados[tamanho_lados - 1] >> (quantidade % 8) != 0 {
+            return Err(anyhow::anyhow!("Bits de lado não usados devem ser zero"));
+        }
+
+        let caminho = bytes[9 + tamanho_lados..]
+            .chunks(TAMANHO_HASH_MERKLE)
+            .enumerate()
+            .map(|(i, hash)| PassoProva {
+                lado: if lados[i / 8] & (1 << (i % 8)) != 0 { LadoIrmao::Direita } else { LadoIrmao::Esquerda },
+                hash: hash.to_vec(),
+            })
+            .collect();
+
+        Ok(Self { posicao, caminho })
+    }
+}
+
+/// Confere que `dados_folha` está na árvore de raiz `root`, sem precisar da árvore
+pub fn verificar(root: &[u8], dados_folha: &[u8], prova: &ProvaInclusao) -> bool {
+    verificar_hash_folha(root, &hash_folha(dados_folha), prova)
+}
+
+/// Como `verificar`, para quem já tem o hash da folha
+pub fn verificar_hash_folha(root: &[u8], hash_folha: &[u8], prova: &ProvaInclusao) -> bool {
+    prova.calcular_raiz(hash_folha) == root
+}
 
 pub struct GeradorProva;
 
@@ -8,48 +115,82 @@ impl GeradorProva {
     pub fn new() -> Self {
         Self
     }
-    
-    pub fn gerar_prova(&self, arvore: &ArvoreMerkle, no_id: &str) -> Result<Option<ProvaInclusao>> {
-        if let Some(posicao) = arvore.obter_posicao_no(no_id) {
-            let merkle_proof = arvore.obter_caminho_prova(posicao);
-            let merkle_root = arvore.obter_root();
-            
-            Ok(Some(ProvaInclusao {
-                merkle_proof,
-                posicao,
-                merkle_root,
-            }))
-        } else {
-            Ok(None)
-        }
-    }
-    
-    pub fn verificar_prova(&self, arvore: &ArvoreMerkle, prova: &ProvaInclusao, no_id: &str) -> Result<bool> {
-        // Calcular hash da folha
-        let hash_folha = calcular_hash(no_id.as_bytes());
-        
-        // Reconstruir caminho até a raiz
-        let mut hash_atual = hash_folha;
-        let mut posicao_atual = prova.posicao;
-        
-        for hash_irmao in &prova.merkle_proof {
-            let mut dados = Vec::new();
-            
-            if posicao_atual % 2 == 0 {
-                // Nó à esquerda
-                dados.extend_from_slice(&hash_atual);
-                dados.extend_from_slice(hash_irmao);
-            } else {
-                // Nó à direita
-                dados.extend_from_slice(hash_irmao);
-                dados.extend_from_slice(&hash_atual);
+
+    pub fn gerar_prova(&self, arvore: &ArvoreMerkle, posicao: usize) -> Result<Option<ProvaInclusao>> {
+        if posicao >= arvore.quantidade_folhas() {
+            return Ok(None);
+        }
+
+        Ok(Some(ProvaInclusao {
+            posicao: posicao as u64,
+            caminho: arvore.obter_caminho_prova(posicao),
+        }))
+    }
+
+    pub fn verificar_prova(&self, arvore: &ArvoreMerkle, prova: &ProvaInclusao, dados_folha: &[u8]) -> Result<bool> {
+        Ok(verificar(&arvore.obter_root(), dados_folha, prova))
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    fn folhas(quantidade: usize) -> Vec<Vec<u8>> {
+        (0..quantidade).map(|i| format!("folha {}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn codificar_e_decodificar_preservam_a_prova() {
+        let gerador = GeradorProva::new();
+
+        for quantidade in 1..=20 {
+            let folhas = folhas(quantidade);
+            let arvore = ArvoreMerkle::construir(&folhas);
+
+            for (posicao, folha) in folhas.iter().enumerate() {
+                let prova = gerador.gerar_prova(&arvore, posicao).unwrap().unwrap();
+                let decodificada = ProvaInclusao::decodificar(&prova.codificar().unwrap()).unwrap();
+
+                assert_eq!(decodificada, prova);
+                assert!(verificar(&arvore.obter_root(), folha, &decodificada));
+                assert!(!verificar(&arvore.obter_root(), b"outra folha", &decodificada));
             }
-            
-            hash_atual = calcular_hash(&dados);
-            posicao_atual /= 2;
+
+            assert!(gerador.gerar_prova(&arvore, quantidade).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn entrada_truncada_ou_com_sobra_e_recusada() {
+        let arvore = ArvoreMerkle::construir(&folhas(11));
+        let bytes = GeradorProva::new().gerar_prova(&arvore, 6).unwrap().unwrap().codificar().unwrap();
+
+        for tamanho in 0..bytes.len() {
+            assert!(ProvaInclusao::decodificar(&bytes[..tamanho]).is_err(), "{} bytes", tamanho);
         }
-        
-        // Verificar se chegamos à raiz correta
-        Ok(hash_atual == prova.merkle_root && prova.merkle_root == arvore.obter_root())
+
+        let mut com_sobra = bytes.clone();
+        com_sobra.push(0);
+        assert!(ProvaInclusao::decodificar(&com_sobra).is_err());
+    }
+
+    #[test]
+    fn bits_de_lado_nao_usados_precisam_ser_zero() {
+        let arvore = ArvoreMerkle::construir(&folhas(4));
+        let mut bytes = GeradorProva::new().gerar_prova(&arvore, 0).unwrap().unwrap().codificar().unwrap();
+
+        // Dois passos: só os dois bits mais baixos do byte de lados valem
+        bytes[9] |= 1 << 5;
+        assert!(ProvaInclusao::decodificar(&bytes).is_err());
+    }
+
+    #[test]
+    fn hash_de_tamanho_errado_nao_codifica() {
+        let prova = ProvaInclusao {
+            posicao: 0,
+            caminho: vec![PassoProva { lado: LadoIrmao::Direita, hash: vec![0; 31] }],
+        };
+        assert!(prova.codificar().is_err());
     }
-}
\ No newline at end of file
+}
@@ -5,60 +5,401 @@ pub mod merkle;
 pub mod processamento;
 pub mod validacao;
 pub mod tipos;
+pub mod cripto;
+pub mod rodada;
+#[cfg(test)]
+pub(crate) mod apoio_testes;
 
 use anyhow::Result;
+use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::info;
+use tracing::{info, warn};
 
 pub use tipos::*;
+pub use cripto::*;
+pub use rodada::*;
+
+/// Intervalo entre as verificações do slot enquanto se espera o bloco do processador da vez
+const INTERVALO_VERIFICACAO_SLOT: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Mensagens que o consenso precisa enviar aos outros nós
+#[derive(Debug, Clone)]
+pub enum SaidaConsenso {
+    Commitment(Commitment),
+    Reveal(Reveal),
+    BilheteVrf(ordenacao::BilheteVrf),
+    CertificadoFila(ordenacao::CertificadoFila),
+    /// Conteúdo do bloco deste nó, a ser montado, assinado e anexado pela blockchain
+    Bloco(PropostaBloco),
+    /// Voto para pular o processador que perdeu o prazo do slot
+    VotoSalto(processamento::VotoSalto),
+}
+
+/// O que o processador da vez põe no bloco: transações executadas e a prova da rodada
+#[derive(Debug, Clone)]
+pub struct PropostaBloco {
+    pub transacoes: Vec<Transacao>,
+    pub derivacao_seed: Option<ordenacao::DerivacaoSeed>,
+    pub certificado_fila: Option<ordenacao::CertificadoFila>,
+    pub raiz_estado: Vec<u8>,
+    /// Processadores pulados nesta rodada antes da vez deste nó
+    pub saltos: Vec<processamento::EvidenciaSalto>,
+}
+
+/// Camadas do consenso que recebem o que chega da rede
+#[derive(Clone)]
+pub struct EntradaConsenso {
+    pub registro: Arc<registro::CamadaRegistro>,
+    pub reveal: Arc<reveal::CamadaReveal>,
+    pub ordenacao: Arc<ordenacao::CamadaOrdenacao>,
+    pub processamento: Arc<processamento::CamadaProcessamento>,
+}
 
 pub struct SistemaConsenso {
-    registro: registro::CamadaRegistro,
-    reveal: reveal::CamadaReveal,
-    ordenacao: ordenacao::CamadaOrdenacao,
+    rodada: Arc<MaquinaRodada>,
+    registro: Arc<registro::CamadaRegistro>,
+    reveal: Arc<reveal::CamadaReveal>,
+    ordenacao: Arc<ordenacao::CamadaOrdenacao>,
     merkle: merkle::CamadaMerkle,
-    processamento: processamento::CamadaProcessamento,
+    processamento: Arc<processamento::CamadaProcessamento>,
     validacao: validacao::CamadaValidacao,
+    assinador: Assinador,
+    saida: mpsc::UnboundedSender<SaidaConsenso>,
 }
 
 impl SistemaConsenso {
-    pub async fn new() -> Result<Self> {
-        info!("Inicializando Sistema de Consenso");
+    pub async fn new(assinador: Assinador, saida: mpsc::UnboundedSender<SaidaConsenso>) -> Result<Self> {
+        Self::new_com_configuracao(assinador, saida, ConfiguracaoRodada::default()).await
+    }
+    
+    pub async fn new_com_configuracao(
+        assinador: Assinador,
+        saida: mpsc::UnboundedSender<SaidaConsenso>,
+        configuracao: ConfiguracaoRodada,
+    ) -> Result<Self> {
+        Self::new_com_maquina(assinador, saida, configuracao, Box::new(processamento::MaquinaTransferencias::new())).await
+    }
+    
+    /// Consenso sobre outra lógica de aplicação; processamento e validação usam a mesma máquina
+    pub async fn new_com_maquina(
+        assinador: Assinador,
+        saida: mpsc::UnboundedSender<SaidaConsenso>,
+        configuracao: ConfiguracaoRodada,
+        maquina: Box<dyn processamento::MaquinaEstado>,
+    ) -> Result<Self> {
+        info!("Inicializando Sistema de Consenso (máquina de estado {})", maquina.nome());
+        
+        let rodada = Arc::new(MaquinaRodada::new_com_configuracao(configuracao));
+        let detector_maliciosos = Arc::new(validacao::DetectorMaliciosos::new());
+        let processamento = Arc::new(processamento::CamadaProcessamento::new_com_maquina(
+            assinador.clone(),
+            detector_maliciosos.clone(),
+            maquina,
+        ));
         
         Ok(Self {
-            registro: registro::CamadaRegistro::new(),
-            reveal: reveal::CamadaReveal::new(),
-            ordenacao: ordenacao::CamadaOrdenacao::new(),
+            registro: Arc::new(registro::CamadaRegistro::new(rodada.clone())),
+            reveal: Arc::new(reveal::CamadaReveal::new(rodada.clone(), detector_maliciosos.clone())),
+            ordenacao: Arc::new(ordenacao::CamadaOrdenacao::new(rodada.clone())),
+            rodada,
             merkle: merkle::CamadaMerkle::new(),
-            processamento: processamento::CamadaProcessamento::new(),
-            validacao: validacao::CamadaValidacao::new(),
+            validacao: validacao::CamadaValidacao::new(assinador.clone(), detector_maliciosos, processamento.maquina_estado()),
+            processamento,
+            assinador,
+            saida,
         })
     }
     
+    /// Camadas compartilhadas com a comunicação, que entrega commitments, reveals,
+    /// bilhetes e transações recebidos
+    pub fn entrada(&self) -> EntradaConsenso {
+        EntradaConsenso {
+            registro: self.registro.clone(),
+            reveal: self.reveal.clone(),
+            ordenacao: self.ordenacao.clone(),
+            processamento: self.processamento.clone(),
+        }
+    }
+    
+    /// Máquina de rodadas, para consultar a fase atual ou se inscrever nas transições
+    pub fn rodada(&self) -> Arc<MaquinaRodada> {
+        self.rodada.clone()
+    }
+    
+    /// Derivação do seed da última rodada ordenada, para o cabeçalho do próximo bloco
+    pub async fn obter_derivacao_seed(&self) -> Option<ordenacao::DerivacaoSeed> {
+        self.ordenacao.obter_ultima_derivacao().await
+    }
+    
+    /// Certificado da fila de uma rodada, se ela chegou ao quorum
+    pub async fn obter_certificado_fila(&self, rodada: u64) -> Option<ordenacao::CertificadoFila> {
+        self.ordenacao.obter_certificado(rodada).await
+    }
+    
+    /// Raiz do estado das contas, para o cabeçalho do próximo bloco
+    pub async fn obter_raiz_estado(&self) -> Vec<u8> {
+        self.processamento.raiz_estado().await
+    }
+    
+    /// Recompensas que descontam os processadores pulados gravados nos blocos anexados
+    pub async fn definir_recompensas(&self, recompensas: Arc<crate::recompensas::CamadaRecompensas>) {
+        self.processamento.definir_recompensas(recompensas).await;
+    }
+    
+    /// Executa uma rodada completa, fase a fase, respeitando o prazo de cada uma
     pub async fn executar(&mut self) -> Result<()> {
-        info!("Executando ciclo de consenso");
+        let mut estado = self.rodada.iniciar_fase().await;
+        info!("Executando rodada {} do consenso", estado.numero);
         
-        // Implementar loop principal do consenso
         loop {
+            if let Err(e) = self.executar_fase(&estado).await {
+                // Uma fase com erro invalida o resto da rodada
+                self.finalizar_rodada().await;
+                self.rodada.encerrar_rodada().await;
+                return Err(e);
+            }
+            
+            Self::aguardar_prazo(&estado).await;
+            
+            if let Err(e) = self.concluir_fase(&estado).await {
+                self.finalizar_rodada().await;
+                self.rodada.encerrar_rodada().await;
+                return Err(e);
+            }
+            
+            let fase_encerrada = estado.fase;
+            estado = self.rodada.avancar().await;
+            
+            if fase_encerrada == FaseRodada::Finalizacao {
+                return Ok(());
+            }
+        }
+    }
+    
+    async fn executar_fase(&mut self, estado: &EstadoRodada) -> Result<()> {
+        match estado.fase {
             // 1. Fase de Registro
-            self.registro.processar_commitments().await?;
+            FaseRodada::Commit => {
+                self.ordenacao.fixar_stakes(self.processamento.stakes().await).await;
+                
+                let commitment = self.registro.iniciar_registro(&self.assinador).await?;
+                self.enviar(SaidaConsenso::Commitment(commitment));
+            }
             
             // 2. Fase de Reveal
-            self.reveal.processar_reveals().await?;
+            FaseRodada::Reveal => {
+                self.registro.processar_commitments().await?;
+                
+                if let Some(local) = self.registro.obter_commitment_local().await {
+                    let reveal = Reveal {
+                        chave_publica: local.chave_publica,
+                        nonce: local.nonce,
+                        no_id: local.commitment.no_id,
+                        timestamp: chrono::Utc::now(),
+                        rodada: estado.numero,
+                    };
+                    
+                    let commitments = self.registro.obter_commitments().await;
+                    self.reveal.adicionar_reveal(reveal.clone(), &commitments).await?;
+                    self.enviar(SaidaConsenso::Reveal(reveal));
+                }
+            }
             
-            // 3. Ordenação Determinística
-            let fila = self.ordenacao.gerar_fila().await?;
-            
-            // 4. Gerar Merkle Tree
-            let merkle_root = self.merkle.gerar_arvore(&fila).await?;
+            // 3. Ordenação Determinística e 4. Merkle Tree
+            FaseRodada::Ordenacao => {
+                let commitments = self.registro.obter_commitments().await;
+                self.reveal.processar_reveals(estado.numero, &commitments).await?;
+                
+                // Seed verificável: nonces revelados + seed anterior
+                let nonces = self.reveal.obter_reveals().await
+                    .into_values()
+                    .map(|reveal| reveal.nonce)
+                    .collect();
+                self.ordenacao.derivar_seed(estado.numero, nonces).await;
+                
+                // No modo VRF a fila só sai no fim da fase, depois de recebidos os bilhetes
+                if self.ordenacao.modo_atual() == ordenacao::ModoOrdenacao::Vrf {
+                    let bilhete = self.ordenacao.gerar_bilhete(&self.assinador.chaves_atuais()).await?;
+                    self.enviar(SaidaConsenso::BilheteVrf(bilhete));
+                } else {
+                    self.gerar_fila(estado).await?;
+                }
+            }
             
             // 5. Processamento Rotativo
-            self.processamento.processar_transacoes(&fila).await?;
+            FaseRodada::Processamento => {
+                self.processar_slots(estado).await?;
+            }
             
             // 6. Validação Distribuída
-            self.validacao.validar_consenso().await?;
+            FaseRodada::Validacao => {
+                match self.processamento.lote_confirmado().await {
+                    Some(lote) => {
+                        let validacoes = self.validacao.validar_consenso(&lote).await?;
+                        info!("{} transações do bloco da rodada validadas", validacoes.len());
+                    }
+                    None => info!("Nenhum bloco anexado na rodada {}; nada a validar", estado.numero),
+                }
+            }
             
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            FaseRodada::Finalizacao => {
+                self.finalizar_rodada().await;
+            }
         }
+        
+        Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Ações que dependem do que chegou durante a fase, executadas no fim do prazo
+    async fn concluir_fase(&mut self, estado: &EstadoRodada) -> Result<()> {
+        if estado.fase == FaseRodada::Ordenacao
+            && self.ordenacao.modo_atual() == ordenacao::ModoOrdenacao::Vrf
+        {
+            self.gerar_fila(estado).await?;
+        }
+        
+        Ok(())
+    }
+    
+    async fn gerar_fila(&mut self, estado: &EstadoRodada) -> Result<()> {
+        let nos_aprovados = self.reveal.obter_nos_aprovados().await;
+        let fila = self.ordenacao.gerar_fila(nos_aprovados).await?;
+        
+        if fila.nos.is_empty() {
+            warn!("Nenhum nó aprovado na rodada {}; processamento suspenso", estado.numero);
+        } else {
+            let merkle_root = self.merkle.gerar_arvore(&fila).await?;
+            info!("Merkle root da fila: {}", hex::encode(&merkle_root));
+            
+            let chaves = self.assinador.chaves_atuais();
+            if let Some(certificado) = self.ordenacao.certificar_fila(estado.numero, merkle_root, &chaves).await? {
+                self.enviar(SaidaConsenso::CertificadoFila(certificado));
+            }
+        }
+        
+        // A rotação de processadores segue a fila desta rodada
+        self.processamento.inicializar_rotacao(estado.numero, &fila).await;
+        
+        Ok(())
+    }
+    
+    /// Percorre os slots da fila até sair o bloco da rodada ou acabar o prazo da fase.
+    ///
+    /// Na vez deste nó, processa e propõe o bloco. Na vez de outro, espera
+    /// `duracao_slot`; se o slot não avançou, vota para pular o processador e
+    /// continua esperando o quorum (ou o bloco atrasado) até o prazo da fase.
+    /// Cada processador tem no máximo um slot por rodada.
+    async fn processar_slots(&mut self, estado: &EstadoRodada) -> Result<()> {
+        let duracao_slot = chrono::Duration::from_std(self.rodada.configuracao().duracao_slot)?;
+        let tamanho_fila = self.processamento.tamanho_fila().await as u64;
+        
+        while chrono::Utc::now() < estado.prazo && self.processamento.blocos_na_rodada().await == 0 {
+            let slot = self.processamento.slot_atual().await;
+            if slot >= tamanho_fila {
+                warn!("Nenhum processador da fila produziu bloco na rodada {}", estado.numero);
+                break;
+            }
+            
+            if let Some(lote) = self.processamento.processar_transacoes().await? {
+                let certificado_fila = self.ordenacao.obter_certificado(estado.numero).await;
+                
+                // Sem o certificado da fila os votos de salto não podem ser conferidos
+                let saltos = if certificado_fila.is_some() {
+                    self.processamento.retirar_evidencias_salto(estado.numero).await
+                } else {
+                    Vec::new()
+                };
+                
+                let proposta = PropostaBloco {
+                    transacoes: lote.transacoes,
+                    derivacao_seed: self.ordenacao.obter_ultima_derivacao().await,
+                    certificado_fila,
+                    raiz_estado: lote.raiz_estado,
+                    saltos,
+                };
+                self.enviar(SaidaConsenso::Bloco(proposta));
+                break;
+            }
+            
+            let prazo_slot = (chrono::Utc::now() + duracao_slot).min(estado.prazo);
+            while chrono::Utc::now() < prazo_slot && self.processamento.slot_atual().await == slot {
+                tokio::time::sleep(INTERVALO_VERIFICACAO_SLOT).await;
+            }
+            
+            if self.processamento.slot_atual().await == slot && chrono::Utc::now() < estado.prazo {
+                if let Some(voto) = self.processamento.votar_salto(&self.assinador.chaves_atuais()).await? {
+                    info!("Votando para pular o processador do slot {} da rodada {}", slot, estado.numero);
+                    self.enviar(SaidaConsenso::VotoSalto(voto));
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn finalizar_rodada(&mut self) {
+        self.registro.limpar_commitments().await;
+        self.reveal.limpar().await;
+        self.ordenacao.limpar_rodada().await;
+    }
+    
+    fn enviar(&self, saida: SaidaConsenso) {
+        if self.saida.send(saida).is_err() {
+            warn!("Canal de saída do consenso fechado; mensagem não enviada");
+        }
+    }
+    
+    async fn aguardar_prazo(estado: &EstadoRodada) {
+        if let Ok(restante) = (estado.prazo - chrono::Utc::now()).to_std() {
+            tokio::time::sleep(restante).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::registro::GeradorCommitment;
+
+    #[tokio::test]
+    async fn aprovados_no_reveal_formam_a_fila_e_a_rotacao() {
+        let (saida, _rx) = mpsc::unbounded_channel();
+        let mut sistema = SistemaConsenso::new(Assinador::new(ParChaves::gerar()), saida).await.unwrap();
+        let rodada = sistema.rodada.rodada_atual().await;
+
+        // Três nós fazem commitment; só dois revelam
+        let chaves = apoio_testes::chaves(3);
+        let nonces: Vec<Vec<u8>> = chaves.iter().map(|_| GeradorCommitment::gerar_nonce()).collect();
+        for (par, nonce) in chaves.iter().zip(&nonces) {
+            let commitment = GeradorCommitment::criar_commitment(ID_CADEIA_PADRAO, rodada, &par.chave_publica(), nonce, par.no_id());
+            assert!(sistema.registro.adicionar_commitment(commitment).await.unwrap());
+        }
+
+        sistema.rodada.avancar().await;
+        let commitments = sistema.registro.obter_commitments().await;
+        for (par, nonce) in chaves.iter().zip(&nonces).take(2) {
+            let reveal = Reveal {
+                chave_publica: par.chave_publica(),
+                nonce: nonce.clone(),
+                no_id: par.no_id(),
+                timestamp: chrono::Utc::now(),
+                rodada,
+            };
+            assert!(sistema.reveal.adicionar_reveal(reveal, &commitments).await.unwrap());
+        }
+
+        let estado = sistema.rodada.avancar().await;
+        sistema.reveal.processar_reveals(rodada, &commitments).await.unwrap();
+        sistema.gerar_fila(&estado).await.unwrap();
+
+        let fila = sistema.ordenacao.obter_fila_atual().await.unwrap();
+        let mut na_fila: Vec<(NodeId, Vec<u8>)> = fila.nos.iter().map(|no| (no.id, no.chave_publica.clone())).collect();
+        let mut revelados: Vec<(NodeId, Vec<u8>)> = chaves[..2].iter().map(|c| (c.no_id(), c.chave_publica())).collect();
+        na_fila.sort();
+        revelados.sort();
+        assert_eq!(na_fila, revelados);
+
+        assert_eq!(sistema.processamento.tamanho_fila().await, 2);
+        assert_eq!(sistema.processamento.slot_atual().await, 0);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::consenso::cripto::{verificar_assinatura, BytesCanonicos, ParChaves};
+use crate::consenso::merkle::raiz_merkle;
+use crate::consenso::tipos::{calcular_hash, FilaOrdenada, NodeId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssinaturaFila {
+    pub no_id: NodeId,
+    pub chave_publica: Vec<u8>,
+    pub assinatura: Vec<u8>,
+}
+
+/// Fila ordenada de uma rodada, com seed e merkle root, assinada pelos próprios nós da fila.
+///
+/// Vale quando mais de 2/3 dos nós listados assinaram o mesmo conteúdo. Isso
+/// só basta para quem calculou a mesma fila: quem confere um certificado de
+/// fora (a cadeia, um nó que entra depois) usa `sucede`, que também exige o
+/// quorum dos nós do último certificado aceito.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificadoFila {
+    pub rodada: u64,
+    /// Ids dos nós na ordem da fila
+    pub nos: Vec<NodeId>,
+    pub seed: Vec<u8>,
+    pub merkle_root: Vec<u8>,
+    pub assinaturas: Vec<AssinaturaFila>,
+}
+
+impl CertificadoFila {
+    pub fn novo(rodada: u64, fila: &FilaOrdenada, merkle_root: Vec<u8>) -> Self {
+        Self {
+            rodada,
+            nos: fila.nos.iter().map(|no| no.id).collect(),
+            seed: fila.seed_global.clone(),
+            merkle_root,
+            assinaturas: Vec::new(),
+        }
+    }
+
+    /// Bytes assinados por cada nó; não incluem as assinaturas
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        let mut bytes = BytesCanonicos::new("NIMBOS_CERTIFICADO_FILA_V1")
+            .inteiro(self.rodada)
+            .campo(&self.seed)
+            .campo(&self.merkle_root)
+            .inteiro(self.nos.len() as u64);
+
+        for no_id in &self.nos {
+            bytes = bytes.campo(no_id.como_bytes());
+        }
+
+        bytes.finalizar()
+    }
+
+    /// Codificação canônica com as assinaturas, ordenadas por nó para não depender
+    /// da ordem em que foram mescladas
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut assinaturas: Vec<&AssinaturaFila> = self.assinaturas.iter().collect();
+        assinaturas.sort_by_key(|a| a.no_id);
+
+        let mut bytes = BytesCanonicos::new("NIMBOS_CERTIFICADO_FILA_CODIFICADO_V1")
+            .campo(&self.bytes_para_assinatura())
+            .inteiro(assinaturas.len() as u64);
+
+        for assinatura in assinaturas {
+            bytes = bytes
+                .campo(assinatura.no_id.como_bytes())
+                .campo(&assinatura.chave_publica)
+                .campo(&assinatura.assinatura);
+        }
+
+        bytes.finalizar()
+    }
+
+    /// Identifica o conteúdo certificado; certificados com o mesmo id podem ter assinaturas mescladas
+    pub fn identificador(&self) -> Vec<u8> {
+        calcular_hash(&self.bytes_para_assinatura())
+    }
+
+    /// Acrescenta a assinatura do nó, se ele estiver na fila e ainda não tiver assinado
+    pub fn assinar(&mut self, chaves: &ParChaves) -> bool {
+        let no_id = chaves.no_id();
+        if !self.nos.contains(&no_id) || self.assinaturas.iter().any(|a| a.no_id == no_id) {
+            return false;
+        }
+
+        let assinatura = chaves.assinar(&self.bytes_para_assinatura());
+        self.assinaturas.push(AssinaturaFila {
+            no_id,
+            chave_publica: chaves.chave_publica(),
+            assinatura,
+        });
+        true
+    }
+
+    /// Incorpora as assinaturas válidas de outro certificado com o mesmo conteúdo.
+    ///
+    /// Retorna quantas assinaturas novas foram aproveitadas.
+    pub fn mesclar(&mut self, outro: &CertificadoFila) -> usize {
+        if self.identificador() != outro.identificador() {
+            return 0;
+        }
+
+        let bytes = self.bytes_para_assinatura();
+        let mut novas = 0;
+
+        for assinatura in &outro.assinaturas {
+            if self.assinaturas.iter().any(|a| a.no_id == assinatura.no_id) {
+                continue;
+            }
+
+            if self.assinatura_valida(assinatura, &bytes) {
+                self.assinaturas.push(assinatura.clone());
+                novas += 1;
+            }
+        }
+
+        novas
+    }
+
+    /// Assinaturas de nós distintos da fila que conferem com o conteúdo
+    pub fn assinaturas_validas(&self) -> usize {
+        self.assinaturas_de(&self.nos)
+    }
+
+    /// Assinaturas válidas de nós distintos que também estão em `conjunto`
+    pub fn assinaturas_de(&self, conjunto: &[NodeId]) -> usize {
+        let bytes = self.bytes_para_assinatura();
+        let mut vistos = HashSet::new();
+
+        self.assinaturas
+            .iter()
+            .filter(|assinatura| conjunto.contains(&assinatura.no_id))
+            .filter(|assinatura| self.assinatura_valida(assinatura, &bytes))
+            .filter(|assinatura| vistos.insert(assinatura.no_id))
+            .count()
+    }
+
+    /// Mais de 2/3 dos nós da fila
+    pub fn quorum(&self) -> usize {
+        quorum_de(self.nos.len())
+    }
+
+    pub fn tem_quorum(&self) -> bool {
+        !self.nos.is_empty() && self.assinaturas_validas() >= self.quorum()
+    }
+
+    /// Mais de 2/3 dos nós de `conjunto` assinaram este certificado
+    pub fn tem_quorum_de(&self, conjunto: &[NodeId]) -> bool {
+        !conjunto.is_empty() && self.assinaturas_de(conjunto) >= quorum_de(conjunto.len())
+    }
+
+    /// Fila sem nós repetidos e com `merkle_root` igual à raiz calculada sobre ela
+    pub fn fila_confere(&self) -> bool {
+        let mut vistos = HashSet::new();
+        if self.nos.is_empty() || !self.nos.iter().all(|no_id| vistos.insert(*no_id)) {
+            return false;
+        }
+
+        let folhas: Vec<&[u8]> = self.nos.iter().map(NodeId::como_bytes).collect();
+        raiz_merkle(&folhas) == self.merkle_root
+    }
+
+    /// Confere o certificado como sucessor de `anterior`, o último aceito na cadeia.
+    ///
+    /// Além da fila coerente e do quorum da própria fila, mais de 2/3 dos nós
+    /// certificados em `anterior` precisam ter assinado: uma fila inventada
+    /// não reúne essas assinaturas. Sem anterior (o primeiro certificado da
+    /// cadeia) vale só o quorum da própria fila.
+    pub fn sucede(&self, anterior: Option<&CertificadoFila>) -> bool {
+        if !self.fila_confere() || !self.tem_quorum() {
+            return false;
+        }
+
+        match anterior {
+            Some(anterior) => self.rodada > anterior.rodada && self.tem_quorum_de(&anterior.nos),
+            None => true,
+        }
+    }
+
+    fn assinatura_valida(&self, assinatura: &AssinaturaFila, bytes: &[u8]) -> bool {
+        self.nos.contains(&assinatura.no_id)
+            && assinatura.no_id.corresponde(&assinatura.chave_publica)
+            && verificar_assinatura(&assinatura.chave_publica, bytes, &assinatura.assinatura)
+    }
+}
+
+fn quorum_de(tamanho: usize) -> usize {
+    tamanho * 2 / 3 + 1
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::{certificado, certificado_assinado as assinado, chaves, referencias};
+
+    #[test]
+    fn fila_inventada_nao_sucede_a_fila_certificada() {
+        let chaves = chaves(4);
+        let todas = referencias(&chaves);
+        let anterior = assinado(1, &todas, &todas);
+        assert!(anterior.sucede(None));
+
+        // Um nó de fora monta uma fila só com ele: tem o quorum da própria fila, mas não o da anterior
+        let intruso = ParChaves::gerar();
+        let inventado = assinado(2, &[&intruso], &[&intruso]);
+        assert!(inventado.tem_quorum());
+        assert!(!inventado.sucede(Some(&anterior)));
+
+        // Nem com um nó da fila anterior: 1 de 4 não é quorum
+        let com_um = assinado(2, &[&intruso, todas[0]], &[&intruso, todas[0]]);
+        assert!(com_um.tem_quorum());
+        assert!(!com_um.sucede(Some(&anterior)));
+
+        // A fila seguinte com um nó novo, assinada por 3 dos 4 anteriores, sucede
+        let proxima = [todas[0], todas[1], todas[2], &intruso];
+        let sucessor = assinado(2, &proxima, &proxima[..3]);
+        assert!(sucessor.sucede(Some(&anterior)));
+
+        // Mas não pode repetir ou voltar a rodada
+        let mesma_rodada = assinado(1, &proxima, &proxima[..3]);
+        assert!(!mesma_rodada.sucede(Some(&anterior)));
+    }
+
+    #[test]
+    fn merkle_root_ou_nos_repetidos_invalidam_o_certificado() {
+        let chaves = chaves(3);
+        let todas = referencias(&chaves);
+
+        let valido = assinado(1, &todas, &todas);
+        assert!(valido.fila_confere());
+
+        let mut outra_raiz = certificado(1, &todas);
+        outra_raiz.merkle_root = vec![0; 32];
+        for chaves in &todas {
+            outra_raiz.assinar(chaves);
+        }
+        assert!(outra_raiz.tem_quorum());
+        assert!(!outra_raiz.sucede(None));
+
+        let repetido = assinado(1, &[todas[0], todas[1], todas[0]], &todas[..2]);
+        assert!(!repetido.fila_confere());
+        assert!(!repetido.sucede(None));
+    }
+
+    #[test]
+    fn assinaturas_repetidas_ou_de_fora_da_fila_nao_contam() {
+        let chaves = chaves(4);
+        let todas = referencias(&chaves);
+        let mut certificado = assinado(1, &todas, &todas[..2]);
+
+        // A mesma assinatura duas vezes conta uma
+        let repetida = certificado.assinaturas[0].clone();
+        certificado.assinaturas.push(repetida);
+
+        // Assinatura válida de quem não está na fila
+        let intruso = ParChaves::gerar();
+        certificado.assinaturas.push(AssinaturaFila {
+            no_id: intruso.no_id(),
+            chave_publica: intruso.chave_publica(),
+            assinatura: intruso.assinar(&certificado.bytes_para_assinatura()),
+        });
+
+        assert_eq!(certificado.assinaturas_validas(), 2);
+        assert!(!certificado.tem_quorum());
+
+        certificado.assinar(todas[2]);
+        assert!(certificado.tem_quorum());
+        assert_eq!(certificado.assinaturas_de(&[chaves[0].no_id(), intruso.no_id()]), 1);
+    }
+}
@@ -1,8 +1,22 @@
+use crate::consenso::cripto::BytesCanonicos;
+use crate::consenso::ordenacao::BilheteVrf;
 use crate::consenso::tipos::*;
 use anyhow::Result;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
 
+/// Como a fila de processadores é sorteada
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModoOrdenacao {
+    /// Todos os nós aprovados têm a mesma chance (ordem por position_hash)
+    #[default]
+    Uniforme,
+    /// Chance de cada posição proporcional ao stake vinculado
+    PonderadoPorStake,
+    /// Ordem pela saída da VRF de cada nó: ninguém conhece a fila antes dos bilhetes publicados
+    Vrf,
+}
+
 #[derive(Debug, Clone)]
 struct NoComPosicao {
     no: No,
@@ -63,4 +77,104 @@ impl GeradorFila {
             timestamp: chrono::Utc::now(),
         })
     }
+    
+    /// Sorteio ponderado sem reposição: a posição i vai para o nó apontado por
+    /// `H(seed || i) mod stake_restante` sobre os stakes acumulados.
+    ///
+    /// Nós sem stake vão para o fim, na ordem uniforme. O resultado depende só do
+    /// seed e dos stakes, então todos os nós chegam à mesma fila.
+    pub async fn gerar_fila_ponderada(
+        &self,
+        nos: Vec<No>,
+        seed_global: Vec<u8>,
+        stakes: &HashMap<NodeId, u64>,
+    ) -> Result<FilaOrdenada> {
+        // Parte da ordem uniforme para que o acumulado seja percorrido sempre na mesma ordem
+        let fila_uniforme = self.gerar_fila_ordenada(nos, seed_global.clone()).await?;
+        
+        let (mut com_stake, sem_stake): (Vec<No>, Vec<No>) = fila_uniforme
+            .nos
+            .into_iter()
+            .partition(|no| stakes.get(&no.id).copied().unwrap_or(0) > 0);
+        
+        let mut stake_restante: u128 = com_stake
+            .iter()
+            .map(|no| stakes[&no.id] as u128)
+            .sum();
+        
+        let mut nos_ordenados = Vec::with_capacity(com_stake.len() + sem_stake.len());
+        let mut sorteio = 0u64;
+        
+        while !com_stake.is_empty() {
+            let alvo = Self::sortear(&seed_global, sorteio, stake_restante);
+            sorteio += 1;
+            
+            let mut acumulado = 0u128;
+            let indice = com_stake
+                .iter()
+                .position(|no| {
+                    acumulado += stakes[&no.id] as u128;
+                    alvo < acumulado
+                })
+                .unwrap_or(com_stake.len() - 1);
+            
+            let escolhido = com_stake.remove(indice);
+            stake_restante -= stakes[&escolhido.id] as u128;
+            nos_ordenados.push(escolhido);
+        }
+        
+        nos_ordenados.extend(sem_stake);
+        
+        Ok(FilaOrdenada {
+            nos: nos_ordenados,
+            seed_global,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+    
+    /// Ordena pela saída da VRF (menor primeiro); nós aprovados sem bilhete
+    /// válido para a mesma chave ficam fora da fila.
+    pub async fn gerar_fila_vrf(
+        &self,
+        nos: Vec<No>,
+        seed_global: Vec<u8>,
+        bilhetes: &HashMap<NodeId, BilheteVrf>,
+    ) -> Result<FilaOrdenada> {
+        let total = nos.len();
+        let mut nos_com_saida: Vec<(Vec<u8>, No)> = nos
+            .into_iter()
+            .filter_map(|no| {
+                bilhetes
+                    .get(&no.id)
+                    .filter(|bilhete| bilhete.chave_publica == no.chave_publica)
+                    .map(|bilhete| (bilhete.saida.clone(), no))
+            })
+            .collect();
+        
+        if nos_com_saida.len() < total {
+            tracing::warn!("{} nós aprovados sem bilhete VRF ficaram fora da fila", total - nos_com_saida.len());
+        }
+        
+        nos_com_saida.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.id.cmp(&b.1.id)));
+        
+        Ok(FilaOrdenada {
+            nos: nos_com_saida.into_iter().map(|(_, no)| no).collect(),
+            seed_global,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+    
+    fn sortear(seed_global: &[u8], indice: u64, total: u128) -> u128 {
+        let dados = BytesCanonicos::new("NIMBOS_SORTEIO_STAKE_V1")
+            .campo(seed_global)
+            .inteiro(indice)
+            .finalizar();
+        
+        // 128 bits do hash: o viés do módulo é desprezível para stakes de 64 bits
+        let hash = calcular_hash(&dados);
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        
+        u128::from_be_bytes(bytes) % total
+    }
 }
\ No newline at end of file
@@ -1,38 +1,150 @@
+mod certificado;
 mod gerador_fila;
 mod seed_global;
+mod stake;
+mod vrf;
 
+use crate::consenso::cripto::ParChaves;
+use crate::consenso::rodada::{FaseRodada, MaquinaRodada};
 use crate::consenso::tipos::*;
 use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 
+pub use certificado::*;
 pub use gerador_fila::*;
 pub use seed_global::*;
+pub use stake::*;
+pub use vrf::*;
+
+/// Certificados de fila mantidos em memória; os mais antigos ficam só nos blocos
+const CERTIFICADOS_EM_MEMORIA: usize = 128;
 
 pub struct CamadaOrdenacao {
     gerador_fila: GeradorFila,
     seed_manager: SeedGlobalManager,
+    /// Fixado pela configuração da rodada
+    modo: ModoOrdenacao,
+    stakes_rodada: RwLock<HashMap<NodeId, u64>>,
+    bilhetes: RwLock<HashMap<NodeId, BilheteVrf>>,
     fila_atual: RwLock<Option<FilaOrdenada>>,
+    /// Certificado da fila local, acumulando assinaturas até o quorum
+    certificado_pendente: RwLock<Option<CertificadoFila>>,
+    /// Certificados da rodada recebidos antes de a fila local existir, por identificador
+    certificados_recebidos: RwLock<HashMap<Vec<u8>, CertificadoFila>>,
+    certificados: RwLock<BTreeMap<u64, CertificadoFila>>,
+    rodada: Arc<MaquinaRodada>,
 }
 
 impl CamadaOrdenacao {
-    pub fn new() -> Self {
+    pub fn new(rodada: Arc<MaquinaRodada>) -> Self {
         Self {
             gerador_fila: GeradorFila::new(),
-            seed_manager: SeedGlobalManager::new(),
+            seed_manager: SeedGlobalManager::new(&rodada.configuracao().id_cadeia),
+            modo: rodada.configuracao().modo_ordenacao,
+            stakes_rodada: RwLock::new(HashMap::new()),
+            bilhetes: RwLock::new(HashMap::new()),
             fila_atual: RwLock::new(None),
+            certificado_pendente: RwLock::new(None),
+            certificados_recebidos: RwLock::new(HashMap::new()),
+            certificados: RwLock::new(BTreeMap::new()),
+            rodada,
         }
     }
     
-    pub async fn gerar_fila(&self) -> Result<FilaOrdenada> {
-        info!("Gerando fila ordenada determinística");
+    pub fn modo_atual(&self) -> ModoOrdenacao {
+        self.modo
+    }
+    
+    /// Avalia a VRF deste nó sobre o seed recém-derivado; o bilhete deve ser enviado aos outros nós
+    pub async fn gerar_bilhete(&self, chaves: &ParChaves) -> Result<BilheteVrf> {
+        let derivacao = self.seed_manager.obter_ultima_derivacao().await
+            .ok_or_else(|| anyhow::anyhow!("Seed da rodada ainda não derivado"))?;
         
-        let seed_global = self.seed_manager.obter_seed_atual().await;
+        let bilhete = BilheteVrf::gerar(chaves, derivacao.rodada, &derivacao.seed)?;
+        self.bilhetes.write().await.insert(bilhete.no_id, bilhete.clone());
         
-        // Em implementação real, obteria nós aprovados da camada anterior
-        let nos_aprovados = vec![];
+        Ok(bilhete)
+    }
+    
+    /// Recebe o bilhete VRF de outro nó, conferindo a prova contra o seed da rodada
+    pub async fn adicionar_bilhete(&self, bilhete: BilheteVrf) -> Result<bool> {
+        let estado = self.rodada.estado_atual().await;
+        if !estado.aceita(FaseRodada::Ordenacao, bilhete.rodada) {
+            warn!(
+                "Bilhete VRF de {} rejeitado (rodada {}, atual {} na fase {:?})",
+                bilhete.no_id, bilhete.rodada, estado.numero, estado.fase
+            );
+            return Ok(false);
+        }
         
-        let fila = self.gerador_fila.gerar_fila_ordenada(nos_aprovados, seed_global).await?;
+        let Some(derivacao) = self.seed_manager.obter_ultima_derivacao().await else {
+            return Ok(false);
+        };
+        
+        if derivacao.rodada != bilhete.rodada || !bilhete.verificar(&derivacao.seed) {
+            warn!("Prova VRF inválida de {}", bilhete.no_id);
+            return Ok(false);
+        }
+        
+        self.bilhetes.write().await.insert(bilhete.no_id, bilhete);
+        Ok(true)
+    }
+    
+    /// Descarta bilhetes e certificados incompletos da rodada encerrada
+    pub async fn limpar_rodada(&self) {
+        self.bilhetes.write().await.clear();
+        self.certificados_recebidos.write().await.clear();
+        
+        if let Some(pendente) = self.certificado_pendente.write().await.take() {
+            if !self.certificados.read().await.contains_key(&pendente.rodada) {
+                warn!(
+                    "Fila da rodada {} sem quorum ({} de {} assinaturas)",
+                    pendente.rodada, pendente.assinaturas_validas(), pendente.quorum()
+                );
+            }
+        }
+    }
+    
+    /// Fixa os stakes que valem para a rodada, lidos do estado confirmado.
+    ///
+    /// Chamado no início do commit: o seed só é conhecido depois, então ninguém
+    /// consegue ajustar o stake sabendo qual será o sorteio.
+    pub async fn fixar_stakes(&self, stakes: HashMap<NodeId, u64>) {
+        *self.stakes_rodada.write().await = stakes;
+    }
+    
+    /// Deriva o seed da rodada a partir dos nonces dos reveals válidos
+    pub async fn derivar_seed(&self, rodada: u64, nonces: Vec<Vec<u8>>) -> DerivacaoSeed {
+        let derivacao = self.seed_manager.derivar_seed(rodada, nonces).await;
+        info!("Seed da rodada {}: {} ({} nonces)", rodada, hex::encode(&derivacao.seed), derivacao.nonces.len());
+        derivacao
+    }
+    
+    pub async fn obter_ultima_derivacao(&self) -> Option<DerivacaoSeed> {
+        self.seed_manager.obter_ultima_derivacao().await
+    }
+    
+    pub async fn gerar_fila(&self, nos_aprovados: Vec<No>) -> Result<FilaOrdenada> {
+        info!("Gerando fila ordenada determinística com {} nós aprovados", nos_aprovados.len());
+        
+        let seed_global = self.seed_manager.obter_seed_atual().await;
+        
+        let fila = match self.modo {
+            ModoOrdenacao::Uniforme => {
+                self.gerador_fila.gerar_fila_ordenada(nos_aprovados, seed_global).await?
+            }
+            ModoOrdenacao::PonderadoPorStake => {
+                let stakes = self.stakes_rodada.read().await;
+                self.gerador_fila.gerar_fila_ponderada(nos_aprovados, seed_global, &stakes).await?
+            }
+            ModoOrdenacao::Vrf => {
+                let bilhetes = self.bilhetes.read().await;
+                self.gerador_fila.gerar_fila_vrf(nos_aprovados, seed_global, &bilhetes).await?
+            }
+        };
         
         *self.fila_atual.write().await = Some(fila.clone());
         
@@ -42,4 +154,156 @@ impl CamadaOrdenacao {
     pub async fn obter_fila_atual(&self) -> Option<FilaOrdenada> {
         self.fila_atual.read().await.clone()
     }
-}
\ No newline at end of file
+    
+    /// Monta o certificado da fila atual e o assina se este nó estiver nela.
+    ///
+    /// Retorna o certificado a enviar aos outros nós, ou `None` se o nó não faz parte da fila.
+    pub async fn certificar_fila(
+        &self,
+        rodada: u64,
+        merkle_root: Vec<u8>,
+        chaves: &ParChaves,
+    ) -> Result<Option<CertificadoFila>> {
+        let fila = self.obter_fila_atual().await
+            .ok_or_else(|| anyhow::anyhow!("Nenhuma fila gerada para certificar"))?;
+        
+        let mut certificado = CertificadoFila::novo(rodada, &fila, merkle_root);
+        let assinado = certificado.assinar(chaves);
+        
+        // Assinaturas que chegaram antes da fila local ficar pronta
+        if let Some(recebido) = self.certificados_recebidos.write().await.remove(&certificado.identificador()) {
+            certificado.mesclar(&recebido);
+        }
+        
+        let enviar = assinado.then(|| certificado.clone());
+        *self.certificado_pendente.write().await = Some(certificado);
+        self.concluir_certificado().await;
+        
+        Ok(enviar)
+    }
+    
+    /// Recebe assinaturas de outros nós sobre a fila da rodada atual
+    pub async fn adicionar_certificado(&self, certificado: CertificadoFila) -> Result<bool> {
+        let rodada_atual = self.rodada.rodada_atual().await;
+        if certificado.rodada != rodada_atual {
+            warn!(
+                "Certificado de fila da rodada {} rejeitado (rodada atual {})",
+                certificado.rodada, rodada_atual
+            );
+            return Ok(false);
+        }
+        
+        if certificado.assinaturas_validas() == 0 {
+            warn!("Certificado de fila sem assinaturas válidas");
+            return Ok(false);
+        }
+        
+        let mut pendente = self.certificado_pendente.write().await;
+        match pendente.as_mut() {
+            Some(local) if local.rodada == certificado.rodada => {
+                // Só conta quem assinou a mesma fila que este nó calculou
+                if local.mesclar(&certificado) == 0 {
+                    return Ok(false);
+                }
+            }
+            _ => {
+                self.certificados_recebidos
+                    .write()
+                    .await
+                    .entry(certificado.identificador())
+                    .and_modify(|existente| {
+                        existente.mesclar(&certificado);
+                    })
+                    .or_insert(certificado);
+                return Ok(true);
+            }
+        }
+        drop(pendente);
+        
+        self.concluir_certificado().await;
+        Ok(true)
+    }
+    
+    /// Registra um certificado já completo (ex.: o gravado num bloco, ao sincronizar),
+    /// conferido contra o certificado aceito antes dele
+    pub async fn registrar_certificado(
+        &self,
+        certificado: CertificadoFila,
+        anterior: Option<&CertificadoFila>,
+    ) -> Result<()> {
+        if !certificado.sucede(anterior) {
+            return Err(anyhow::anyhow!("Certificado da rodada {} sem quorum", certificado.rodada));
+        }
+        
+        self.guardar_certificado(certificado).await;
+        Ok(())
+    }
+    
+    pub async fn obter_certificado(&self, rodada: u64) -> Option<CertificadoFila> {
+        self.certificados.read().await.get(&rodada).cloned()
+    }
+    
+    async fn concluir_certificado(&self) {
+        let completo = self.certificado_pendente.read().await
+            .as_ref()
+            .filter(|certificado| certificado.tem_quorum())
+            .cloned();
+        
+        if let Some(certificado) = completo {
+            if !self.certificados.read().await.contains_key(&certificado.rodada) {
+                info!(
+                    "Fila da rodada {} certificada por {} nós",
+                    certificado.rodada, certificado.assinaturas_validas()
+                );
+            }
+            self.guardar_certificado(certificado).await;
+        }
+    }
+    
+    async fn guardar_certificado(&self, certificado: CertificadoFila) {
+        let mut certificados = self.certificados.write().await;
+        certificados.insert(certificado.rodada, certificado);
+        
+        while certificados.len() > CERTIFICADOS_EM_MEMORIA {
+            certificados.pop_first();
+        }
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::{chaves, fila};
+    use crate::consenso::rodada::ConfiguracaoRodada;
+
+    fn ordenacao(modo_ordenacao: ModoOrdenacao) -> CamadaOrdenacao {
+        let configuracao = ConfiguracaoRodada { modo_ordenacao, ..ConfiguracaoRodada::default() };
+        CamadaOrdenacao::new(Arc::new(MaquinaRodada::new_com_configuracao(configuracao)))
+    }
+
+    #[tokio::test]
+    async fn stake_da_configuracao_muda_a_ordem_da_fila() {
+        let nos = fila(&chaves(4)).nos;
+
+        let uniforme = ordenacao(ModoOrdenacao::Uniforme);
+        let ordem_uniforme = uniforme.gerar_fila(nos.clone()).await.unwrap().nos;
+        let ultimo = ordem_uniforme[3].id;
+
+        // Quase todo o stake no último da ordem uniforme: ele passa à frente
+        let ponderada = ordenacao(ModoOrdenacao::PonderadoPorStake);
+        assert_eq!(ponderada.modo_atual(), ModoOrdenacao::PonderadoPorStake);
+        let stakes = nos.iter().map(|no| (no.id, if no.id == ultimo { 1_000_000_000 } else { 1 })).collect();
+        ponderada.fixar_stakes(stakes).await;
+        assert_eq!(ponderada.gerar_fila(nos.clone()).await.unwrap().nos[0].id, ultimo);
+
+        // Só ele com stake: os demais ficam atrás, na ordem uniforme
+        ponderada.fixar_stakes(HashMap::from([(ultimo, 10)])).await;
+        let ids: Vec<NodeId> = ponderada.gerar_fila(nos.clone()).await.unwrap().nos.iter().map(|no| no.id).collect();
+        let esperado: Vec<NodeId> = std::iter::once(ultimo).chain(ordem_uniforme[..3].iter().map(|no| no.id)).collect();
+        assert_eq!(ids, esperado);
+
+        // O modo uniforme ignora os stakes
+        uniforme.fixar_stakes(HashMap::from([(ultimo, 10)])).await;
+        assert_eq!(uniforme.gerar_fila(nos).await.unwrap().nos[3].id, ultimo);
+    }
+}
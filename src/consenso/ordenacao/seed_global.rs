@@ -1,33 +1,125 @@
-use anyhow::Result;
-use rand::Rng;
+use crate::consenso::cripto::BytesCanonicos;
+use crate::consenso::tipos::calcular_hash;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
+/// Seed da primeira rodada; depende só do id da cadeia, então todos os nós partem do mesmo valor
+pub fn seed_genesis(id_cadeia: &str) -> Vec<u8> {
+    let dados = BytesCanonicos::new("NIMBOS_SEED_GENESIS_V1")
+        .campo(id_cadeia.as_bytes())
+        .finalizar();
+
+    calcular_hash(&dados)
+}
+
+/// Registro de como o seed de uma rodada foi obtido, gravado no cabeçalho do bloco.
+///
+/// `seed = H(tag || rodada || seed_anterior || nonces ordenados)`; qualquer nó
+/// com os reveals da rodada chega ao mesmo valor e pode conferir com `verificar`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivacaoSeed {
+    pub rodada: u64,
+    pub seed_anterior: Vec<u8>,
+    /// Nonces dos reveals válidos, em ordem lexicográfica
+    pub nonces: Vec<Vec<u8>>,
+    pub seed: Vec<u8>,
+}
+
+impl DerivacaoSeed {
+    pub fn derivar(rodada: u64, seed_anterior: Vec<u8>, mut nonces: Vec<Vec<u8>>) -> Self {
+        // A ordem de chegada dos reveals varia entre nós; a ordem lexicográfica não
+        nonces.sort();
+        nonces.dedup();
+
+        let seed = Self::calcular_seed(rodada, &seed_anterior, &nonces);
+
+        Self {
+            rodada,
+            seed_anterior,
+            nonces,
+            seed,
+        }
+    }
+
+    /// Recalcula o seed a partir dos dados registrados
+    pub fn verificar(&self) -> bool {
+        self.nonces.windows(2).all(|par| par[0] < par[1])
+            && Self::calcular_seed(self.rodada, &self.seed_anterior, &self.nonces) == self.seed
+    }
+
+    /// Confere que esta derivação pode suceder a anterior.
+    ///
+    /// Rodadas sem bloco não deixam registro, então o encadeamento dos seeds só
+    /// é exigido entre rodadas consecutivas.
+    pub fn continua(&self, anterior: &DerivacaoSeed) -> bool {
+        if self.rodada == anterior.rodada + 1 {
+            self.seed_anterior == anterior.seed
+        } else {
+            self.rodada > anterior.rodada
+        }
+    }
+
+    /// Codificação canônica, usada no hash do cabeçalho do bloco
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut bytes = BytesCanonicos::new("NIMBOS_DERIVACAO_SEED_V1")
+            .inteiro(self.rodada)
+            .campo(&self.seed_anterior)
+            .inteiro(self.nonces.len() as u64);
+
+        for nonce in &self.nonces {
+            bytes = bytes.campo(nonce);
+        }
+
+        bytes.campo(&self.seed).finalizar()
+    }
+
+    fn calcular_seed(rodada: u64, seed_anterior: &[u8], nonces: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = BytesCanonicos::new("NIMBOS_SEED_V1")
+            .inteiro(rodada)
+            .campo(seed_anterior)
+            .inteiro(nonces.len() as u64);
+
+        for nonce in nonces {
+            bytes = bytes.campo(nonce);
+        }
+
+        calcular_hash(&bytes.finalizar())
+    }
+}
+
 pub struct SeedGlobalManager {
     seed_atual: RwLock<Vec<u8>>,
+    ultima_derivacao: RwLock<Option<DerivacaoSeed>>,
 }
 
 impl SeedGlobalManager {
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let seed_inicial: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-        
+    pub fn new(id_cadeia: &str) -> Self {
         Self {
-            seed_atual: RwLock::new(seed_inicial),
+            seed_atual: RwLock::new(seed_genesis(id_cadeia)),
+            ultima_derivacao: RwLock::new(None),
         }
     }
-    
+
     pub async fn obter_seed_atual(&self) -> Vec<u8> {
         self.seed_atual.read().await.clone()
     }
-    
-    pub async fn atualizar_seed(&self, novo_seed: Vec<u8>) {
-        *self.seed_atual.write().await = novo_seed;
+
+    /// Ajusta o seed a partir de uma derivação já verificada (ex.: a do último bloco, ao sincronizar)
+    pub async fn atualizar_seed(&self, derivacao: DerivacaoSeed) {
+        *self.seed_atual.write().await = derivacao.seed.clone();
+        *self.ultima_derivacao.write().await = Some(derivacao);
+    }
+
+    /// Deriva o seed da rodada a partir dos nonces revelados e do seed atual
+    pub async fn derivar_seed(&self, rodada: u64, nonces: Vec<Vec<u8>>) -> DerivacaoSeed {
+        let seed_anterior = self.obter_seed_atual().await;
+        let derivacao = DerivacaoSeed::derivar(rodada, seed_anterior, nonces);
+
+        self.atualizar_seed(derivacao.clone()).await;
+        derivacao
     }
-    
-    pub async fn gerar_novo_seed(&self) -> Vec<u8> {
-        let mut rng = rand::thread_rng();
-        let novo_seed: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-        self.atualizar_seed(novo_seed.clone()).await;
-        novo_seed
+
+    pub async fn obter_ultima_derivacao(&self) -> Option<DerivacaoSeed> {
+        self.ultima_derivacao.read().await.clone()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,111 @@
+use crate::consenso::cripto::BytesCanonicos;
+use anyhow::Result;
+
+/// Blocos que um valor em desvínculo leva para voltar ao saldo
+pub const BLOCOS_DESVINCULO: u64 = 10;
+
+/// Stake de um nó, guardado no estado da máquina de transferências sob
+/// `PREFIXO_STAKE || endereço`, e portanto coberto pela raiz de estado.
+///
+/// O desvínculo não é imediato: o valor deixa de contar para a ordenação na
+/// hora, mas só volta ao saldo `BLOCOS_DESVINCULO` blocos depois, para que
+/// continue em risco durante esse período.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntradaStake {
+    /// Stake que conta para a ordenação
+    pub vinculado: u64,
+    /// Valores saindo: (valor, bloco a partir do qual é liberado), na ordem em que entraram
+    pub desvinculando: Vec<(u64, u64)>,
+}
+
+impl EntradaStake {
+    pub fn vazia(&self) -> bool {
+        self.vinculado == 0 && self.desvinculando.is_empty()
+    }
+
+    /// Tira do desvínculo o que vence até o bloco `altura` e devolve o total liberado
+    pub fn liberar(&mut self, altura: u64) -> u64 {
+        let mut total = 0u64;
+        self.desvinculando.retain(|(valor, liberacao)| {
+            if *liberacao <= altura {
+                total = total.saturating_add(*valor);
+                false
+            } else {
+                true
+            }
+        });
+        total
+    }
+
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut bytes = BytesCanonicos::new("NIMBOS_STAKE_V1")
+            .inteiro(self.vinculado)
+            .inteiro(self.desvinculando.len() as u64);
+
+        for (valor, liberacao) in &self.desvinculando {
+            bytes = bytes.inteiro(*valor).inteiro(*liberacao);
+        }
+
+        bytes.finalizar()
+    }
+
+    pub fn decodificar(bytes: &[u8]) -> Result<Self> {
+        let prefixo = BytesCanonicos::new("NIMBOS_STAKE_V1").finalizar();
+        let corpo = bytes
+            .strip_prefix(prefixo.as_slice())
+            .ok_or_else(|| anyhow::anyhow!("Stake codificado sem o prefixo esperado"))?;
+
+        let inteiros: Vec<u64> = corpo
+            .chunks(8)
+            .map(|pedaco| pedaco.try_into().map(u64::from_be_bytes))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("Stake codificado com tamanho inválido"))?;
+
+        let [vinculado, quantidade, pares @ ..] = inteiros.as_slice() else {
+            return Err(anyhow::anyhow!("Stake codificado truncado"));
+        };
+
+        if pares.len() as u64 != quantidade.saturating_mul(2) {
+            return Err(anyhow::anyhow!("Stake codificado com {} desvínculos declarados", quantidade));
+        }
+
+        Ok(Self {
+            vinculado: *vinculado,
+            desvinculando: pares.chunks(2).map(|par| (par[0], par[1])).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    #[test]
+    fn codificacao_preserva_a_entrada_e_recusa_bytes_malformados() {
+        let entrada = EntradaStake {
+            vinculado: 7,
+            desvinculando: vec![(3, 20), (5, 31)],
+        };
+        let bytes = entrada.codificar();
+        assert_eq!(EntradaStake::decodificar(&bytes).unwrap(), entrada);
+
+        assert!(EntradaStake::decodificar(&bytes[..bytes.len() - 1]).is_err());
+        assert!(EntradaStake::decodificar(&bytes[..bytes.len() - 16]).is_err());
+        assert!(EntradaStake::decodificar(&[bytes.as_slice(), &[0; 16]].concat()).is_err());
+    }
+
+    #[test]
+    fn liberar_tira_so_os_vencidos() {
+        let mut entrada = EntradaStake {
+            vinculado: 0,
+            desvinculando: vec![(3, 20), (5, 31), (2, 20)],
+        };
+
+        assert_eq!(entrada.liberar(19), 0);
+        assert_eq!(entrada.liberar(20), 5);
+        assert_eq!(entrada.desvinculando, vec![(5, 31)]);
+        assert!(!entrada.vazia());
+        assert_eq!(entrada.liberar(40), 5);
+        assert!(entrada.vazia());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::consenso::cripto::{BytesCanonicos, ParChaves, TAMANHO_CHAVE_PUBLICA};
+use crate::consenso::tipos::NodeId;
+use anyhow::Result;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+/// Tamanho da prova: Gamma (32) || c (16) || s (32)
+pub const TAMANHO_PROVA_VRF: usize = 80;
+
+// Identificador da suíte ECVRF-EDWARDS25519-SHA512-TAI (RFC 9381)
+const SUITE: u8 = 0x03;
+
+/// VRF sobre as mesmas chaves Ed25519 do nó, no formato da RFC 9381
+/// (ECVRF-EDWARDS25519-SHA512-TAI).
+///
+/// Só o dono da chave consegue calcular a saída para uma entrada, e qualquer um
+/// confere a prova com a chave pública.
+pub struct Vrf;
+
+impl Vrf {
+    /// Calcula a prova; a saída se obtém com `saida_da_prova`
+    pub fn provar(chaves: &ParChaves, entrada: &[u8]) -> Result<Vec<u8>> {
+        let x = chaves.escalar_secreto();
+        let chave_publica = chaves.chave_publica();
+
+        // Falha só se nenhum dos 256 contadores der um ponto válido (probabilidade desprezível)
+        let h = Self::hash_para_curva(&chave_publica, entrada)
+            .ok_or_else(|| anyhow::anyhow!("Entrada da VRF não mapeou para a curva"))?;
+        let gamma = h * x;
+
+        let k = Self::gerar_nonce(&chaves.prefixo_nonce(), &h);
+        let c = Self::desafio(&chave_publica, &h, &gamma, &EdwardsPoint::mul_base(&k), &(h * k));
+        let s = k + Self::escalar_do_desafio(&c) * x;
+
+        let mut prova = Vec::with_capacity(TAMANHO_PROVA_VRF);
+        prova.extend_from_slice(gamma.compress().as_bytes());
+        prova.extend_from_slice(&c);
+        prova.extend_from_slice(s.as_bytes());
+        Ok(prova)
+    }
+
+    /// Confere a prova e devolve a saída (64 bytes) se ela for válida
+    pub fn verificar(chave_publica: &[u8], entrada: &[u8], prova: &[u8]) -> Option<Vec<u8>> {
+        let y = Self::decodificar_ponto(chave_publica)?;
+        if y.is_small_order() {
+            return None;
+        }
+
+        let (gamma, c, s) = Self::decodificar_prova(prova)?;
+        let h = Self::hash_para_curva(chave_publica, entrada)?;
+
+        let c_escalar = Self::escalar_do_desafio(&c);
+        let u = EdwardsPoint::mul_base(&s) - y * c_escalar;
+        let v = h * s - gamma * c_escalar;
+
+        if Self::desafio(chave_publica, &h, &gamma, &u, &v) != c {
+            return None;
+        }
+
+        Some(Self::saida_de_gamma(&gamma))
+    }
+
+    /// Saída correspondente a uma prova (sem verificá-la)
+    pub fn saida_da_prova(prova: &[u8]) -> Option<Vec<u8>> {
+        let (gamma, _, _) = Self::decodificar_prova(prova)?;
+        Some(Self::saida_de_gamma(&gamma))
+    }
+
+    // Try-and-increment: primeiro contador cujo hash é um ponto válido
+    fn hash_para_curva(chave_publica: &[u8], entrada: &[u8]) -> Option<EdwardsPoint> {
+        for contador in 0u8..=255 {
+            let hash = Sha512::new()
+                .chain_update([SUITE, 0x01])
+                .chain_update(chave_publica)
+                .chain_update(entrada)
+                .chain_update([contador, 0x00])
+                .finalize();
+
+            let mut candidato = [0u8; 32];
+            candidato.copy_from_slice(&hash[..32]);
+
+            if let Some(ponto) = CompressedEdwardsY(candidato).decompress() {
+                let ponto = ponto.mul_by_cofactor();
+                if !ponto.is_identity() {
+                    return Some(ponto);
+                }
+            }
+        }
+
+        None
+    }
+
+    // RFC 9381, seção 5.4.2.2: como o nonce do Ed25519, a partir do hash da chave privada
+    fn gerar_nonce(prefixo: &[u8; 32], h: &EdwardsPoint) -> Scalar {
+        let hash = Sha512::new()
+            .chain_update(prefixo)
+            .chain_update(h.compress().as_bytes())
+            .finalize();
+
+        let mut bytes = [0u8; 64];
+        bytes.copy_from_slice(&hash);
+        Scalar::from_bytes_mod_order_wide(&bytes)
+    }
+
+    fn desafio(
+        chave_publica: &[u8],
+        h: &EdwardsPoint,
+        gamma: &EdwardsPoint,
+        u: &EdwardsPoint,
+        v: &EdwardsPoint,
+    ) -> [u8; 16] {
+        let hash = Sha512::new()
+            .chain_update([SUITE, 0x02])
+            .chain_update(chave_publica)
+            .chain_update(h.compress().as_bytes())
+            .chain_update(gamma.compress().as_bytes())
+            .chain_update(u.compress().as_bytes())
+            .chain_update(v.compress().as_bytes())
+            .chain_update([0x00])
+            .finalize();
+
+        let mut c = [0u8; 16];
+        c.copy_from_slice(&hash[..16]);
+        c
+    }
+
+    fn escalar_do_desafio(c: &[u8; 16]) -> Scalar {
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(c);
+        Scalar::from_bytes_mod_order(bytes)
+    }
+
+    fn saida_de_gamma(gamma: &EdwardsPoint) -> Vec<u8> {
+        Sha512::new()
+            .chain_update([SUITE, 0x03])
+            .chain_update(gamma.mul_by_cofactor().compress().as_bytes())
+            .chain_update([0x00])
+            .finalize()
+            .to_vec()
+    }
+
+    fn decodificar_ponto(bytes: &[u8]) -> Option<EdwardsPoint> {
+        let bytes: [u8; TAMANHO_CHAVE_PUBLICA] = bytes.try_into().ok()?;
+        CompressedEdwardsY(bytes).decompress()
+    }
+
+    fn decodificar_prova(prova: &[u8]) -> Option<(EdwardsPoint, [u8; 16], Scalar)> {
+        if prova.len() != TAMANHO_PROVA_VRF {
+            return None;
+        }
+
+        let gamma = Self::decodificar_ponto(&prova[..32])?;
+
+        let mut c = [0u8; 16];
+        c.copy_from_slice(&prova[32..48]);
+
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&prova[48..]);
+        let s = Option::<Scalar>::from(Scalar::from_canonical_bytes(s))?;
+
+        Some((gamma, c, s))
+    }
+}
+
+/// Saída de VRF publicada por um nó aprovado para disputar posição na fila
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BilheteVrf {
+    pub no_id: NodeId,
+    pub chave_publica: Vec<u8>,
+    pub rodada: u64,
+    pub saida: Vec<u8>,
+    pub prova: Vec<u8>,
+}
+
+impl BilheteVrf {
+    /// Entrada da VRF: rodada e seed da rodada, com tag de domínio
+    pub fn entrada(rodada: u64, seed: &[u8]) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_VRF_FILA_V1")
+            .inteiro(rodada)
+            .campo(seed)
+            .finalizar()
+    }
+
+    pub fn gerar(chaves: &ParChaves, rodada: u64, seed: &[u8]) -> Result<Self> {
+        let prova = Vrf::provar(chaves, &Self::entrada(rodada, seed))?;
+        let saida = Vrf::saida_da_prova(&prova)
+            .ok_or_else(|| anyhow::anyhow!("Prova VRF gerada com formato inválido"))?;
+
+        Ok(Self {
+            no_id: chaves.no_id(),
+            chave_publica: chaves.chave_publica(),
+            rodada,
+            saida,
+            prova,
+        })
+    }
+
+    /// Confere identidade, prova e saída contra o seed da rodada
+    pub fn verificar(&self, seed: &[u8]) -> bool {
+        if !self.no_id.corresponde(&self.chave_publica) {
+            return false;
+        }
+
+        match Vrf::verificar(&self.chave_publica, &Self::entrada(self.rodada, seed), &self.prova) {
+            Some(saida) => saida == self.saida,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    fn chaves(chave_privada: &str) -> ParChaves {
+        ParChaves::de_bytes(&hex::decode(chave_privada).unwrap()).unwrap()
+    }
+
+    // RFC 9381, apêndice B.3 (ECVRF-EDWARDS25519-SHA512-TAI): (SK, alpha, pi, beta)
+    const VETORES: [(&str, &str, &str, &str); 2] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "",
+            "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+            "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "72",
+            "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+            "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+        ),
+    ];
+
+    #[test]
+    fn vetores_da_rfc_9381() {
+        for (chave_privada, alfa, pi, beta) in VETORES {
+            let chaves = chaves(chave_privada);
+            let alfa = hex::decode(alfa).unwrap();
+
+            let prova = Vrf::provar(&chaves, &alfa).unwrap();
+            assert_eq!(hex::encode(&prova), pi);
+            assert_eq!(
+                Vrf::verificar(&chaves.chave_publica(), &alfa, &prova).map(hex::encode).as_deref(),
+                Some(beta)
+            );
+        }
+    }
+
+    #[test]
+    fn bilhete_confere_so_com_a_mesma_entrada_e_chave() {
+        let chaves = ParChaves::gerar();
+        let seed = [7u8; 32];
+        let bilhete = BilheteVrf::gerar(&chaves, 3, &seed).unwrap();
+
+        assert!(bilhete.verificar(&seed));
+        assert_eq!(bilhete.saida.len(), 64);
+
+        // Seed ou rodada diferentes
+        assert!(!bilhete.verificar(&[8u8; 32]));
+        assert!(!BilheteVrf { rodada: 4, ..bilhete.clone() }.verificar(&seed));
+
+        // Prova adulterada em cada uma das partes (Gamma, c, s)
+        for posicao in [0, 40, 60] {
+            let mut adulterado = bilhete.clone();
+            adulterado.prova[posicao] ^= 1;
+            assert!(!adulterado.verificar(&seed));
+        }
+
+        // Saída que não corresponde à prova
+        let mut adulterado = bilhete.clone();
+        adulterado.saida[0] ^= 1;
+        assert!(!adulterado.verificar(&seed));
+
+        // Prova de outra chave, com ou sem a identidade trocada junto
+        let outras = ParChaves::gerar();
+        let mut outra_chave = bilhete.clone();
+        outra_chave.chave_publica = outras.chave_publica();
+        assert!(!outra_chave.verificar(&seed));
+        outra_chave.no_id = outras.no_id();
+        assert!(!outra_chave.verificar(&seed));
+    }
+
+    #[test]
+    fn prova_com_tamanho_errado_e_recusada() {
+        let chaves = ParChaves::gerar();
+        let prova = Vrf::provar(&chaves, b"entrada").unwrap();
+
+        assert!(Vrf::verificar(&chaves.chave_publica(), b"entrada", &prova[..TAMANHO_PROVA_VRF - 1]).is_none());
+        assert!(Vrf::saida_da_prova(&[]).is_none());
+    }
+}
@@ -1,70 +1,171 @@
+use super::maquina::{MaquinaCompartilhada, MaquinaEstado, MaquinaTransferencias};
+use crate::consenso::merkle::ProvaEstado;
 use crate::consenso::tipos::*;
 use anyhow::Result;
 use std::collections::HashMap;
-use tokio::sync::RwLock;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EstadoTransacao {
-    pub id: String,
-    pub estado_anterior: Vec<u8>,
-    pub estado_atual: Vec<u8>,
-    pub timestamp_processamento: chrono::DateTime<chrono::Utc>,
-    pub processador_id: String,
-    pub hash_cadeia: Vec<u8>,
+/// Saldo e nonce de uma conta, como gravados na árvore de estado
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conta {
+    pub saldo: u64,
+    pub nonce: u64,
+}
+
+impl Conta {
+    pub const TAMANHO_CODIFICADO: usize = 16;
+    
+    /// `saldo (u64 BE) || nonce (u64 BE)`
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::TAMANHO_CODIFICADO);
+        bytes.extend_from_slice(&self.saldo.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        bytes
+    }
+    
+    pub fn decodificar(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::TAMANHO_CODIFICADO {
+            return Err(anyhow::anyhow!("Conta codificada deve ter {} bytes", Self::TAMANHO_CODIFICADO));
+        }
+        
+        Ok(Self {
+            saldo: u64::from_be_bytes(bytes[..8].try_into()?),
+            nonce: u64::from_be_bytes(bytes[8..].try_into()?),
+        })
+    }
 }
 
 pub struct GerenciadorEstado {
-    estados: RwLock<HashMap<String, EstadoTransacao>>,
-    snapshots: RwLock<Vec<HashMap<String, EstadoTransacao>>>,
+    /// Estado da aplicação; a raiz vai para o cabeçalho de cada bloco
+    maquina: MaquinaCompartilhada,
 }
 
 impl GerenciadorEstado {
     pub fn new() -> Self {
+        Self::new_com_maquina(Box::new(MaquinaTransferencias::new()))
+    }
+    
+    pub fn new_com_maquina(maquina: Box<dyn MaquinaEstado>) -> Self {
         Self {
-            estados: RwLock::new(HashMap::new()),
-            snapshots: RwLock::new(Vec::new()),
+            maquina: Arc::new(Mutex::new(maquina)),
         }
     }
     
-    pub async fn atualizar_estado(&self, transacao: &Transacao, processador_id: &str, hash_cadeia: Vec<u8>) -> Result<()> {
-        let estado = EstadoTransacao {
-            id: transacao.id.clone(),
-            estado_anterior: transacao.estado_anterior.clone(),
-            estado_atual: transacao.estado_final.clone(),
-            timestamp_processamento: transacao.timestamp,
-            processador_id: processador_id.to_string(),
-            hash_cadeia,
-        };
-        
-        self.estados.write().await.insert(transacao.id.clone(), estado);
-        Ok(())
+    /// A mesma máquina, para a validação distribuída conferir as transações
+    pub fn maquina(&self) -> MaquinaCompartilhada {
+        self.maquina.clone()
     }
     
-    pub async fn obter_estado(&self, transacao_id: &str) -> Option<EstadoTransacao> {
-        self.estados.read().await.get(transacao_id).cloned()
+    /// Conta ausente (ou estado que não guarda contas) equivale a saldo e nonce zero
+    pub async fn obter_conta(&self, id_conta: &str) -> Conta {
+        self.maquina
+            .lock()
+            .await
+            .consultar(id_conta.as_bytes())
+            .and_then(|bytes| Conta::decodificar(&bytes).ok())
+            .unwrap_or_default()
     }
     
-    pub async fn criar_snapshot(&self) -> Result<()> {
-        let estados_atuais = self.estados.read().await.clone();
-        self.snapshots.write().await.push(estados_atuais);
-        Ok(())
+    /// Aplica e confirma uma transação; ela volta com os estados antes e depois preenchidos
+    pub async fn aplicar_transacao(&self, transacao: &Transacao, produtor: &str) -> Result<Transacao> {
+        let mut maquina = self.maquina.lock().await;
+        let aplicada = maquina.aplicar(transacao, produtor)?;
+        maquina.confirmar();
+        Ok(aplicada)
+    }
+    
+    /// Aplica um lote sem confirmá-lo; devolve as transações na mesma ordem, com os
+    /// estados preenchidos, ou o erro que rejeitou cada uma (sem alterar o estado).
+    ///
+    /// O lote, que forma o bloco `numero`, fica pendente até `confirmar` ou `descartar`.
+    pub async fn executar_lote(&self, transacoes: &[Transacao], numero: u64, produtor: &str) -> Result<Vec<Result<Transacao>>> {
+        self.com_maquina(transacoes, produtor, move |maquina, transacoes, produtor| {
+            maquina.iniciar_bloco(numero);
+            maquina.aplicar_lote(&transacoes, &produtor)
+        })
+        .await
+    }
+    
+    /// Torna definitivo o que foi executado desde a última confirmação
+    pub async fn confirmar(&self) {
+        self.maquina.lock().await.confirmar();
     }
     
-    pub async fn obter_ultimo_snapshot(&self) -> Option<HashMap<String, EstadoTransacao>> {
-        self.snapshots.read().await.last().cloned()
+    /// Desfaz o que foi executado desde a última confirmação
+    pub async fn descartar(&self) {
+        self.maquina.lock().await.descartar();
     }
     
-    pub async fn validar_consistencia(&self) -> Result<bool> {
-        let estados = self.estados.read().await;
+    /// Reexecuta as transações de um bloco produzido por outro nó.
+    ///
+    /// Cada transação precisa chegar nos mesmos estados que o processador
+    /// registrou, e o bloco precisa terminar em `raiz_esperada`; caso contrário o
+    /// estado local fica como estava. Conferido, o bloco fica pendente até
+    /// `confirmar` ou `descartar`.
+    pub async fn aplicar_bloco(&self, transacoes: &[Transacao], numero: u64, produtor: &str, raiz_esperada: &[u8]) -> Result<()> {
+        let raiz_esperada = raiz_esperada.to_vec();
         
-        // Verificar se todos os estados têm hashes válidos
-        for estado in estados.values() {
-            if estado.hash_cadeia.len() != 32 {
-                return Ok(false);
+        self.com_maquina(transacoes, produtor, move |maquina, transacoes, produtor| {
+            maquina.iniciar_bloco(numero);
+            let resultado = Self::conferir_bloco(maquina.as_mut(), &transacoes, &produtor, &raiz_esperada);
+            if resultado.is_err() {
+                maquina.descartar();
+            }
+            resultado
+        })
+        .await?
+    }
+    
+    fn conferir_bloco(maquina: &mut dyn MaquinaEstado, transacoes: &[Transacao], produtor: &str, raiz_esperada: &[u8]) -> Result<()> {
+        for (recebida, aplicada) in transacoes.iter().zip(maquina.aplicar_lote(transacoes, produtor)) {
+            let aplicada = aplicada?;
+            
+            if aplicada.estado_anterior != recebida.estado_anterior {
+                return Err(anyhow::anyhow!("Transação {} não parte do estado atual", recebida.id));
+            }
+            
+            if aplicada.estado_final != recebida.estado_final {
+                return Err(anyhow::anyhow!("Estado final da transação {} não confere", recebida.id));
             }
         }
         
-        Ok(true)
+        if maquina.raiz_estado() != raiz_esperada {
+            return Err(anyhow::anyhow!("Raiz de estado do bloco não confere com a reexecução"));
+        }
+        
+        Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Roda `operacao` com a máquina travada, fora das threads do runtime: um
+    /// lote pode ocupar todas as threads do executor paralelo
+    async fn com_maquina<T, F>(&self, transacoes: &[Transacao], produtor: &str, operacao: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Box<dyn MaquinaEstado>, Vec<Transacao>, String) -> T + Send + 'static,
+    {
+        let mut maquina = self.maquina.clone().lock_owned().await;
+        let transacoes = transacoes.to_vec();
+        let produtor = produtor.to_string();
+        
+        tokio::task::spawn_blocking(move || operacao(&mut maquina, transacoes, produtor))
+            .await
+            .map_err(|e| anyhow::anyhow!("Execução do lote interrompida: {}", e))
+    }
+    
+    /// Stake vinculado de cada nó no estado atual
+    pub async fn stakes(&self) -> HashMap<NodeId, u64> {
+        self.maquina.lock().await.stakes()
+    }
+    
+    pub async fn raiz_estado(&self) -> Vec<u8> {
+        self.maquina.lock().await.raiz_estado()
+    }
+    
+    /// Prova do saldo e nonce de uma conta (ou da sua ausência) contra `raiz_estado`,
+    /// se a máquina de estado mantém uma árvore
+    pub async fn provar_conta(&self, id_conta: &str) -> Option<ProvaEstado> {
+        self.maquina.lock().await.provar(id_conta.as_bytes())
+    }
+}
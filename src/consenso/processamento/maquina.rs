@@ -0,0 +1,592 @@
+use super::estado::Conta;
+use super::paralelo::ExecutorParalelo;
+use super::transicao::{self, Contas, Stakes};
+use crate::consenso::merkle::{ArvoreEstado, ProvaEstado, RAIZ_VAZIA};
+use crate::consenso::ordenacao::EntradaStake;
+use crate::consenso::tipos::{calcular_hash, NodeId, Transacao, PREFIXO_STAKE};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Lógica de aplicação que roda sobre o consenso.
+///
+/// O consenso decide a ordem das transações; a máquina decide o que elas fazem.
+/// O processamento aplica os lotes, a validação distribuída confere cada
+/// transação e o checkpoint reexecuta a cadeia a partir do genesis, todos pela
+/// mesma interface. `raiz_estado` vai para o cabeçalho dos blocos e precisa ser
+/// um hash de 32 bytes.
+pub trait MaquinaEstado: Send + Sync {
+    /// Nome da máquina, para logs
+    fn nome(&self) -> &'static str;
+
+    /// Confere a transação isoladamente (formato, assinaturas, coerência dos
+    /// estados declarados), sem consultar nem alterar o estado
+    fn validar(&self, transacao: &Transacao) -> Result<()>;
+
+    /// Aplica a transação e a devolve com `estado_anterior` e `estado_final`
+    /// preenchidos pela máquina. Em caso de erro o estado fica como estava.
+    fn aplicar(&mut self, transacao: &Transacao, produtor: &str) -> Result<Transacao>;
+
+    /// Aplica um lote na ordem dada; as rejeitadas não alteram o estado.
+    ///
+    /// O resultado precisa ser o mesmo de `aplicar` uma a uma, que é o que esta
+    /// implementação padrão faz.
+    fn aplicar_lote(&mut self, transacoes: &[Transacao], produtor: &str) -> Vec<Result<Transacao>> {
+        transacoes.iter().map(|transacao| self.aplicar(transacao, produtor)).collect()
+    }
+
+    /// Chamado antes das transações do bloco `numero` (também de um bloco vazio),
+    /// para o que a máquina faz a cada bloco. Desfeito por `descartar`.
+    fn iniciar_bloco(&mut self, _numero: u64) {}
+
+    /// Torna definitivo o que foi aplicado desde o último `confirmar`
+    fn confirmar(&mut self);
+
+    /// Volta ao estado do último `confirmar`
+    fn descartar(&mut self);
+
+    /// Valor atual de uma chave do estado
+    fn consultar(&self, chave: &[u8]) -> Option<Vec<u8>>;
+
+    /// Raiz do estado atual, incluindo o que ainda não foi confirmado
+    fn raiz_estado(&self) -> Vec<u8>;
+
+    /// Prova de uma chave contra `raiz_estado`, se a máquina mantém uma árvore de estado
+    fn provar(&self, _chave: &[u8]) -> Option<ProvaEstado> {
+        None
+    }
+
+    /// Todas as entradas do estado atual, em ordem de chave, para os checkpoints
+    fn entradas(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+
+    /// Stake vinculado de cada nó, para a ordenação ponderada; vazio se a
+    /// máquina não tem stake
+    fn stakes(&self) -> HashMap<NodeId, u64> {
+        HashMap::new()
+    }
+}
+
+/// Máquina usada ao mesmo tempo pelo processamento e pela validação
+pub type MaquinaCompartilhada = Arc<tokio::sync::Mutex<Box<dyn MaquinaEstado>>>;
+
+/// Cria a máquina no estado do genesis; o checkpoint usa uma nova a cada reexecução da cadeia
+pub type FabricaMaquinaEstado = Arc<dyn Fn() -> Box<dyn MaquinaEstado> + Send + Sync>;
+
+/// Máquina original do processamento: o estado é um hash opaco e cada
+/// transação leva a `H(estado_anterior || dados)`.
+///
+/// O estado anterior é o declarado na própria transação; a máquina guarda o
+/// último estado final, consultável pela chave `CHAVE_ESTADO`.
+#[derive(Debug, Clone)]
+pub struct MaquinaEstadoHash {
+    atual: Vec<u8>,
+    confirmado: Vec<u8>,
+}
+
+impl MaquinaEstadoHash {
+    pub const CHAVE_ESTADO: &'static [u8] = b"estado";
+
+    pub fn new() -> Self {
+        Self {
+            atual: RAIZ_VAZIA.to_vec(),
+            confirmado: RAIZ_VAZIA.to_vec(),
+        }
+    }
+
+    pub fn calcular_novo_estado(estado_anterior: &[u8], dados: &[u8]) -> Vec<u8> {
+        let mut novo_estado = Vec::with_capacity(estado_anterior.len() + dados.len());
+        novo_estado.extend_from_slice(estado_anterior);
+        novo_estado.extend_from_slice(dados);
+        calcular_hash(&novo_estado)
+    }
+}
+
+impl Default for MaquinaEstadoHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaquinaEstado for MaquinaEstadoHash {
+    fn nome(&self) -> &'static str {
+        "hash"
+    }
+
+    fn validar(&self, transacao: &Transacao) -> Result<()> {
+        if transacao.dados.is_empty() {
+            return Err(anyhow::anyhow!("Dados da transação {} não podem estar vazios", transacao.id));
+        }
+
+        if transacao.estado_anterior.is_empty() {
+            return Err(anyhow::anyhow!("Estado anterior da transação {} não pode estar vazio", transacao.id));
+        }
+
+        // Estado final já preenchido precisa ser derivado do anterior
+        if !transacao.estado_final.is_empty()
+            && transacao.estado_final != Self::calcular_novo_estado(&transacao.estado_anterior, &transacao.dados)
+        {
+            return Err(anyhow::anyhow!("Estado final da transação {} não deriva do anterior", transacao.id));
+        }
+
+        Ok(())
+    }
+
+    fn aplicar(&mut self, transacao: &Transacao, _produtor: &str) -> Result<Transacao> {
+        let mut aplicada = transacao.clone();
+        aplicada.estado_final.clear();
+        self.validar(&aplicada)?;
+
+        aplicada.estado_final = Self::calcular_novo_estado(&aplicada.estado_anterior, &aplicada.dados);
+        self.atual = aplicada.estado_final.clone();
+
+        Ok(aplicada)
+    }
+
+    fn confirmar(&mut self) {
+        self.confirmado = self.atual.clone();
+    }
+
+    fn descartar(&mut self) {
+        self.atual = self.confirmado.clone();
+    }
+
+    fn consultar(&self, chave: &[u8]) -> Option<Vec<u8>> {
+        (chave == Self::CHAVE_ESTADO).then(|| self.atual.clone())
+    }
+
+    fn raiz_estado(&self) -> Vec<u8> {
+        self.atual.clone()
+    }
+
+    fn entradas(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        vec![(Self::CHAVE_ESTADO.to_vec(), self.atual.clone())]
+    }
+}
+
+/// Transferências de tokens entre contas (saldo e nonce) numa árvore de Merkle esparsa.
+///
+/// A chave de cada conta é o seu id, e o valor a `Conta` codificada. O stake de
+/// cada nó fica na mesma árvore, sob `PREFIXO_STAKE || endereço`, e muda por
+/// transferências aos destinos reservados de stake. Transações sem
+/// transferência carregam dados opacos e não mudam o estado. Os lotes rodam
+/// no `ExecutorParalelo`.
+#[derive(Debug, Clone)]
+pub struct MaquinaTransferencias {
+    arvore: ArvoreEstado,
+    /// Mesmas contas da árvore, pelo id; a árvore só guarda o hash das chaves
+    contas: BTreeMap<String, Conta>,
+    /// Stakes da árvore, pelo endereço do nó
+    stakes: BTreeMap<String, EntradaStake>,
+    /// Bloco em execução, para os prazos de desvínculo
+    altura: u64,
+    /// Valor anterior de cada entrada alterada desde o último `confirmar`
+    diario: Vec<Alteracao>,
+    executor: ExecutorParalelo,
+}
+
+#[derive(Debug, Clone)]
+enum Alteracao {
+    Conta(String, Option<Conta>),
+    Stake(String, Option<EntradaStake>),
+    Altura(u64),
+}
+
+impl MaquinaTransferencias {
+    pub fn new() -> Self {
+        Self {
+            arvore: ArvoreEstado::new(),
+            contas: BTreeMap::new(),
+            stakes: BTreeMap::new(),
+            altura: 0,
+            diario: Vec::new(),
+            executor: ExecutorParalelo::default(),
+        }
+    }
+
+    /// Máquina já confirmada com os saldos iniciais das contas
+    pub fn com_saldos(saldos: &std::collections::HashMap<String, u64>) -> Self {
+        let mut maquina = Self::new();
+        for (id, saldo) in saldos {
+            maquina.gravar_conta(id, Conta { saldo: *saldo, nonce: 0 });
+        }
+        maquina.confirmar();
+        maquina
+    }
+
+    /// Conta ausente equivale a saldo e nonce zero
+    pub fn obter_conta(&self, id_conta: &str) -> Conta {
+        self.contas.get(id_conta).copied().unwrap_or_default()
+    }
+
+    fn gravar_conta(&mut self, id_conta: &str, conta: Conta) {
+        let anterior = if conta == Conta::default() {
+            // Conta zerada sai da árvore, para que a raiz não dependa de contas vazias
+            self.arvore.remover(id_conta.as_bytes());
+            self.contas.remove(id_conta)
+        } else {
+            self.arvore.inserir(id_conta.as_bytes(), conta.codificar());
+            self.contas.insert(id_conta.to_string(), conta)
+        };
+
+        if anterior != Some(conta) {
+            self.diario.push(Alteracao::Conta(id_conta.to_string(), anterior));
+        }
+    }
+
+    /// Stake ausente equivale a nada vinculado nem em desvínculo
+    pub fn obter_stake(&self, endereco: &str) -> EntradaStake {
+        self.stakes.get(endereco).cloned().unwrap_or_default()
+    }
+
+    fn chave_stake(endereco: &str) -> Vec<u8> {
+        format!("{}{}", PREFIXO_STAKE, endereco).into_bytes()
+    }
+
+    fn gravar_stake(&mut self, endereco: &str, stake: EntradaStake) {
+        let chave = Self::chave_stake(endereco);
+        let anterior = if stake.vazia() {
+            self.arvore.remover(&chave);
+            self.stakes.remove(endereco)
+        } else {
+            self.arvore.inserir(&chave, stake.codificar());
+            self.stakes.insert(endereco.to_string(), stake.clone())
+        };
+
+        if anterior.as_ref() != Some(&stake) {
+            self.diario.push(Alteracao::Stake(endereco.to_string(), anterior));
+        }
+    }
+
+    fn aplicar_operacao_stake(&mut self, transacao: &Transacao, produtor: &str) -> Result<Transacao> {
+        let Some(transferencia) = &transacao.transferencia else {
+            return Ok(transacao.clone());
+        };
+
+        let mut contas = Contas::new();
+        for id in [transferencia.de.as_str(), produtor] {
+            contas.insert(id.to_string(), self.obter_conta(id));
+        }
+        let mut stakes = Stakes::from([(transferencia.de.clone(), self.obter_stake(&transferencia.de))]);
+
+        transicao::aplicar_operacao_stake(&mut contas, &mut stakes, transferencia, produtor, self.altura)
+            .map_err(|e| anyhow::anyhow!("Transação {} rejeitada: {}", transacao.id, e))?;
+
+        let mut aplicada = transacao.clone();
+        aplicada.estado_anterior = self.arvore.raiz();
+        for (id, conta) in contas {
+            self.gravar_conta(&id, conta);
+        }
+        for (endereco, stake) in stakes {
+            self.gravar_stake(&endereco, stake);
+        }
+        aplicada.estado_final = self.arvore.raiz();
+
+        Ok(aplicada)
+    }
+}
+
+impl Default for MaquinaTransferencias {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaquinaEstado for MaquinaTransferencias {
+    fn nome(&self) -> &'static str {
+        "transferencias"
+    }
+
+    fn validar(&self, transacao: &Transacao) -> Result<()> {
+        let Some(transferencia) = &transacao.transferencia else {
+            return Ok(());
+        };
+
+        if !transferencia.verificar_assinatura() {
+            return Err(anyhow::anyhow!("Assinatura da transferência {} inválida", transacao.id));
+        }
+
+        transferencia.debito()
+            .ok_or_else(|| anyhow::anyhow!("Valor mais taxa da transferência {} excede o limite", transacao.id))?;
+
+        Ok(())
+    }
+
+    fn aplicar(&mut self, transacao: &Transacao, produtor: &str) -> Result<Transacao> {
+        let Some(transferencia) = &transacao.transferencia else {
+            return Ok(transacao.clone());
+        };
+
+        if transferencia.operacao_stake().is_some() {
+            return self.aplicar_operacao_stake(transacao, produtor);
+        }
+
+        let mut contas = Contas::new();
+        for id in [transferencia.de.as_str(), transferencia.para.as_str(), produtor] {
+            contas.insert(id.to_string(), self.obter_conta(id));
+        }
+
+        transicao::aplicar_transferencia(&mut contas, transferencia, produtor)
+            .map_err(|e| anyhow::anyhow!("Transação {} rejeitada: {}", transacao.id, e))?;
+
+        let mut aplicada = transacao.clone();
+        aplicada.estado_anterior = self.arvore.raiz();
+        for (id, conta) in contas {
+            self.gravar_conta(&id, conta);
+        }
+        aplicada.estado_final = self.arvore.raiz();
+
+        Ok(aplicada)
+    }
+
+    fn aplicar_lote(&mut self, transacoes: &[Transacao], produtor: &str) -> Vec<Result<Transacao>> {
+        // O executor paralelo só conhece contas; lotes com stake vão em sequência
+        if transacoes.iter().any(|t| t.transferencia.as_ref().is_some_and(|t| t.operacao_stake().is_some())) {
+            return transacoes.iter().map(|transacao| self.aplicar(transacao, produtor)).collect();
+        }
+
+        let transferencias: Vec<_> = transacoes.iter().filter_map(|t| t.transferencia.as_ref()).collect();
+
+        let mut contas = Contas::new();
+        for transferencia in &transferencias {
+            for id in [transferencia.de.as_str(), transferencia.para.as_str()] {
+                contas.entry(id.to_string()).or_insert_with(|| self.obter_conta(id));
+            }
+        }
+        contas.insert(produtor.to_string(), self.obter_conta(produtor));
+
+        let (escritas, _) = self.executor.executar(&mut contas, &transferencias, produtor);
+        let mut escritas = escritas.into_iter();
+
+        // As raízes por transação saem gravando as escritas na ordem do bloco
+        transacoes
+            .iter()
+            .map(|transacao| {
+                if transacao.transferencia.is_none() {
+                    return Ok(transacao.clone());
+                }
+
+                let escritas = escritas.next().unwrap_or_else(|| Err("transferência não executada".to_string()))
+                    .map_err(|e| anyhow::anyhow!("Transação {} rejeitada: {}", transacao.id, e))?;
+
+                let mut aplicada = transacao.clone();
+                aplicada.estado_anterior = self.arvore.raiz();
+                for (id, conta) in escritas {
+                    self.gravar_conta(&id, conta);
+                }
+                aplicada.estado_final = self.arvore.raiz();
+
+                Ok(aplicada)
+            })
+            .collect()
+    }
+
+    /// Devolve ao saldo os desvínculos que vencem neste bloco
+    fn iniciar_bloco(&mut self, numero: u64) {
+        self.diario.push(Alteracao::Altura(self.altura));
+        self.altura = numero;
+
+        let vencidos: Vec<String> = self.stakes
+            .iter()
+            .filter(|(_, stake)| stake.desvinculando.iter().any(|(_, liberacao)| *liberacao <= numero))
+            .map(|(endereco, _)| endereco.clone())
+            .collect();
+
+        for endereco in vencidos {
+            let mut stake = self.obter_stake(&endereco);
+            let liberado = stake.liberar(numero);
+            let conta = self.obter_conta(&endereco);
+
+            // Liberado só se couber no saldo; senão continua retido
+            let Some(saldo) = conta.saldo.checked_add(liberado) else {
+                continue;
+            };
+
+            self.gravar_conta(&endereco, Conta { saldo, ..conta });
+            self.gravar_stake(&endereco, stake);
+        }
+    }
+
+    fn confirmar(&mut self) {
+        self.diario.clear();
+    }
+
+    fn descartar(&mut self) {
+        while let Some(alteracao) = self.diario.pop() {
+            match alteracao {
+                Alteracao::Conta(id, Some(conta)) => {
+                    self.arvore.inserir(id.as_bytes(), conta.codificar());
+                    self.contas.insert(id, conta);
+                }
+                Alteracao::Conta(id, None) => {
+                    self.arvore.remover(id.as_bytes());
+                    self.contas.remove(&id);
+                }
+                Alteracao::Stake(endereco, Some(stake)) => {
+                    self.arvore.inserir(&Self::chave_stake(&endereco), stake.codificar());
+                    self.stakes.insert(endereco, stake);
+                }
+                Alteracao::Stake(endereco, None) => {
+                    self.arvore.remover(&Self::chave_stake(&endereco));
+                    self.stakes.remove(&endereco);
+                }
+                Alteracao::Altura(altura) => self.altura = altura,
+            }
+        }
+    }
+
+    fn consultar(&self, chave: &[u8]) -> Option<Vec<u8>> {
+        self.arvore.obter(chave).map(<[u8]>::to_vec)
+    }
+
+    fn raiz_estado(&self) -> Vec<u8> {
+        self.arvore.raiz()
+    }
+
+    fn provar(&self, chave: &[u8]) -> Option<ProvaEstado> {
+        Some(self.arvore.provar(chave))
+    }
+
+    fn entradas(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut entradas: Vec<(Vec<u8>, Vec<u8>)> = self.contas
+            .iter()
+            .map(|(id, conta)| (id.as_bytes().to_vec(), conta.codificar()))
+            .chain(self.stakes.iter().map(|(endereco, stake)| (Self::chave_stake(endereco), stake.codificar())))
+            .collect();
+        entradas.sort();
+        entradas
+    }
+
+    fn stakes(&self) -> HashMap<NodeId, u64> {
+        self.stakes
+            .iter()
+            .filter(|(_, stake)| stake.vinculado > 0)
+            .filter_map(|(endereco, stake)| Some((NodeId::de_endereco(endereco)?, stake.vinculado)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::chaves;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::tipos::Transferencia;
+    use std::collections::HashMap;
+
+    #[test]
+    fn lote_igual_a_aplicacao_uma_a_uma_e_descarte_restaura() {
+        let chaves = chaves(3);
+        let endereco = |i: usize| chaves[i].no_id().to_string();
+        let produtor = endereco(2);
+
+        let saldos: HashMap<String, u64> = [(endereco(0), 500), (endereco(1), 100)].into_iter().collect();
+        let transacoes: Vec<Transacao> = [
+            Transferencia::nova(&chaves[0], endereco(1), 300, 5, 0),
+            Transferencia::nova(&chaves[1], endereco(0), 350, 5, 0),
+            Transferencia::nova(&chaves[0], endereco(1), 600, 1, 1),
+        ]
+        .into_iter()
+        .map(Transacao::de_transferencia)
+        .collect();
+
+        let mut uma_a_uma = MaquinaTransferencias::com_saldos(&saldos);
+        let esperadas: Vec<Option<Transacao>> = transacoes.iter().map(|t| uma_a_uma.aplicar(t, &produtor).ok()).collect();
+
+        let mut em_lote = MaquinaTransferencias::com_saldos(&saldos);
+        let raiz_inicial = em_lote.raiz_estado();
+        let obtidas: Vec<Option<Transacao>> = em_lote.aplicar_lote(&transacoes, &produtor).into_iter().map(Result::ok).collect();
+
+        assert_eq!(obtidas.iter().filter(|t| t.is_none()).count(), 1);
+        for (obtida, esperada) in obtidas.iter().zip(&esperadas) {
+            assert_eq!(
+                obtida.as_ref().map(|t| (&t.estado_anterior, &t.estado_final)),
+                esperada.as_ref().map(|t| (&t.estado_anterior, &t.estado_final))
+            );
+        }
+        assert_eq!(em_lote.raiz_estado(), uma_a_uma.raiz_estado());
+
+        em_lote.descartar();
+        assert_eq!(em_lote.raiz_estado(), raiz_inicial);
+        assert_eq!(em_lote.obter_conta(&endereco(0)), Conta { saldo: 500, nonce: 0 });
+    }
+
+    #[test]
+    fn stake_vinculado_conta_na_raiz_e_volta_ao_saldo_depois_do_desvinculo() {
+        use crate::consenso::ordenacao::BLOCOS_DESVINCULO;
+
+        let chaves = ParChaves::gerar();
+        let endereco = chaves.no_id().to_string();
+        let produtor = "produtor";
+
+        let mut maquina = MaquinaTransferencias::com_saldos(&HashMap::from([(endereco.clone(), 1_000)]));
+        let aplicar = |maquina: &mut MaquinaTransferencias, transferencia: Transferencia| {
+            maquina.aplicar(&Transacao::de_transferencia(transferencia), produtor)
+        };
+
+        maquina.iniciar_bloco(1);
+        aplicar(&mut maquina, Transferencia::vincular_stake(&chaves, 600, 10, 0)).unwrap();
+        maquina.confirmar();
+
+        assert_eq!(maquina.obter_conta(&endereco), Conta { saldo: 390, nonce: 1 });
+        assert_eq!(maquina.obter_conta(produtor).saldo, 10);
+        assert_eq!(maquina.stakes(), HashMap::from([(chaves.no_id(), 600)]));
+        assert!(maquina.provar(format!("{}{}", PREFIXO_STAKE, endereco).as_bytes()).unwrap().verificar(&maquina.raiz_estado()));
+
+        // Desvincular mais do que o vinculado é recusado; o desvínculo só cobra a taxa
+        maquina.iniciar_bloco(2);
+        assert!(aplicar(&mut maquina, Transferencia::desvincular_stake(&chaves, 601, 0, 1)).is_err());
+        aplicar(&mut maquina, Transferencia::desvincular_stake(&chaves, 200, 5, 1)).unwrap();
+        maquina.confirmar();
+
+        assert_eq!(maquina.obter_conta(&endereco), Conta { saldo: 385, nonce: 2 });
+        assert_eq!(maquina.stakes(), HashMap::from([(chaves.no_id(), 400)]));
+        assert_eq!(maquina.obter_stake(&endereco).desvinculando, vec![(200, 2 + BLOCOS_DESVINCULO)]);
+
+        // Antes do prazo nada volta; no bloco do prazo volta, e o descarte desfaz
+        maquina.iniciar_bloco(1 + BLOCOS_DESVINCULO);
+        maquina.confirmar();
+        assert_eq!(maquina.obter_conta(&endereco).saldo, 385);
+
+        let raiz_antes = maquina.raiz_estado();
+        maquina.iniciar_bloco(2 + BLOCOS_DESVINCULO);
+        assert_eq!(maquina.obter_conta(&endereco).saldo, 585);
+        assert!(maquina.obter_stake(&endereco).desvinculando.is_empty());
+
+        maquina.descartar();
+        assert_eq!(maquina.raiz_estado(), raiz_antes);
+        assert_eq!(maquina.obter_stake(&endereco).desvinculando.len(), 1);
+    }
+
+    #[test]
+    fn lote_com_stake_igual_a_aplicacao_uma_a_uma() {
+        let chaves = chaves(2);
+        let endereco = |i: usize| chaves[i].no_id().to_string();
+        let saldos: HashMap<String, u64> = [(endereco(0), 500), (endereco(1), 500)].into_iter().collect();
+
+        let transacoes: Vec<Transacao> = [
+            Transferencia::nova(&chaves[0], endereco(1), 100, 1, 0),
+            Transferencia::vincular_stake(&chaves[1], 550, 1, 0),
+            Transferencia::vincular_stake(&chaves[0], 300, 1, 1),
+            Transferencia::desvincular_stake(&chaves[1], 50, 1, 1),
+            // Conta comum não pode escrever numa chave de stake
+            Transferencia::nova(&chaves[0], format!("{}{}", PREFIXO_STAKE, endereco(0)), 1, 0, 2),
+        ]
+        .into_iter()
+        .map(Transacao::de_transferencia)
+        .collect();
+
+        let mut uma_a_uma = MaquinaTransferencias::com_saldos(&saldos);
+        uma_a_uma.iniciar_bloco(1);
+        let esperadas: Vec<bool> = transacoes.iter().map(|t| uma_a_uma.aplicar(t, "produtor").is_ok()).collect();
+        assert_eq!(esperadas, vec![true, true, true, true, false]);
+
+        let mut em_lote = MaquinaTransferencias::com_saldos(&saldos);
+        em_lote.iniciar_bloco(1);
+        let obtidas: Vec<bool> = em_lote.aplicar_lote(&transacoes, "produtor").iter().map(Result::is_ok).collect();
+
+        assert_eq!(obtidas, esperadas);
+        assert_eq!(em_lote.raiz_estado(), uma_a_uma.raiz_estado());
+        assert_eq!(em_lote.entradas(), uma_a_uma.entradas());
+        assert_eq!(em_lote.stakes().values().sum::<u64>(), 300 + 500);
+    }
+}
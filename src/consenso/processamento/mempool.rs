@@ -0,0 +1,487 @@
+use super::estado::Conta;
+use crate::consenso::tipos::Transacao;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use tokio::sync::RwLock;
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct ConfiguracaoMempool {
+    /// Total de transações pendentes no pool
+    pub capacidade: usize,
+    /// Transações pendentes por conta de origem; também limita o quanto o nonce pode se adiantar
+    pub maximo_por_conta: usize,
+    /// Tamanho máximo, em bytes, dos dados de uma transação
+    pub tamanho_maximo_transacao: usize,
+    /// Transações mais antigas que isso são descartadas
+    pub idade_maxima: chrono::Duration,
+    /// Aumento mínimo de taxa, em porcento, para substituir uma transação com o mesmo nonce
+    pub aumento_minimo_substituicao: u64,
+}
+
+impl Default for ConfiguracaoMempool {
+    fn default() -> Self {
+        Self {
+            capacidade: 10_000,
+            maximo_por_conta: 64,
+            tamanho_maximo_transacao: 64 * 1024,
+            idade_maxima: chrono::Duration::minutes(30),
+            aumento_minimo_substituicao: 10,
+        }
+    }
+}
+
+/// O que aconteceu com uma transação oferecida ao pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultadoInsercao {
+    Adicionada,
+    /// Tomou o lugar da transação de mesmo nonce, cujo id é informado
+    Substituiu(String),
+    /// Já estava no pool
+    Duplicada,
+}
+
+#[derive(Debug, Clone)]
+struct Pendente {
+    transacao: Transacao,
+    taxa: u64,
+    recebida_em: DateTime<Utc>,
+}
+
+/// Transações de transferência aguardando um bloco.
+///
+/// Cada conta de origem tem sua fila ordenada por nonce; entre contas, a ordem
+/// é pela taxa. Só entra no bloco uma transação cujo nonce segue o da conta (ou
+/// o da transação anterior da mesma conta já selecionada).
+pub struct Mempool {
+    filas: RwLock<HashMap<String, BTreeMap<u64, Pendente>>>,
+    configuracao: ConfiguracaoMempool,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::new_com_configuracao(ConfiguracaoMempool::default())
+    }
+
+    pub fn new_com_configuracao(configuracao: ConfiguracaoMempool) -> Self {
+        Self {
+            filas: RwLock::new(HashMap::new()),
+            configuracao,
+        }
+    }
+
+    /// Oferece uma transação ao pool; `conta_origem` é o estado atual da conta que paga.
+    ///
+    /// Com o pool cheio, a transação só entra se pagar mais que a pendente de menor
+    /// taxa, que é descartada.
+    pub async fn adicionar(&self, transacao: Transacao, conta_origem: Conta) -> Result<ResultadoInsercao> {
+        let transferencia = transacao.transferencia.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Transação {} não é uma transferência", transacao.id))?;
+
+        if transacao.dados.len() > self.configuracao.tamanho_maximo_transacao {
+            return Err(anyhow::anyhow!(
+                "Transação {} com {} bytes excede o limite de {}",
+                transacao.id, transacao.dados.len(), self.configuracao.tamanho_maximo_transacao
+            ));
+        }
+
+        if !transacao.corresponde_a_transferencia() {
+            return Err(anyhow::anyhow!("Id ou dados da transação {} não correspondem à transferência", transacao.id));
+        }
+
+        if !transferencia.verificar_assinatura() {
+            return Err(anyhow::anyhow!("Assinatura da transferência {} inválida", transacao.id));
+        }
+
+        if transferencia.nonce < conta_origem.nonce {
+            return Err(anyhow::anyhow!(
+                "Nonce {} já usado pela conta {} (atual {})",
+                transferencia.nonce, transferencia.de, conta_origem.nonce
+            ));
+        }
+
+        if transferencia.nonce - conta_origem.nonce >= self.configuracao.maximo_por_conta as u64 {
+            return Err(anyhow::anyhow!("Nonce {} adiantado demais para a conta {}", transferencia.nonce, transferencia.de));
+        }
+
+        let custo = transferencia.debito()
+            .ok_or_else(|| anyhow::anyhow!("Valor mais taxa excede o limite"))?;
+        if custo > conta_origem.saldo {
+            return Err(anyhow::anyhow!("Saldo insuficiente na conta {}", transferencia.de));
+        }
+
+        let de = transferencia.de.clone();
+        let nonce = transferencia.nonce;
+        let pendente = Pendente {
+            taxa: transferencia.taxa,
+            transacao,
+            recebida_em: Utc::now(),
+        };
+
+        let mut filas = self.filas.write().await;
+
+        // Substituição por taxa: mesmo remetente e nonce, taxa suficientemente maior
+        if let Some(anterior) = filas.get(&de).and_then(|fila| fila.get(&nonce)) {
+            if anterior.transacao.id == pendente.transacao.id {
+                return Ok(ResultadoInsercao::Duplicada);
+            }
+
+            let minima = anterior.taxa.saturating_mul(100 + self.configuracao.aumento_minimo_substituicao) / 100;
+            if pendente.taxa <= anterior.taxa || pendente.taxa < minima {
+                return Err(anyhow::anyhow!(
+                    "Substituição do nonce {} da conta {} exige taxa de pelo menos {}",
+                    nonce, de, minima.max(anterior.taxa + 1)
+                ));
+            }
+
+            let id_anterior = anterior.transacao.id.clone();
+            filas.entry(de.clone()).or_default().insert(nonce, pendente);
+            info!("Transação {} substituída no mempool", id_anterior);
+            return Ok(ResultadoInsercao::Substituiu(id_anterior));
+        }
+
+        if filas.get(&de).map_or(0, |fila| fila.len()) >= self.configuracao.maximo_por_conta {
+            return Err(anyhow::anyhow!("Conta {} atingiu o limite de transações pendentes", de));
+        }
+
+        if Self::contar(&filas) >= self.configuracao.capacidade
+            && !Self::despejar_menor_taxa(&mut filas, pendente.taxa)
+        {
+            return Err(anyhow::anyhow!("Mempool cheio; taxa {} insuficiente", pendente.taxa));
+        }
+
+        filas.entry(de).or_default().insert(nonce, pendente);
+        Ok(ResultadoInsercao::Adicionada)
+    }
+
+    /// Até `limite` transações executáveis, as de maior taxa primeiro.
+    ///
+    /// `nonces` traz o nonce atual de cada conta de origem (ausente vale zero). As
+    /// transações continuam no pool até `remover_confirmadas`.
+    pub async fn selecionar(&self, limite: usize, nonces: &HashMap<String, u64>) -> Vec<Transacao> {
+        let filas = self.filas.read().await;
+        let mut proximos: HashMap<&str, u64> = HashMap::new();
+        let mut candidatas = BinaryHeap::new();
+
+        // Só a cabeça de cada fila concorre; a seguinte entra quando ela é escolhida
+        for (de, fila) in filas.iter() {
+            let nonce = nonces.get(de).copied().unwrap_or(0);
+            if let Some(pendente) = fila.get(&nonce) {
+                candidatas.push((pendente.taxa, Reverse(pendente.recebida_em), Reverse(de.as_str())));
+                proximos.insert(de, nonce);
+            }
+        }
+
+        let mut selecionadas = Vec::new();
+        while selecionadas.len() < limite {
+            let Some((_, _, Reverse(de))) = candidatas.pop() else {
+                break;
+            };
+
+            let nonce = proximos[de];
+            selecionadas.push(filas[de][&nonce].transacao.clone());
+
+            if let Some(seguinte) = filas[de].get(&(nonce + 1)) {
+                candidatas.push((seguinte.taxa, Reverse(seguinte.recebida_em), Reverse(de)));
+                proximos.insert(de, nonce + 1);
+            }
+        }
+
+        selecionadas
+    }
+
+    /// Tira do pool as transações incluídas num bloco e as que ficaram com nonce obsoleto
+    pub async fn remover_confirmadas(&self, transacoes: &[Transacao]) {
+        let mut filas = self.filas.write().await;
+
+        for transferencia in transacoes.iter().filter_map(|t| t.transferencia.as_ref()) {
+            if let Some(fila) = filas.get_mut(&transferencia.de) {
+                *fila = fila.split_off(&(transferencia.nonce + 1));
+                if fila.is_empty() {
+                    filas.remove(&transferencia.de);
+                }
+            }
+        }
+    }
+
+    /// Descarta uma transação que falhou na execução e as seguintes da mesma conta
+    pub async fn descartar(&self, transacao: &Transacao) {
+        let Some(transferencia) = &transacao.transferencia else {
+            return;
+        };
+
+        let mut filas = self.filas.write().await;
+        if let Some(fila) = filas.get_mut(&transferencia.de) {
+            fila.split_off(&transferencia.nonce);
+            if fila.is_empty() {
+                filas.remove(&transferencia.de);
+            }
+        }
+    }
+
+    /// Descarta as transações recebidas há mais de `idade_maxima`, junto com as
+    /// seguintes da mesma conta, que não teriam mais como ser executadas
+    pub async fn remover_expiradas(&self, agora: DateTime<Utc>) -> usize {
+        let limite = agora - self.configuracao.idade_maxima;
+        let mut filas = self.filas.write().await;
+        let antes = Self::contar(&filas);
+
+        filas.retain(|_, fila| {
+            if let Some(nonce) = fila.iter().find(|(_, p)| p.recebida_em < limite).map(|(n, _)| *n) {
+                fila.split_off(&nonce);
+            }
+            !fila.is_empty()
+        });
+
+        let removidas = antes - Self::contar(&filas);
+        if removidas > 0 {
+            info!("{} transações expiradas removidas do mempool", removidas);
+        }
+        removidas
+    }
+
+    pub async fn contem(&self, id_transacao: &str) -> bool {
+        self.filas.read().await.values()
+            .any(|fila| fila.values().any(|p| p.transacao.id == id_transacao))
+    }
+
+    pub async fn quantidade(&self) -> usize {
+        Self::contar(&*self.filas.read().await)
+    }
+
+    /// Contas com transações pendentes, para consultar os nonces antes de `selecionar`
+    pub async fn remetentes(&self) -> Vec<String> {
+        self.filas.read().await.keys().cloned().collect()
+    }
+
+    fn contar(filas: &HashMap<String, BTreeMap<u64, Pendente>>) -> usize {
+        filas.values().map(BTreeMap::len).sum()
+    }
+
+    /// Descarta a transação de maior nonce de menor taxa, se ela pagar menos que `taxa`.
+    ///
+    /// Só a última de cada conta é considerada, para não abrir lacunas de nonce.
+    fn despejar_menor_taxa(filas: &mut HashMap<String, BTreeMap<u64, Pendente>>, taxa: u64) -> bool {
+        let vitima = filas.iter()
+            .filter_map(|(de, fila)| fila.iter().next_back().map(|(nonce, p)| (p.taxa, Reverse(p.recebida_em), de.clone(), *nonce)))
+            .min();
+
+        match vitima {
+            Some((taxa_vitima, _, de, nonce)) if taxa_vitima < taxa => {
+                if let Some(fila) = filas.get_mut(&de) {
+                    if let Some(removida) = fila.remove(&nonce) {
+                        info!("Transação {} despejada do mempool (taxa {})", removida.transacao.id, taxa_vitima);
+                    }
+                    if fila.is_empty() {
+                        filas.remove(&de);
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::tipos::Transferencia;
+
+    const RICA: Conta = Conta { saldo: 1_000_000, nonce: 0 };
+
+    fn transferencia(chaves: &ParChaves, taxa: u64, nonce: u64) -> Transacao {
+        Transacao::de_transferencia(Transferencia::nova(chaves, "destino".to_string(), 10, taxa, nonce))
+    }
+
+    fn configuracao(capacidade: usize, maximo_por_conta: usize) -> ConfiguracaoMempool {
+        ConfiguracaoMempool {
+            capacidade,
+            maximo_por_conta,
+            ..ConfiguracaoMempool::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn id_ou_dados_diferentes_da_transferencia_sao_recusados() {
+        let mempool = Mempool::new();
+        let chaves = ParChaves::gerar();
+
+        let mut outro_id = transferencia(&chaves, 1, 0);
+        outro_id.id = "escolhido pelo remetente".to_string();
+        assert!(mempool.adicionar(outro_id, RICA).await.is_err());
+
+        let mut outros_dados = transferencia(&chaves, 1, 0);
+        outros_dados.dados = b"qualquer coisa".to_vec();
+        assert!(mempool.adicionar(outros_dados, RICA).await.is_err());
+
+        let mut outro_nonce = transferencia(&chaves, 1, 0);
+        outro_nonce.nonce = 5;
+        assert!(mempool.adicionar(outro_nonce, RICA).await.is_err());
+
+        let mut sem_transferencia = transferencia(&chaves, 1, 0);
+        sem_transferencia.transferencia = None;
+        assert!(mempool.adicionar(sem_transferencia, RICA).await.is_err());
+
+        let valida = transferencia(&chaves, 1, 0);
+        assert_eq!(mempool.adicionar(valida.clone(), RICA).await.unwrap(), ResultadoInsercao::Adicionada);
+        assert_eq!(mempool.adicionar(valida, RICA).await.unwrap(), ResultadoInsercao::Duplicada);
+    }
+
+    #[tokio::test]
+    async fn substituicao_exige_o_aumento_minimo_de_taxa() {
+        let mempool = Mempool::new();
+        let chaves = ParChaves::gerar();
+
+        let original = transferencia(&chaves, 100, 0);
+        mempool.adicionar(original.clone(), RICA).await.unwrap();
+
+        // 10% sobre 100: 109 não basta, 110 substitui
+        assert!(mempool.adicionar(transferencia(&chaves, 109, 0), RICA).await.is_err());
+        let nova = transferencia(&chaves, 110, 0);
+        assert_eq!(
+            mempool.adicionar(nova.clone(), RICA).await.unwrap(),
+            ResultadoInsercao::Substituiu(original.id.clone())
+        );
+        assert!(!mempool.contem(&original.id).await);
+        assert!(mempool.contem(&nova.id).await);
+        assert_eq!(mempool.quantidade().await, 1);
+
+        // Taxa baixa: o mínimo ainda é uma unidade acima da anterior
+        let barata = transferencia(&chaves, 1, 1);
+        mempool.adicionar(barata, RICA).await.unwrap();
+        let mesma_taxa = Transferencia::nova(&chaves, "outro destino".to_string(), 10, 1, 1);
+        assert!(mempool.adicionar(Transacao::de_transferencia(mesma_taxa), RICA).await.is_err());
+        assert!(matches!(
+            mempool.adicionar(transferencia(&chaves, 2, 1), RICA).await.unwrap(),
+            ResultadoInsercao::Substituiu(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn nonce_usado_adiantado_demais_ou_sem_saldo_e_recusado() {
+        let mempool = Mempool::new_com_configuracao(configuracao(100, 4));
+        let chaves = ParChaves::gerar();
+        let conta = Conta { saldo: 1_000, nonce: 5 };
+
+        assert!(mempool.adicionar(transferencia(&chaves, 1, 4), conta).await.is_err());
+        assert!(mempool.adicionar(transferencia(&chaves, 1, 9), conta).await.is_err());
+        assert!(mempool.adicionar(transferencia(&chaves, 1, 8), conta).await.is_ok());
+        assert!(mempool.adicionar(transferencia(&chaves, 991, 5), conta).await.is_err());
+        assert!(mempool.adicionar(transferencia(&chaves, 990, 5), conta).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn selecionar_para_na_lacuna_de_nonce() {
+        let mempool = Mempool::new();
+        let chaves = ParChaves::gerar();
+        let de = endereco(&chaves);
+
+        for nonce in [0, 1, 3] {
+            mempool.adicionar(transferencia(&chaves, 5, nonce), RICA).await.unwrap();
+        }
+
+        let nonces = |n: u64| HashMap::from([(de.clone(), n)]);
+        let selecionadas: Vec<u64> = mempool.selecionar(10, &nonces(0)).await.iter().map(|t| t.nonce).collect();
+        assert_eq!(selecionadas, vec![0, 1]);
+
+        // Com o nonce da conta já em 2, nada é executável até chegar o nonce 2
+        assert!(mempool.selecionar(10, &nonces(2)).await.is_empty());
+        assert_eq!(mempool.selecionar(10, &nonces(3)).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn selecionar_ordena_por_taxa_respeitando_o_nonce() {
+        let mempool = Mempool::new();
+        let (a, b) = (ParChaves::gerar(), ParChaves::gerar());
+
+        // A paga pouco no nonce 0 e muito no 1; B paga o meio-termo
+        mempool.adicionar(transferencia(&a, 1, 0), RICA).await.unwrap();
+        mempool.adicionar(transferencia(&a, 50, 1), RICA).await.unwrap();
+        mempool.adicionar(transferencia(&b, 10, 0), RICA).await.unwrap();
+        mempool.adicionar(transferencia(&b, 5, 1), RICA).await.unwrap();
+
+        let taxas: Vec<u64> = mempool.selecionar(10, &HashMap::new()).await
+            .iter()
+            .map(|t| t.transferencia.as_ref().unwrap().taxa)
+            .collect();
+        assert_eq!(taxas, vec![10, 5, 1, 50]);
+
+        assert_eq!(mempool.selecionar(2, &HashMap::new()).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn limite_por_conta_e_despejo_quando_cheio() {
+        let mempool = Mempool::new_com_configuracao(configuracao(3, 2));
+        let (a, b, c) = (ParChaves::gerar(), ParChaves::gerar(), ParChaves::gerar());
+
+        mempool.adicionar(transferencia(&a, 5, 0), RICA).await.unwrap();
+        mempool.adicionar(transferencia(&a, 2, 1), RICA).await.unwrap();
+        assert!(mempool.adicionar(transferencia(&a, 9, 2), RICA).await.is_err());
+
+        mempool.adicionar(transferencia(&b, 4, 0), RICA).await.unwrap();
+        assert_eq!(mempool.quantidade().await, 3);
+
+        // Cheio: taxa igual à menor não entra; maior despeja a última de menor taxa (A, nonce 1)
+        assert!(mempool.adicionar(transferencia(&c, 2, 0), RICA).await.is_err());
+        mempool.adicionar(transferencia(&c, 3, 0), RICA).await.unwrap();
+        assert_eq!(mempool.quantidade().await, 3);
+
+        let nonces_a: Vec<u64> = mempool.selecionar(10, &HashMap::new()).await
+            .iter()
+            .filter(|t| t.transferencia.as_ref().unwrap().de == endereco(&a))
+            .map(|t| t.nonce)
+            .collect();
+        assert_eq!(nonces_a, vec![0]);
+
+        // Despejo nunca abre lacuna: a cabeça de A (taxa 5) fica, sai a de C (taxa 3)
+        mempool.adicionar(transferencia(&b, 6, 1), RICA).await.unwrap();
+        assert!(mempool.remetentes().await.iter().all(|de| *de != endereco(&c)));
+    }
+
+    #[tokio::test]
+    async fn expiradas_levam_junto_as_seguintes_da_conta() {
+        let mempool = Mempool::new();
+        let (a, b) = (ParChaves::gerar(), ParChaves::gerar());
+
+        mempool.adicionar(transferencia(&a, 1, 0), RICA).await.unwrap();
+        mempool.adicionar(transferencia(&a, 1, 1), RICA).await.unwrap();
+        assert_eq!(mempool.remover_expiradas(Utc::now()).await, 0);
+
+        let depois = Utc::now() + chrono::Duration::minutes(31);
+        mempool.adicionar(transferencia(&b, 1, 0), RICA).await.unwrap();
+        assert_eq!(mempool.remover_expiradas(depois).await, 3);
+        assert_eq!(mempool.quantidade().await, 0);
+    }
+
+    #[tokio::test]
+    async fn confirmadas_e_descartadas_saem_do_pool() {
+        let mempool = Mempool::new();
+        let chaves = ParChaves::gerar();
+        let transacoes: Vec<Transacao> = (0..4).map(|nonce| transferencia(&chaves, 1, nonce)).collect();
+
+        for transacao in &transacoes {
+            mempool.adicionar(transacao.clone(), RICA).await.unwrap();
+        }
+
+        mempool.remover_confirmadas(&transacoes[1..2]).await;
+        assert_eq!(mempool.quantidade().await, 2);
+        assert!(!mempool.contem(&transacoes[0].id).await);
+
+        mempool.descartar(&transacoes[2]).await;
+        assert_eq!(mempool.quantidade().await, 0);
+    }
+
+    fn endereco(chaves: &ParChaves) -> String {
+        crate::consenso::tipos::endereco_da_chave(&chaves.chave_publica())
+    }
+}
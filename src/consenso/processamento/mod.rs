@@ -1,75 +1,553 @@
 mod processador;
 mod rotacao;
 mod estado;
+mod transicao;
+mod mempool;
+mod salto;
+mod paralelo;
+mod maquina;
 
 use crate::consenso::tipos::*;
+use crate::consenso::cripto::{Assinador, ParChaves};
+use crate::consenso::validacao::DetectorMaliciosos;
+use crate::recompensas::CamadaRecompensas;
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 
 pub use processador::*;
 pub use rotacao::*;
 pub use estado::*;
+pub use mempool::*;
+pub use salto::*;
+pub use maquina::*;
+
+/// Transações que o processador da vez tira do mempool por bloco
+pub const TRANSACOES_POR_BLOCO_PADRAO: usize = 500;
+
+/// Transações executadas pelo processador da vez, prontas para virar um bloco
+#[derive(Debug, Clone)]
+pub struct LoteProcessado {
+    pub transacoes: Vec<Transacao>,
+    /// Raiz do estado da máquina depois das transações
+    pub raiz_estado: Vec<u8>,
+}
+
+/// Transações do bloco anexado na rodada, com o hash da cadeia antes delas,
+/// para a fase de validação reconferir
+#[derive(Debug, Clone)]
+pub struct LoteConfirmado {
+    pub transacoes: Vec<Transacao>,
+    pub hash_cadeia_anterior: Vec<u8>,
+}
 
 pub struct CamadaProcessamento {
     processador: ProcessadorTransacao,
     rotacao: GerenciadorRotacao,
     estado: GerenciadorEstado,
+    mempool: Mempool,
+    saltos: GerenciadorSaltos,
+    /// Rodada da fila em rotação; votos de salto de outras rodadas são recusados
+    rodada: RwLock<u64>,
+    /// Nós da fila da rodada, na ordem original, que votam os saltos
+    nos_fila: RwLock<Vec<NodeId>>,
+    detector_maliciosos: Arc<DetectorMaliciosos>,
     hash_cadeia_anterior: RwLock<Vec<u8>>,
+    /// Lote do bloco anexado na rodada atual, se já houve
+    lote_confirmado: RwLock<Option<LoteConfirmado>>,
+    /// Número do último bloco confirmado; o lote deste nó forma o seguinte
+    altura: RwLock<u64>,
+    /// Descontam os processadores pulados gravados nos blocos anexados
+    recompensas: RwLock<Option<Arc<CamadaRecompensas>>>,
 }
 
 impl CamadaProcessamento {
-    pub fn new() -> Self {
+    pub fn new(assinador: Assinador, detector_maliciosos: Arc<DetectorMaliciosos>) -> Self {
+        Self::new_com_maquina(assinador, detector_maliciosos, Box::new(MaquinaTransferencias::new()))
+    }
+    
+    /// Processamento sobre outra lógica de aplicação que não as transferências de tokens
+    pub fn new_com_maquina(
+        assinador: Assinador,
+        detector_maliciosos: Arc<DetectorMaliciosos>,
+        maquina: Box<dyn MaquinaEstado>,
+    ) -> Self {
         Self {
-            processador: ProcessadorTransacao::new(),
+            processador: ProcessadorTransacao::new(assinador),
             rotacao: GerenciadorRotacao::new(),
-            estado: GerenciadorEstado::new(),
+            estado: GerenciadorEstado::new_com_maquina(maquina),
+            mempool: Mempool::new(),
+            saltos: GerenciadorSaltos::new(),
+            rodada: RwLock::new(0),
+            nos_fila: RwLock::new(Vec::new()),
+            detector_maliciosos,
             hash_cadeia_anterior: RwLock::new(vec![0; 32]), // Genesis hash
+            lote_confirmado: RwLock::new(None),
+            altura: RwLock::new(0),
+            recompensas: RwLock::new(None),
+        }
+    }
+    
+    /// Recompensas que descontam os processadores pulados de cada bloco anexado
+    pub async fn definir_recompensas(&self, recompensas: Arc<CamadaRecompensas>) {
+        *self.recompensas.write().await = Some(recompensas);
+    }
+    
+    /// Reinicia a rotação de processadores com a fila ordenada da rodada
+    pub async fn inicializar_rotacao(&self, rodada: u64, fila: &FilaOrdenada) {
+        *self.rodada.write().await = rodada;
+        *self.nos_fila.write().await = fila.nos.iter().map(|no| no.id).collect();
+        self.saltos.limpar_votos().await;
+        *self.lote_confirmado.write().await = None;
+        self.rotacao.inicializar_fila(fila.nos.clone()).await;
+    }
+    
+    /// Slot atual da rodada; muda quando o processador da vez produz o bloco ou é pulado
+    pub async fn slot_atual(&self) -> u64 {
+        self.rotacao.obter_slot().await
+    }
+    
+    pub async fn blocos_na_rodada(&self) -> u64 {
+        self.rotacao.obter_blocos_rodada().await
+    }
+    
+    pub async fn tamanho_fila(&self) -> usize {
+        self.nos_fila.read().await.len()
+    }
+    
+    /// Vota para pular o processador da vez, que perdeu o prazo do slot.
+    ///
+    /// O voto já conta localmente; devolve-o para ser transmitido, ou `None` se
+    /// este nó não está na fila ou já votou neste slot.
+    pub async fn votar_salto(&self, chaves: &ParChaves) -> Result<Option<VotoSalto>> {
+        let Some(processador) = self.rotacao.obter_proximo_processador().await else {
+            return Ok(None);
+        };
+        
+        let rodada = *self.rodada.read().await;
+        let slot = self.rotacao.obter_slot().await;
+        if !self.nos_fila.read().await.contains(&chaves.no_id())
+            || self.saltos.ja_votou(rodada, slot, processador.id, &chaves.no_id()).await
+        {
+            return Ok(None);
         }
+        
+        let voto = VotoSalto::novo(chaves, rodada, slot, processador.id);
+        self.adicionar_voto_salto(voto.clone()).await?;
+        
+        Ok(Some(voto))
     }
     
-    pub async fn processar_transacoes(&self, fila: &FilaOrdenada) -> Result<()> {
-        info!("Iniciando processamento rotativo de transações");
+    /// Voto de salto recebido de um nó da fila; devolve `true` se com ele o
+    /// processador da vez foi pulado
+    pub async fn adicionar_voto_salto(&self, voto: VotoSalto) -> Result<bool> {
+        if !voto.verificar() {
+            return Err(anyhow::anyhow!("Assinatura do voto de salto de {} inválida", voto.no_id));
+        }
         
-        if let Some(no_processador) = fila.nos.first() {
-            // Simular transação para processamento
-            let transacao = self.criar_transacao_exemplo().await;
-            
-            // Processar transação
-            let resultado = self.processador.processar(&transacao, &no_processador.id).await?;
+        let rodada = *self.rodada.read().await;
+        let slot = self.rotacao.obter_slot().await;
+        if voto.rodada != rodada || voto.slot != slot {
+            return Err(anyhow::anyhow!(
+                "Voto de salto de {} para rodada {} slot {}; atual é rodada {} slot {}",
+                voto.no_id, voto.rodada, voto.slot, rodada, slot
+            ));
+        }
+        
+        let processador = self.rotacao.obter_proximo_processador().await
+            .ok_or_else(|| anyhow::anyhow!("Nenhum processador a pular nesta rodada"))?;
+        if voto.processador != processador.id {
+            return Err(anyhow::anyhow!("Voto de salto para {}, mas a vez é de {}", voto.processador, processador.id));
+        }
+        
+        let nos_fila = self.nos_fila.read().await.clone();
+        if !nos_fila.contains(&voto.no_id) {
+            return Err(anyhow::anyhow!("Nó {} fora da fila da rodada não vota salto", voto.no_id));
+        }
+        
+        match self.saltos.adicionar_voto(voto, &nos_fila).await {
+            Some(evidencia) => {
+                self.registrar_salto(&evidencia).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+    
+    /// Evidências de salto da rodada ainda fora da cadeia, para o bloco deste nó.
+    ///
+    /// As de rodadas anteriores são descartadas: sem o certificado daquela fila
+    /// não há como conferi-las.
+    pub async fn retirar_evidencias_salto(&self, rodada: u64) -> Vec<EvidenciaSalto> {
+        self.saltos.retirar_evidencias().await
+            .into_iter()
+            .filter(|evidencia| evidencia.rodada == rodada)
+            .collect()
+    }
+    
+    /// Passa a vez ao próximo da fila. A punição do processador só vem com o
+    /// bloco que grava a evidência (`confirmar_lote`)
+    async fn registrar_salto(&self, evidencia: &EvidenciaSalto) {
+        warn!(
+            "Processador {} pulado no slot {} da rodada {} ({} votos)",
+            evidencia.processador, evidencia.slot, evidencia.rodada, evidencia.votos.len()
+        );
+        
+        self.rotacao.pular_processador().await;
+    }
+    
+    /// Máquina de estado das transações, compartilhada com a validação
+    pub fn maquina_estado(&self) -> MaquinaCompartilhada {
+        self.estado.maquina()
+    }
+    
+    /// Stake de cada nó no estado confirmado, para a ordenação ponderada
+    pub async fn stakes(&self) -> HashMap<NodeId, u64> {
+        self.estado.stakes().await
+    }
+    
+    /// Raiz do estado da máquina, gravada no cabeçalho do próximo bloco
+    pub async fn raiz_estado(&self) -> Vec<u8> {
+        self.estado.raiz_estado().await
+    }
+    
+    pub async fn provar_conta(&self, id_conta: &str) -> Option<crate::consenso::merkle::ProvaEstado> {
+        self.estado.provar_conta(id_conta).await
+    }
+    
+    /// Transação recebida da rede; entra no mempool se for válida contra o estado atual
+    pub async fn receber_transacao(&self, transacao: Transacao) -> Result<ResultadoInsercao> {
+        let de = transacao.transferencia.as_ref()
+            .map(|t| t.de.clone())
+            .ok_or_else(|| anyhow::anyhow!("Transação {} não é uma transferência", transacao.id))?;
+        
+        let conta = self.estado.obter_conta(&de).await;
+        self.mempool.adicionar(transacao, conta).await
+    }
+    
+    /// Até `limite` transações do mempool executáveis sobre o estado atual, por ordem de taxa
+    pub async fn obter_transacoes_bloco(&self, limite: usize) -> Vec<Transacao> {
+        let mut nonces = HashMap::new();
+        for de in self.mempool.remetentes().await {
+            let nonce = self.estado.obter_conta(&de).await.nonce;
+            nonces.insert(de, nonce);
+        }
+        
+        self.mempool.selecionar(limite, &nonces).await
+    }
+    
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+    
+    /// Se for a vez deste nó, executa transações do mempool e devolve o lote para o bloco.
+    ///
+    /// O estado fica pendente até o bloco ser anexado: `confirmar_lote` com as
+    /// transações do bloco, ou `descartar_lote` se ele não entrou na cadeia.
+    pub async fn processar_transacoes(&self) -> Result<Option<LoteProcessado>> {
+        let Some(no_processador) = self.rotacao.obter_proximo_processador().await else {
+            return Ok(None);
+        };
+        
+        if !self.processador.e_no_local(&no_processador) {
+            info!("Vez do nó {}, aguardando processamento remoto", no_processador.id);
+            return Ok(None);
+        }
+        
+        self.mempool.remover_expiradas(chrono::Utc::now()).await;
+        let mut transacoes = Vec::new();
+        for transacao in self.obter_transacoes_bloco(TRANSACOES_POR_BLOCO_PADRAO).await {
+            match self.processador.validar_transacao(&transacao) {
+                Ok(()) => transacoes.push(transacao),
+                Err(e) => {
+                    warn!("Transação {} descartada: {}", transacao.id, e);
+                    self.mempool.descartar(&transacao).await;
+                }
+            }
+        }
+        
+        // Lote executado pela máquina de estado e assinado na ordem. Se alguma
+        // executada não puder ser assinada, o lote inteiro é desfeito e executado
+        // de novo sem ela, para o estado conter só o que vai para o bloco.
+        let numero = *self.altura.read().await + 1;
+        let processadas = loop {
+            let executadas = self.estado.executar_lote(&transacoes, numero, &no_processador.id.to_string()).await?;
             
-            // Calcular hash combinado
-            let hash_anterior = self.hash_cadeia_anterior.read().await.clone();
-            let hash_combinado = self.calcular_hash_combinado(&resultado, &hash_anterior).await;
+            let mut processadas = Vec::with_capacity(transacoes.len());
+            let mut recusadas = Vec::new();
+            for (transacao, executada) in transacoes.iter().zip(executadas) {
+                let executada = match executada {
+                    Ok(executada) => executada,
+                    Err(e) => {
+                        warn!("Transação {} descartada: {}", transacao.id, e);
+                        self.mempool.descartar(transacao).await;
+                        continue;
+                    }
+                };
+                
+                match self.processador.processar(&executada, &no_processador).await {
+                    Ok(processada) => processadas.push(processada),
+                    Err(e) => {
+                        warn!("Transação {} executada mas não assinada: {}", transacao.id, e);
+                        recusadas.push(transacao.id.clone());
+                    }
+                }
+            }
             
-            // Atualizar hash da cadeia
-            *self.hash_cadeia_anterior.write().await = hash_combinado;
+            if recusadas.is_empty() {
+                break processadas;
+            }
             
-            // Rotacionar fila
-            self.rotacao.rotacionar_fila().await;
+            self.estado.descartar().await;
+            for transacao in transacoes.iter().filter(|t| recusadas.contains(&t.id)) {
+                self.mempool.descartar(transacao).await;
+            }
+            transacoes.retain(|t| !recusadas.contains(&t.id));
+        };
+        
+        info!("{} transações processadas; aguardando o bloco ser anexado", processadas.len());
+        
+        Ok(Some(LoteProcessado {
+            transacoes: processadas,
+            raiz_estado: self.estado.raiz_estado().await,
+        }))
+    }
+    
+    /// Bloco recebido de outro nó: confere que era a vez dele e reexecuta as
+    /// transações, que precisam chegar em `raiz_estado`.
+    ///
+    /// Como em `processar_transacoes`, o resultado fica pendente até o bloco ser
+    /// anexado (`confirmar_lote`) ou recusado pela cadeia (`descartar_lote`).
+    ///
+    /// A vez esperada já conta os `saltos` gravados no bloco que este nó ainda
+    /// não tinha visto, mas a rotação só anda quando o bloco for anexado.
+    pub async fn aplicar_bloco_remoto(
+        &self,
+        transacoes: &[Transacao],
+        numero: u64,
+        minerador: NodeId,
+        raiz_estado: &[u8],
+        saltos: &[EvidenciaSalto],
+    ) -> Result<()> {
+        let processador = self.processador_apos_saltos(saltos).await
+            .ok_or_else(|| anyhow::anyhow!("Nenhum processador esperado nesta rodada"))?;
+        
+        if processador.id != minerador {
+            return Err(anyhow::anyhow!("Bloco de {} fora da vez; esperado {}", minerador, processador.id));
+        }
+        
+        for transacao in transacoes {
+            if !self.processador.verificar_assinatura_transacao(transacao, &processador) {
+                return Err(anyhow::anyhow!("Transação {} sem assinatura válida do processador", transacao.id));
+            }
             
-            info!("Transação processada e nó rotacionado");
+            // Mesma regra do mempool: id, dados e nonce precisam ser os da transferência assinada
+            if transacao.transferencia.is_some() && !transacao.corresponde_a_transferencia() {
+                return Err(anyhow::anyhow!("Transação {} não corresponde à transferência que carrega", transacao.id));
+            }
         }
         
+        self.estado.aplicar_bloco(transacoes, numero, &minerador.to_string(), raiz_estado).await?;
+        
+        info!("Bloco de {} reexecutado com {} transações", minerador, transacoes.len());
         Ok(())
     }
     
-    async fn criar_transacao_exemplo(&self) -> Transacao {
-        Transacao {
-            id: "tx_exemplo".to_string(),
-            dados: b"dados_exemplo".to_vec(),
-            estado_anterior: b"estado_anterior".to_vec(),
-            estado_final: b"estado_final".to_vec(),
-            timestamp: chrono::Utc::now(),
-            nonce: 12345,
-            assinatura: b"assinatura_exemplo".to_vec(),
+    /// O bloco `numero` com estas transações e `saltos` foi anexado: alcança a vez
+    /// do produtor, pune os processadores pulados, confirma o estado pendente e passa a vez
+    pub async fn confirmar_lote(&self, numero: u64, transacoes: &[Transacao], saltos: &[EvidenciaSalto]) {
+        self.alcancar_saltos(saltos).await;
+        self.punir_saltos(saltos).await;
+        
+        self.estado.confirmar().await;
+        *self.altura.write().await = numero;
+        self.concluir_lote(transacoes).await;
+    }
+    
+    /// Lote do bloco anexado nesta rodada, `None` se nenhum entrou na cadeia
+    pub async fn lote_confirmado(&self) -> Option<LoteConfirmado> {
+        self.lote_confirmado.read().await.clone()
+    }
+    
+    /// O bloco não entrou na cadeia: o estado volta ao do último bloco e a vez não muda
+    pub async fn descartar_lote(&self) {
+        self.estado.descartar().await;
+    }
+    
+    /// Saltos da rodada, em ordem, que seguem a vez atual sem buraco
+    async fn saltos_seguintes<'a>(&self, saltos: &'a [EvidenciaSalto]) -> Vec<&'a EvidenciaSalto> {
+        let rodada = *self.rodada.read().await;
+        let nos_fila = self.nos_fila.read().await.clone();
+        let mut slot = self.rotacao.obter_slot().await;
+        
+        let mut da_rodada: Vec<&EvidenciaSalto> = saltos.iter().filter(|e| e.rodada == rodada).collect();
+        da_rodada.sort_by_key(|evidencia| evidencia.slot);
+        
+        let mut seguintes = Vec::new();
+        for evidencia in da_rodada {
+            if evidencia.slot == slot && evidencia.confere_com_fila(&nos_fila) {
+                seguintes.push(evidencia);
+                slot += 1;
+            }
         }
+        
+        seguintes
     }
     
+    /// Processador da vez depois dos `saltos`, sem mexer na rotação
+    async fn processador_apos_saltos(&self, saltos: &[EvidenciaSalto]) -> Option<No> {
+        let pulados = self.saltos_seguintes(saltos).await.len();
+        let fila = self.rotacao.obter_fila_atual().await;
+        
+        (!fila.is_empty()).then(|| fila[pulados % fila.len()].clone())
+    }
+    
+    async fn alcancar_saltos(&self, saltos: &[EvidenciaSalto]) {
+        for evidencia in self.saltos_seguintes(saltos).await {
+            self.registrar_salto(evidencia).await;
+        }
+        
+        self.saltos.remover_gravadas(saltos).await;
+    }
+    
+    /// Único ponto em que um salto tem consequência: o bloco que o grava entrou
+    /// na cadeia. Cada evidência da fila vira uma suspeita no detector e um
+    /// desconto nas recompensas, uma vez por slot.
+    async fn punir_saltos(&self, saltos: &[EvidenciaSalto]) {
+        let nos_fila = self.nos_fila.read().await.clone();
+        let mut punidos = HashSet::new();
+        let validos: Vec<EvidenciaSalto> = saltos
+            .iter()
+            .filter(|evidencia| evidencia.confere_com_fila(&nos_fila))
+            .filter(|evidencia| punidos.insert((evidencia.rodada, evidencia.slot)))
+            .cloned()
+            .collect();
+        
+        for evidencia in &validos {
+            self.detector_maliciosos.reportar_processador_ausente(&evidencia.processador, evidencia.rodada, evidencia.slot);
+        }
+        
+        if let Some(recompensas) = self.recompensas.read().await.as_ref() {
+            recompensas.aplicar_saltos(&validos, &nos_fila).await;
+        }
+    }
+    
+    /// Encadeia o hash das transações, tira-as do mempool e passa a vez ao próximo da fila
+    async fn concluir_lote(&self, transacoes: &[Transacao]) {
+        // Calcular hash combinado
+        let hash_anterior = self.hash_cadeia_anterior.read().await.clone();
+        let mut hash_combinado = hash_anterior.clone();
+        for transacao in transacoes {
+            hash_combinado = self.calcular_hash_combinado(transacao, &hash_combinado).await;
+        }
+        
+        // Atualizar hash da cadeia
+        *self.hash_cadeia_anterior.write().await = hash_combinado;
+        *self.lote_confirmado.write().await = Some(LoteConfirmado {
+            transacoes: transacoes.to_vec(),
+            hash_cadeia_anterior: hash_anterior,
+        });
+        
+        self.mempool.remover_confirmadas(transacoes).await;
+        
+        // Rotacionar fila
+        self.rotacao.rotacionar_fila().await;
+    }
+    
+    /// Aplica a transação na máquina de estado e a assina como processador.
+    ///
+    /// `estado_anterior` e `estado_final` passam a ser os que a máquina calculou.
+    pub async fn executar_transacao(&self, transacao: &Transacao, processador: &No) -> Result<Transacao> {
+        if !self.processador.e_no_local(processador) {
+            return Err(anyhow::anyhow!("Nó {} não corresponde à chave local", processador.id));
+        }
+        
+        let transacao = self.estado
+            .aplicar_transacao(transacao, &processador.id.to_string())
+            .await?;
+        
+        self.processador.processar(&transacao, processador).await
+    }
+    
+    /// Mesmo encadeamento que os validadores recalculam
     async fn calcular_hash_combinado(&self, transacao: &Transacao, hash_anterior: &[u8]) -> Vec<u8> {
-        let mut dados = Vec::new();
-        dados.extend_from_slice(&serde_json::to_vec(transacao).unwrap_or_default());
-        dados.extend_from_slice(hash_anterior);
-        calcular_hash(&dados)
+        calcular_hash_cadeia(&transacao.hash(), hash_anterior)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::{chaves, evidencia, fila, ids};
+
+    #[tokio::test]
+    async fn saltos_de_bloco_recebido_so_valem_depois_de_anexado() {
+        let chaves = chaves(3);
+        let ids = ids(&chaves);
+        let detector = Arc::new(DetectorMaliciosos::new());
+        let processamento = CamadaProcessamento::new(Assinador::new(ParChaves::gerar()), detector.clone());
+        processamento.inicializar_rotacao(1, &fila(&chaves)).await;
+
+        let saltos = [evidencia(1, 0, ids[0], &chaves[1..])];
+        let raiz = processamento.raiz_estado().await;
+
+        // Raiz errada ou produtor fora da vez: recusado sem pular ninguém nem punir
+        assert!(processamento.aplicar_bloco_remoto(&[], 1, ids[1], &[7; 32], &saltos).await.is_err());
+        processamento.descartar_lote().await;
+        assert!(processamento.aplicar_bloco_remoto(&[], 1, ids[2], &raiz, &saltos).await.is_err());
+        assert!(processamento.aplicar_bloco_remoto(&[], 1, ids[1], &raiz, &[]).await.is_err());
+        assert_eq!(processamento.slot_atual().await, 0);
+        assert!(detector.obter_historico_suspeitas().is_empty());
+
+        // Na vez certa a reexecução passa, mas a rotação só anda ao confirmar
+        processamento.aplicar_bloco_remoto(&[], 1, ids[1], &raiz, &saltos).await.unwrap();
+        assert_eq!(processamento.slot_atual().await, 0);
+
+        processamento.confirmar_lote(1, &[], &saltos).await;
+        assert_eq!(processamento.slot_atual().await, 2);
+        assert_eq!(detector.obter_historico_suspeitas().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bloco_recebido_com_transacao_que_nao_corresponde_a_transferencia_e_recusado() {
+        let chaves = chaves(2);
+        let dono = ParChaves::gerar();
+        let produtor = chaves[0].no_id();
+        let saldos = HashMap::from([(dono.no_id().to_string(), 100)]);
+
+        let processamento = CamadaProcessamento::new_com_maquina(
+            Assinador::new(ParChaves::gerar()),
+            Arc::new(DetectorMaliciosos::new()),
+            Box::new(MaquinaTransferencias::com_saldos(&saldos)),
+        );
+        processamento.inicializar_rotacao(1, &fila(&chaves)).await;
+
+        // O que o produtor da vez executou, numa máquina à parte
+        let mut referencia = MaquinaTransferencias::com_saldos(&saldos);
+        referencia.iniciar_bloco(1);
+        let transferencia = Transferencia::nova(&dono, "bob".to_string(), 40, 1, 0);
+        let mut executada = referencia
+            .aplicar(&Transacao::de_transferencia(transferencia), &produtor.to_string())
+            .unwrap();
+        let raiz = referencia.raiz_estado();
+
+        // Mesma transferência com outro id, assinada pelo produtor: a reexecução
+        // chegaria à mesma raiz, mas o bloco é recusado antes
+        let mut adulterada = executada.clone();
+        adulterada.id = "outro_id".to_string();
+        adulterada.assinatura = chaves[0].assinar(&adulterada.bytes_para_assinatura());
+
+        let erro = processamento
+            .aplicar_bloco_remoto(&[adulterada], 1, produtor, &raiz, &[])
+            .await
+            .unwrap_err();
+        assert!(erro.to_string().contains("não corresponde"));
+        assert_eq!(processamento.estado.obter_conta(&dono.no_id().to_string()).await.saldo, 100);
+
+        executada.assinatura = chaves[0].assinar(&executada.bytes_para_assinatura());
+        processamento.aplicar_bloco_remoto(&[executada], 1, produtor, &raiz, &[]).await.unwrap();
+        assert_eq!(processamento.raiz_estado().await, raiz);
+    }
+}
@@ -0,0 +1,331 @@
+use super::estado::Conta;
+use super::transicao::{self, Contas};
+use crate::consenso::tipos::Transferencia;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::info;
+
+/// Contas escritas por uma transferência aceita, na ordem do bloco
+pub type Escritas = Vec<(String, Conta)>;
+
+/// Execução especulativa de uma transferência contra o estado do início do bloco
+#[derive(Debug, Clone)]
+struct Execucao {
+    /// Contas lidas e o valor visto; a execução só vale se continuarem iguais
+    lidas: Vec<(String, Conta)>,
+    escritas: Escritas,
+    /// Taxa creditada ao produtor quando ele não foi lido; soma comutativa, não gera conflito
+    credito_produtor: u64,
+    resultado: Result<(), String>,
+}
+
+/// Estatísticas da última execução de um lote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EstatisticasExecucao {
+    pub transacoes: usize,
+    /// Transações cuja execução especulativa leu um valor já alterado por outra anterior
+    pub conflitos: usize,
+}
+
+/// Executor otimista de transferências.
+///
+/// Todas as transferências do lote rodam em paralelo contra o estado do início
+/// do bloco, registrando as contas lidas e escritas. Depois, na ordem do bloco,
+/// cada execução é conferida contra o estado já confirmado: se alguma conta lida
+/// mudou, a transferência é reexecutada ali mesmo, em sequência. Como a
+/// transição só depende das contas lidas, o resultado é idêntico ao da execução
+/// sequencial, inclusive a raiz de estado.
+///
+/// O crédito da taxa ao produtor é tratado como soma comutativa, senão toda
+/// transferência com taxa conflitaria com todas as outras.
+#[derive(Debug, Clone)]
+pub struct ExecutorParalelo {
+    threads: usize,
+}
+
+impl ExecutorParalelo {
+    pub fn new(threads: usize) -> Self {
+        Self { threads: threads.max(1) }
+    }
+
+    /// Executa as transferências na ordem dada sobre `contas` (ausente vale zero).
+    ///
+    /// Devolve, por transferência, as contas escritas ou o erro que a rejeitou; as
+    /// rejeitadas não alteram nada. `contas` termina com o estado final do lote.
+    pub fn executar(
+        &self,
+        contas: &mut Contas,
+        transferencias: &[&Transferencia],
+        produtor: &str,
+    ) -> (Vec<Result<Escritas, String>>, EstatisticasExecucao) {
+        let especulativas = self.executar_em_paralelo(contas, transferencias, produtor);
+
+        let mut estatisticas = EstatisticasExecucao {
+            transacoes: transferencias.len(),
+            conflitos: 0,
+        };
+        let mut resultados = Vec::with_capacity(transferencias.len());
+
+        for (transferencia, execucao) in transferencias.iter().zip(especulativas) {
+            let execucao = if Self::confirmar(contas, &execucao, produtor) {
+                execucao
+            } else {
+                // Reexecução sequencial sobre o estado confirmado, lendo até o
+                // produtor: é exatamente a transição sequencial e sempre confirma
+                estatisticas.conflitos += 1;
+                let reexecucao = Self::executar_uma(contas, transferencia, produtor, false);
+                Self::confirmar(contas, &reexecucao, produtor);
+                reexecucao
+            };
+
+            let resultado = execucao.resultado.clone().map(|_| Self::escritas_com_credito(contas, &execucao, produtor));
+            resultados.push(resultado);
+        }
+
+        if estatisticas.conflitos > 0 {
+            info!(
+                "{} de {} transferências reexecutadas por conflito",
+                estatisticas.conflitos, estatisticas.transacoes
+            );
+        }
+
+        (resultados, estatisticas)
+    }
+
+    fn executar_em_paralelo(&self, contas: &Contas, transferencias: &[&Transferencia], produtor: &str) -> Vec<Execucao> {
+        let proxima = AtomicUsize::new(0);
+        let execucoes: Vec<Mutex<Option<Execucao>>> = transferencias.iter().map(|_| Mutex::new(None)).collect();
+        let threads = self.threads.min(transferencias.len());
+
+        std::thread::scope(|escopo| {
+            for _ in 0..threads {
+                escopo.spawn(|| loop {
+                    let indice = proxima.fetch_add(1, Ordering::Relaxed);
+                    let Some(transferencia) = transferencias.get(indice) else {
+                        break;
+                    };
+
+                    let execucao = Self::executar_uma(contas, transferencia, produtor, true);
+                    if let Ok(mut posicao) = execucoes[indice].lock() {
+                        *posicao = Some(execucao);
+                    }
+                });
+            }
+        });
+
+        execucoes
+            .into_iter()
+            .zip(transferencias)
+            .map(|(execucao, transferencia)| {
+                execucao
+                    .into_inner()
+                    .ok()
+                    .flatten()
+                    .unwrap_or_else(|| Self::executar_uma(contas, transferencia, produtor, true))
+            })
+            .collect()
+    }
+
+    /// Executa uma transferência lendo apenas as contas que ela toca.
+    ///
+    /// Com `taxa_como_soma`, o produtor que não é origem nem destino não é lido:
+    /// a taxa creditada a ele vira uma soma aplicada na confirmação.
+    fn executar_uma(contas: &Contas, transferencia: &Transferencia, produtor: &str, taxa_como_soma: bool) -> Execucao {
+        let somar_taxa = taxa_como_soma && produtor != transferencia.de && produtor != transferencia.para;
+
+        let mut ids = vec![transferencia.de.as_str()];
+        for id in [transferencia.para.as_str(), produtor] {
+            let somada = somar_taxa && id == produtor;
+            if !somada && !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        let lidas: Vec<(String, Conta)> = ids
+            .iter()
+            .map(|id| (id.to_string(), contas.get(*id).copied().unwrap_or_default()))
+            .collect();
+
+        let mut locais: Contas = lidas.iter().cloned().collect();
+        if somar_taxa {
+            locais.insert(produtor.to_string(), Conta::default());
+        }
+
+        let resultado = transicao::aplicar_transferencia(&mut locais, transferencia, produtor).map_err(|e| e.to_string());
+
+        let credito_produtor = if somar_taxa {
+            locais.get(produtor).map_or(0, |conta| conta.saldo)
+        } else {
+            0
+        };
+
+        let escritas = match resultado {
+            Ok(()) => lidas.iter().map(|(id, _)| (id.clone(), locais[id])).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Execucao {
+            lidas,
+            escritas,
+            credito_produtor,
+            resultado,
+        }
+    }
+
+    /// Grava a execução se as contas lidas não mudaram; senão deixa `contas` intacto
+    fn confirmar(contas: &mut Contas, execucao: &Execucao, produtor: &str) -> bool {
+        let leituras_validas = execucao
+            .lidas
+            .iter()
+            .all(|(id, conta)| contas.get(id).copied().unwrap_or_default() == *conta);
+        if !leituras_validas {
+            return false;
+        }
+
+        if execucao.credito_produtor > 0 {
+            let conta_produtor = contas.get(produtor).copied().unwrap_or_default();
+            // Na execução sequencial o estouro do saldo do produtor rejeita a
+            // transferência; a reexecução, que lê o produtor, chega ao mesmo erro
+            let Some(saldo) = conta_produtor.saldo.checked_add(execucao.credito_produtor) else {
+                return false;
+            };
+            contas.insert(produtor.to_string(), Conta { saldo, ..conta_produtor });
+        }
+
+        contas.extend(execucao.escritas.iter().cloned());
+        true
+    }
+
+    /// Escritas da transferência, incluindo o produtor creditado pela soma comutativa
+    fn escritas_com_credito(contas: &Contas, execucao: &Execucao, produtor: &str) -> Escritas {
+        let mut escritas = execucao.escritas.clone();
+        if execucao.credito_produtor > 0 {
+            escritas.push((produtor.to_string(), contas.get(produtor).copied().unwrap_or_default()));
+        }
+        escritas
+    }
+}
+
+impl Default for ExecutorParalelo {
+    fn default() -> Self {
+        Self::new(std::thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::chaves;
+    use crate::consenso::cripto::ParChaves;
+
+    /// Executa em paralelo e em sequência e confere que aceitam as mesmas
+    /// transferências e chegam às mesmas contas
+    fn comparar_com_sequencial(iniciais: &Contas, transferencias: &[Transferencia], produtor: &str) -> EstatisticasExecucao {
+        let mut sequencial = iniciais.clone();
+        let esperados: Vec<bool> = transferencias
+            .iter()
+            .map(|t| transicao::aplicar_transferencia(&mut sequencial, t, produtor).is_ok())
+            .collect();
+
+        let referencias: Vec<&Transferencia> = transferencias.iter().collect();
+        let mut paralelo = iniciais.clone();
+        let (resultados, estatisticas) = ExecutorParalelo::new(4).executar(&mut paralelo, &referencias, produtor);
+
+        let obtidos: Vec<bool> = resultados.iter().map(Result::is_ok).collect();
+        assert_eq!(obtidos, esperados);
+
+        let normalizar = |contas: &Contas| {
+            let mut contas: Vec<(String, Conta)> = contas
+                .iter()
+                .filter(|(_, conta)| **conta != Conta::default())
+                .map(|(id, conta)| (id.clone(), *conta))
+                .collect();
+            contas.sort_by(|a, b| a.0.cmp(&b.0));
+            contas
+        };
+        assert_eq!(normalizar(&paralelo), normalizar(&sequencial));
+
+        estatisticas
+    }
+
+    fn contas_iniciais(chaves: &[ParChaves], saldo: u64) -> Contas {
+        chaves
+            .iter()
+            .map(|c| (c.no_id().to_string(), Conta { saldo, nonce: 0 }))
+            .collect()
+    }
+
+    #[test]
+    fn mesmo_resultado_da_execucao_sequencial() {
+        let chaves = chaves(4);
+        let endereco = |i: usize| chaves[i].no_id().to_string();
+        let produtor = endereco(3);
+        let iniciais = contas_iniciais(&chaves[..3], 1_000);
+
+        // Cadeia de dependências (0 → 1 → 2 → 0), nonce repetido, saldo
+        // insuficiente e o próprio produtor gastando taxas recebidas no lote
+        let transferencias = [
+            Transferencia::nova(&chaves[0], endereco(1), 900, 10, 0),
+            Transferencia::nova(&chaves[1], endereco(2), 1_800, 5, 0),
+            Transferencia::nova(&chaves[0], endereco(2), 50, 1, 0),
+            Transferencia::nova(&chaves[2], endereco(0), 2_700, 3, 0),
+            Transferencia::nova(&chaves[0], endereco(1), 2_000, 1, 1),
+            Transferencia::nova(&chaves[3], endereco(0), 15, 0, 0),
+            Transferencia::nova(&chaves[1], endereco(3), 90, 2, 1),
+        ];
+
+        assert!(comparar_com_sequencial(&iniciais, &transferencias, &produtor).conflitos > 0);
+    }
+
+    #[test]
+    fn muitas_transferencias_entre_as_mesmas_contas() {
+        let chaves = chaves(3);
+        let endereco = |i: usize| chaves[i].no_id().to_string();
+        let iniciais = contas_iniciais(&chaves, 500);
+
+        // Todas as contas enviam e recebem o tempo todo; valores variados fazem
+        // parte delas faltar saldo no meio do lote e voltar a ter depois
+        let mut nonces = [0u64; 3];
+        let mut semente = 7u64;
+        let transferencias: Vec<Transferencia> = (0..120)
+            .map(|_| {
+                semente = semente.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let de = (semente >> 33) as usize % 3;
+                let para = (de + 1 + (semente >> 40) as usize % 2) % 3;
+                let valor = (semente >> 20) % 400;
+                let transferencia = Transferencia::nova(&chaves[de], endereco(para), valor, 1, nonces[de]);
+                nonces[de] += 1;
+                transferencia
+            })
+            .collect();
+
+        // O produtor também é uma das contas em disputa
+        let estatisticas = comparar_com_sequencial(&iniciais, &transferencias, &endereco(0));
+        assert_eq!(estatisticas.transacoes, transferencias.len());
+        assert!(estatisticas.conflitos > transferencias.len() / 2);
+    }
+
+    #[test]
+    fn cadeias_de_nonce_do_mesmo_remetente() {
+        let chaves = chaves(2);
+        let destino = chaves[1].no_id().to_string();
+        let iniciais = contas_iniciais(&chaves[..1], 1_000);
+
+        // Nonces em ordem, um repetido, um adiantado que só vale depois de
+        // preenchido o buraco, e a cadeia seguindo depois de uma rejeição
+        let nonces = [0, 1, 2, 2, 4, 3, 4, 5, 7, 6, 7, 8];
+        let transferencias: Vec<Transferencia> = nonces
+            .iter()
+            .map(|nonce| Transferencia::nova(&chaves[0], destino.clone(), 10 * (nonce + 1), 1, *nonce))
+            .collect();
+
+        let estatisticas = comparar_com_sequencial(&iniciais, &transferencias, "produtor");
+        assert!(estatisticas.conflitos > 0);
+
+        // Outra cadeia longa que esgota o saldo no meio
+        let longa: Vec<Transferencia> = (0..40)
+            .map(|nonce| Transferencia::nova(&chaves[0], destino.clone(), 60, 2, nonce))
+            .collect();
+        comparar_com_sequencial(&iniciais, &longa, "produtor");
+    }
+}
@@ -1,21 +1,34 @@
 use crate::consenso::tipos::*;
+use crate::consenso::cripto::{Assinador, verificar_assinatura};
 use anyhow::Result;
 use tracing::{info, warn};
 use chrono::Utc;
 
 pub struct ProcessadorTransacao {
     contador_transacoes: std::sync::atomic::AtomicU64,
+    assinador: Assinador,
 }
 
 impl ProcessadorTransacao {
-    pub fn new() -> Self {
+    pub fn new(assinador: Assinador) -> Self {
         Self {
             contador_transacoes: std::sync::atomic::AtomicU64::new(0),
+            assinador,
         }
     }
     
-    pub async fn processar(&self, transacao: &Transacao, processador_id: &str) -> Result<Transacao> {
-        info!("Processando transação {} pelo nó {}", transacao.id, processador_id);
+    /// Indica se o nó informado é este nó (mesma chave pública)
+    pub fn e_no_local(&self, no: &No) -> bool {
+        no.chave_publica == self.assinador.chave_publica()
+    }
+    
+    pub async fn processar(&self, transacao: &Transacao, processador: &No) -> Result<Transacao> {
+        info!("Processando transação {} pelo nó {}", transacao.id, processador.id);
+        
+        // Só o dono da chave pode assinar como processador
+        if !self.e_no_local(processador) {
+            return Err(anyhow::anyhow!("Nó {} não corresponde à chave local", processador.id));
+        }
         
         // Validar transação
         self.validar_transacao(transacao)?;
@@ -29,17 +42,18 @@ impl ProcessadorTransacao {
         // Incrementar contador
         self.contador_transacoes.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
         
-        // Simular cálculo de novo estado
-        transacao_processada.estado_final = self.calcular_novo_estado(&transacao.estado_anterior, &transacao.dados)?;
+        // Os estados anterior e final já vêm preenchidos pela máquina de estado
         
         // Gerar assinatura do processador
-        transacao_processada.assinatura = self.assinar_transacao(&transacao_processada, processador_id)?;
+        transacao_processada.assinatura = self.assinar_transacao(&transacao_processada);
         
         info!("Transação processada com sucesso");
         Ok(transacao_processada)
     }
     
-    fn validar_transacao(&self, transacao: &Transacao) -> Result<()> {
+    /// O que `processar` exige da transação; conferido antes da execução, para
+    /// que nenhuma transação executada seja recusada na hora de assinar
+    pub fn validar_transacao(&self, transacao: &Transacao) -> Result<()> {
         if transacao.id.is_empty() {
             return Err(anyhow::anyhow!("ID da transação não pode estar vazio"));
         }
@@ -48,24 +62,29 @@ impl ProcessadorTransacao {
             return Err(anyhow::anyhow!("Dados da transação não podem estar vazios"));
         }
         
+        if transacao.transferencia.is_some() && !transacao.corresponde_a_transferencia() {
+            return Err(anyhow::anyhow!("Id ou dados da transação {} não correspondem à transferência", transacao.id));
+        }
+        
         Ok(())
     }
     
-    fn calcular_novo_estado(&self, estado_anterior: &[u8], dados: &[u8]) -> Result<Vec<u8>> {
-        // Simular cálculo de estado (em implementação real seria mais complexo)
-        let mut novo_estado = Vec::new();
-        novo_estado.extend_from_slice(estado_anterior);
-        novo_estado.extend_from_slice(dados);
-        Ok(calcular_hash(&novo_estado))
+    fn assinar_transacao(&self, transacao: &Transacao) -> Vec<u8> {
+        self.assinador.assinar(&transacao.bytes_para_assinatura())
     }
     
-    fn assinar_transacao(&self, transacao: &Transacao, processador_id: &str) -> Result<Vec<u8>> {
-        // Simular assinatura digital
-        let dados_para_assinar = serde_json::to_vec(transacao)?;
-        let mut assinatura = Vec::new();
-        assinatura.extend_from_slice(processador_id.as_bytes());
-        assinatura.extend_from_slice(&dados_para_assinar);
-        Ok(calcular_hash(&assinatura))
+    pub fn verificar_assinatura_transacao(&self, transacao: &Transacao, processador: &No) -> bool {
+        let valida = processador.identidade_valida() && verificar_assinatura(
+            &processador.chave_publica,
+            &transacao.bytes_para_assinatura(),
+            &transacao.assinatura,
+        );
+        
+        if !valida {
+            warn!("Assinatura da transação {} não confere com o nó {}", transacao.id, processador.id);
+        }
+        
+        valida
     }
     
     pub fn obter_total_transacoes(&self) -> u64 {
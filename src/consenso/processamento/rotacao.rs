@@ -1,10 +1,14 @@
+use crate::consenso::tipos::{No, NodeId};
 use tokio::sync::RwLock;
 use std::collections::VecDeque;
 use tracing::info;
 
 pub struct GerenciadorRotacao {
-    fila_nos: RwLock<VecDeque<String>>,
-    historico_processamento: RwLock<Vec<String>>,
+    fila_nos: RwLock<VecDeque<No>>,
+    historico_processamento: RwLock<Vec<NodeId>>,
+    /// Posição na rotação da rodada: avança a cada bloco e a cada processador pulado
+    slot: RwLock<u64>,
+    blocos_rodada: RwLock<u64>,
 }
 
 impl GerenciadorRotacao {
@@ -12,15 +16,19 @@ impl GerenciadorRotacao {
         Self {
             fila_nos: RwLock::new(VecDeque::new()),
             historico_processamento: RwLock::new(Vec::new()),
+            slot: RwLock::new(0),
+            blocos_rodada: RwLock::new(0),
         }
     }
     
-    pub async fn inicializar_fila(&self, nos: Vec<String>) {
+    pub async fn inicializar_fila(&self, nos: Vec<No>) {
         let mut fila = self.fila_nos.write().await;
         fila.clear();
         for no in nos {
             fila.push_back(no);
         }
+        *self.slot.write().await = 0;
+        *self.blocos_rodada.write().await = 0;
         info!("Fila de rotação inicializada com {} nós", fila.len());
     }
     
@@ -28,24 +36,48 @@ impl GerenciadorRotacao {
         let mut fila = self.fila_nos.write().await;
         if let Some(no_processador) = fila.pop_front() {
             // Adicionar ao histórico
-            self.historico_processamento.write().await.push(no_processador.clone());
+            self.historico_processamento.write().await.push(no_processador.id);
             
-            // Mover para o final da fila
-            fila.push_back(no_processador.clone());
+            info!("Nó {} rotacionado para o final da fila", no_processador.id);
             
-            info!("Nó {} rotacionado para o final da fila", no_processador);
+            // Mover para o final da fila
+            fila.push_back(no_processador);
+            *self.slot.write().await += 1;
+            *self.blocos_rodada.write().await += 1;
         }
     }
     
-    pub async fn obter_proximo_processador(&self) -> Option<String> {
+    /// Passa a vez ao próximo da fila sem registrar processamento para o nó pulado
+    pub async fn pular_processador(&self) -> Option<NodeId> {
+        let mut fila = self.fila_nos.write().await;
+        let pulado = fila.pop_front()?;
+        
+        info!("Nó {} pulado por perder o prazo do slot", pulado.id);
+        
+        let id = pulado.id;
+        fila.push_back(pulado);
+        *self.slot.write().await += 1;
+        Some(id)
+    }
+    
+    pub async fn obter_slot(&self) -> u64 {
+        *self.slot.read().await
+    }
+    
+    /// Blocos processados desde `inicializar_fila`
+    pub async fn obter_blocos_rodada(&self) -> u64 {
+        *self.blocos_rodada.read().await
+    }
+    
+    pub async fn obter_proximo_processador(&self) -> Option<No> {
         self.fila_nos.read().await.front().cloned()
     }
     
-    pub async fn obter_historico(&self) -> Vec<String> {
+    pub async fn obter_historico(&self) -> Vec<NodeId> {
         self.historico_processamento.read().await.clone()
     }
     
-    pub async fn obter_fila_atual(&self) -> Vec<String> {
+    pub async fn obter_fila_atual(&self) -> Vec<No> {
         self.fila_nos.read().await.iter().cloned().collect()
     }
 }
\ No newline at end of file
@@ -0,0 +1,302 @@
+use crate::consenso::cripto::{verificar_assinatura, BytesCanonicos, ParChaves};
+use crate::consenso::tipos::NodeId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use tokio::sync::RwLock;
+
+/// Voto de um nó da fila para pular o processador que perdeu o prazo do slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VotoSalto {
+    pub rodada: u64,
+    /// Posição na rotação da rodada, a partir de zero
+    pub slot: u64,
+    pub processador: NodeId,
+    pub no_id: NodeId,
+    pub chave_publica: Vec<u8>,
+    pub assinatura: Vec<u8>,
+}
+
+impl VotoSalto {
+    pub fn novo(chaves: &ParChaves, rodada: u64, slot: u64, processador: NodeId) -> Self {
+        let mut voto = Self {
+            rodada,
+            slot,
+            processador,
+            no_id: chaves.no_id(),
+            chave_publica: chaves.chave_publica(),
+            assinatura: Vec::new(),
+        };
+
+        voto.assinatura = chaves.assinar(&voto.bytes_para_assinatura());
+        voto
+    }
+
+    /// Bytes assinados pelo votante; identificam o slot e o processador pulado
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_VOTO_SALTO_V1")
+            .inteiro(self.rodada)
+            .inteiro(self.slot)
+            .campo(self.processador.como_bytes())
+            .finalizar()
+    }
+
+    pub fn verificar(&self) -> bool {
+        self.no_id.corresponde(&self.chave_publica)
+            && verificar_assinatura(&self.chave_publica, &self.bytes_para_assinatura(), &self.assinatura)
+    }
+}
+
+/// Prova de que a fila pulou um processador: votos de mais de 2/3 dos outros nós da fila.
+///
+/// O processador pulado não entra na conta, já que quem está fora do ar não
+/// vota; senão, numa fila de 3 nós, um só ausente nunca seria pulado. Vai no
+/// cabeçalho do bloco seguinte, onde o detector de maliciosos e as
+/// recompensas podem contar as ausências de cada processador.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvidenciaSalto {
+    pub rodada: u64,
+    pub slot: u64,
+    pub processador: NodeId,
+    pub votos: Vec<VotoSalto>,
+}
+
+impl EvidenciaSalto {
+    /// Quorum entre `votantes` nós (a fila sem o processador pulado)
+    pub fn quorum(votantes: usize) -> usize {
+        votantes * 2 / 3 + 1
+    }
+
+    /// Nós da fila que votam este salto: todos menos o processador pulado
+    fn votantes<'a>(&self, fila: &'a [NodeId]) -> Vec<&'a NodeId> {
+        fila.iter().filter(|no_id| **no_id != self.processador).collect()
+    }
+
+    /// Votos de nós distintos da fila, fora o pulado, assinados e para este mesmo slot
+    pub fn votos_validos(&self, fila: &[NodeId]) -> usize {
+        let votantes = self.votantes(fila);
+        let mut vistos = HashSet::new();
+
+        self.votos
+            .iter()
+            .filter(|voto| {
+                voto.rodada == self.rodada
+                    && voto.slot == self.slot
+                    && voto.processador == self.processador
+                    && votantes.contains(&&voto.no_id)
+                    && voto.verificar()
+                    && vistos.insert(voto.no_id)
+            })
+            .count()
+    }
+
+    pub fn tem_quorum(&self, fila: &[NodeId]) -> bool {
+        let votantes = self.votantes(fila).len();
+        votantes > 0 && self.votos_validos(fila) >= Self::quorum(votantes)
+    }
+
+    /// Evidência que vale para a fila certificada: o processador pulado é o
+    /// da vez no slot (cada nó tem no máximo um slot por rodada) e o quorum votou
+    pub fn confere_com_fila(&self, fila: &[NodeId]) -> bool {
+        usize::try_from(self.slot).ok().and_then(|slot| fila.get(slot)) == Some(&self.processador)
+            && self.tem_quorum(fila)
+    }
+
+    /// Codificação canônica, com os votos ordenados por votante
+    pub fn codificar(&self) -> Vec<u8> {
+        let mut votos: Vec<&VotoSalto> = self.votos.iter().collect();
+        votos.sort_by_key(|voto| voto.no_id);
+
+        let mut bytes = BytesCanonicos::new("NIMBOS_EVIDENCIA_SALTO_V1")
+            .inteiro(self.rodada)
+            .inteiro(self.slot)
+            .campo(self.processador.como_bytes())
+            .inteiro(votos.len() as u64);
+
+        for voto in votos {
+            bytes = bytes
+                .campo(voto.no_id.como_bytes())
+                .campo(&voto.chave_publica)
+                .campo(&voto.assinatura);
+        }
+
+        bytes.finalizar()
+    }
+}
+
+/// Votos de salto da rodada e evidências que ainda não entraram num bloco
+pub struct GerenciadorSaltos {
+    /// Votos por (rodada, slot, processador), até o quorum
+    votos: RwLock<BTreeMap<(u64, u64, NodeId), Vec<VotoSalto>>>,
+    evidencias_pendentes: RwLock<Vec<EvidenciaSalto>>,
+}
+
+impl GerenciadorSaltos {
+    pub fn new() -> Self {
+        Self {
+            votos: RwLock::new(BTreeMap::new()),
+            evidencias_pendentes: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub async fn ja_votou(&self, rodada: u64, slot: u64, processador: NodeId, no_id: &NodeId) -> bool {
+        self.votos.read().await
+            .get(&(rodada, slot, processador))
+            .is_some_and(|votos| votos.iter().any(|voto| voto.no_id == *no_id))
+    }
+
+    /// Registra um voto já conferido; devolve a evidência quando o slot atinge o quorum.
+    ///
+    /// Só se somam votos da mesma rodada, do mesmo slot e contra o mesmo processador.
+    pub async fn adicionar_voto(&self, voto: VotoSalto, fila: &[NodeId]) -> Option<EvidenciaSalto> {
+        let chave = (voto.rodada, voto.slot, voto.processador);
+        let mut votos = self.votos.write().await;
+        let votos_slot = votos.entry(chave).or_default();
+
+        if votos_slot.iter().any(|v| v.no_id == voto.no_id) {
+            return None;
+        }
+
+        let evidencia = EvidenciaSalto {
+            rodada: voto.rodada,
+            slot: voto.slot,
+            processador: voto.processador,
+            votos: {
+                votos_slot.push(voto);
+                votos_slot.clone()
+            },
+        };
+
+        if !evidencia.tem_quorum(fila) {
+            return None;
+        }
+
+        votos.remove(&chave);
+        self.evidencias_pendentes.write().await.push(evidencia.clone());
+        Some(evidencia)
+    }
+
+    /// Evidências ainda não gravadas, para o próximo bloco deste nó
+    pub async fn retirar_evidencias(&self) -> Vec<EvidenciaSalto> {
+        std::mem::take(&mut *self.evidencias_pendentes.write().await)
+    }
+
+    /// Descarta as evidências que outro processador já gravou
+    pub async fn remover_gravadas(&self, gravadas: &[EvidenciaSalto]) {
+        self.evidencias_pendentes.write().await
+            .retain(|e| !gravadas.iter().any(|g| g.rodada == e.rodada && g.slot == e.slot));
+    }
+
+    /// Votos pendentes são da rodada; as evidências ficam até entrarem num bloco
+    pub async fn limpar_votos(&self) {
+        self.votos.write().await.clear();
+    }
+}
+
+impl Default for GerenciadorSaltos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes::{chaves, evidencia, ids};
+
+    #[test]
+    fn quorum_e_mais_de_dois_tercos_da_fila_sem_o_pulado() {
+        assert_eq!(EvidenciaSalto::quorum(1), 1);
+        assert_eq!(EvidenciaSalto::quorum(2), 2);
+        assert_eq!(EvidenciaSalto::quorum(3), 3);
+        assert_eq!(EvidenciaSalto::quorum(5), 4);
+
+        let chaves = chaves(4);
+        let fila = ids(&chaves);
+
+        assert!(!evidencia(1, 0, fila[0], &chaves[1..3]).tem_quorum(&fila));
+        assert!(evidencia(1, 0, fila[0], &chaves[1..4]).tem_quorum(&fila));
+        assert!(!evidencia(1, 0, fila[0], &chaves).tem_quorum(&[]));
+
+        // O voto do próprio pulado não conta
+        assert!(!evidencia(1, 0, fila[0], &chaves[..3]).tem_quorum(&fila));
+
+        // Fila de um nó só: ninguém pode pular o único processador
+        assert!(!evidencia(1, 0, fila[0], &chaves[..1]).tem_quorum(&fila[..1]));
+    }
+
+    #[tokio::test]
+    async fn fila_de_tres_pula_o_unico_ausente() {
+        let chaves = chaves(3);
+        let fila = ids(&chaves);
+        let gerenciador = GerenciadorSaltos::default();
+
+        // fila[0] está fora do ar; só os outros dois votam
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[1], 1, 0, fila[0]), &fila).await.is_none());
+        let evidencia = gerenciador.adicionar_voto(VotoSalto::novo(&chaves[2], 1, 0, fila[0]), &fila).await.unwrap();
+
+        assert!(evidencia.confere_com_fila(&fila));
+        assert_eq!(evidencia.votos_validos(&fila), 2);
+    }
+
+    #[test]
+    fn votos_repetidos_de_fora_ou_de_outro_slot_nao_contam() {
+        let chaves = chaves(4);
+        let fila = ids(&chaves);
+        let mut salto = evidencia(1, 0, fila[0], &chaves[1..3]);
+
+        // O mesmo votante duas vezes
+        salto.votos.push(salto.votos[0].clone());
+        // Nó fora da fila
+        salto.votos.push(VotoSalto::novo(&ParChaves::gerar(), 1, 0, fila[0]));
+        // Votos da fila, mas para outro slot, outra rodada ou outro processador
+        salto.votos.push(VotoSalto::novo(&chaves[3], 1, 1, fila[0]));
+        salto.votos.push(VotoSalto::novo(&chaves[3], 2, 0, fila[0]));
+        salto.votos.push(VotoSalto::novo(&chaves[3], 1, 0, fila[1]));
+        // Assinatura que não confere
+        let mut adulterado = VotoSalto::novo(&chaves[3], 1, 0, fila[0]);
+        adulterado.assinatura[0] ^= 1;
+        salto.votos.push(adulterado);
+
+        assert_eq!(salto.votos_validos(&fila), 2);
+        assert!(!salto.tem_quorum(&fila));
+
+        salto.votos.push(VotoSalto::novo(&chaves[3], 1, 0, fila[0]));
+        assert!(salto.tem_quorum(&fila));
+    }
+
+    #[test]
+    fn evidencia_precisa_pular_o_processador_do_slot() {
+        let chaves = chaves(4);
+        let fila = ids(&chaves);
+
+        assert!(evidencia(1, 1, fila[1], &chaves).confere_com_fila(&fila));
+        assert!(!evidencia(1, 1, fila[2], &chaves).confere_com_fila(&fila));
+        assert!(!evidencia(1, 4, fila[0], &chaves).confere_com_fila(&fila));
+        assert!(!evidencia(1, 1, fila[1], &chaves[..2]).confere_com_fila(&fila));
+    }
+
+    #[tokio::test]
+    async fn votos_so_se_somam_na_mesma_rodada_slot_e_processador() {
+        let chaves = chaves(4);
+        let fila = ids(&chaves);
+        let gerenciador = GerenciadorSaltos::default();
+
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[1], 1, 0, fila[0]), &fila).await.is_none());
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[2], 1, 0, fila[0]), &fila).await.is_none());
+
+        // Repetido, de outra rodada ou contra outro processador: não completam o quorum
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[2], 1, 0, fila[0]), &fila).await.is_none());
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[3], 2, 0, fila[0]), &fila).await.is_none());
+        assert!(gerenciador.adicionar_voto(VotoSalto::novo(&chaves[3], 1, 0, fila[1]), &fila).await.is_none());
+        assert!(gerenciador.ja_votou(1, 0, fila[0], &fila[2]).await);
+        assert!(!gerenciador.ja_votou(1, 0, fila[1], &fila[2]).await);
+
+        let evidencia = gerenciador.adicionar_voto(VotoSalto::novo(&chaves[3], 1, 0, fila[0]), &fila).await.unwrap();
+        assert!(evidencia.confere_com_fila(&fila));
+        assert_eq!(evidencia.votos.len(), 3);
+        assert!(!gerenciador.ja_votou(1, 0, fila[0], &fila[2]).await);
+
+        assert_eq!(gerenciador.retirar_evidencias().await, vec![evidencia]);
+        assert!(gerenciador.retirar_evidencias().await.is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+use super::estado::Conta;
+use crate::consenso::ordenacao::{EntradaStake, BLOCOS_DESVINCULO};
+use crate::consenso::tipos::{OperacaoStake, Transferencia, PREFIXO_STAKE};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Contas tocadas pelas transições; conta ausente equivale a saldo e nonce zero
+pub type Contas = HashMap<String, Conta>;
+
+/// Stakes tocados pelas transições, por endereço; ausente equivale a stake vazio
+pub type Stakes = HashMap<String, EntradaStake>;
+
+/// Confere uma transferência contra as contas atuais sem alterá-las
+pub fn validar_transferencia(contas: &Contas, transferencia: &Transferencia) -> Result<()> {
+    if !transferencia.verificar_assinatura() {
+        return Err(anyhow::anyhow!("Assinatura da transferência de {} inválida", transferencia.de));
+    }
+
+    // As chaves de stake dividem o estado com as contas
+    if transferencia.operacao_stake().is_none() && transferencia.para.starts_with(PREFIXO_STAKE) {
+        return Err(anyhow::anyhow!("Destino {} reservado", transferencia.para));
+    }
+
+    let origem = contas.get(&transferencia.de).copied().unwrap_or_default();
+
+    // Nonces em sequência: nem repetição nem lacunas
+    if transferencia.nonce != origem.nonce {
+        return Err(anyhow::anyhow!(
+            "Nonce {} fora de ordem para a conta {} (esperado {})",
+            transferencia.nonce, transferencia.de, origem.nonce
+        ));
+    }
+
+    let debito = transferencia.debito()
+        .ok_or_else(|| anyhow::anyhow!("Valor mais taxa excede o limite"))?;
+
+    if origem.saldo < debito {
+        return Err(anyhow::anyhow!(
+            "Saldo insuficiente na conta {}: {} < {}",
+            transferencia.de, origem.saldo, debito
+        ));
+    }
+
+    Ok(())
+}
+
+/// Debita valor e taxa da origem, credita o destino e paga a taxa ao produtor do bloco.
+///
+/// Em caso de erro as contas ficam como estavam.
+pub fn aplicar_transferencia(contas: &mut Contas, transferencia: &Transferencia, produtor: &str) -> Result<()> {
+    if transferencia.operacao_stake().is_some() {
+        return Err(anyhow::anyhow!("Operação de stake aplicada como transferência"));
+    }
+
+    validar_transferencia(contas, transferencia)?;
+
+    let mut novas = Contas::new();
+    let obter = |novas: &Contas, id: &str| {
+        novas.get(id).or_else(|| contas.get(id)).copied().unwrap_or_default()
+    };
+
+    let mut origem = obter(&novas, &transferencia.de);
+    origem.saldo -= transferencia.valor + transferencia.taxa;
+    origem.nonce += 1;
+    novas.insert(transferencia.de.clone(), origem);
+
+    let mut destino = obter(&novas, &transferencia.para);
+    destino.saldo = destino.saldo.checked_add(transferencia.valor)
+        .ok_or_else(|| anyhow::anyhow!("Saldo da conta {} excede o limite", transferencia.para))?;
+    novas.insert(transferencia.para.clone(), destino);
+
+    if transferencia.taxa > 0 {
+        let mut conta_produtor = obter(&novas, produtor);
+        conta_produtor.saldo = conta_produtor.saldo.checked_add(transferencia.taxa)
+            .ok_or_else(|| anyhow::anyhow!("Saldo da conta {} excede o limite", produtor))?;
+        novas.insert(produtor.to_string(), conta_produtor);
+    }
+
+    contas.extend(novas);
+    Ok(())
+}
+
+/// Vincula ou desvincula stake da conta de origem, pagando a taxa ao produtor.
+///
+/// `altura` é o número do bloco em execução; o desvínculo volta ao saldo em
+/// `altura + BLOCOS_DESVINCULO`. Em caso de erro contas e stakes ficam como estavam.
+pub fn aplicar_operacao_stake(
+    contas: &mut Contas,
+    stakes: &mut Stakes,
+    transferencia: &Transferencia,
+    produtor: &str,
+    altura: u64,
+) -> Result<()> {
+    let operacao = transferencia.operacao_stake()
+        .ok_or_else(|| anyhow::anyhow!("{} não é um destino de stake", transferencia.para))?;
+
+    validar_transferencia(contas, transferencia)?;
+
+    let mut stake = stakes.get(&transferencia.de).cloned().unwrap_or_default();
+    match operacao {
+        OperacaoStake::Vincular => {
+            stake.vinculado = stake.vinculado.checked_add(transferencia.valor)
+                .ok_or_else(|| anyhow::anyhow!("Stake de {} excede o limite", transferencia.de))?;
+        }
+        OperacaoStake::Desvincular => {
+            if transferencia.valor > stake.vinculado {
+                return Err(anyhow::anyhow!(
+                    "Desvínculo de {} maior que o stake vinculado de {} ({})",
+                    transferencia.valor, transferencia.de, stake.vinculado
+                ));
+            }
+
+            let liberacao = altura.checked_add(BLOCOS_DESVINCULO)
+                .ok_or_else(|| anyhow::anyhow!("Altura {} sem espaço para o desvínculo", altura))?;
+            stake.vinculado -= transferencia.valor;
+            stake.desvinculando.push((transferencia.valor, liberacao));
+        }
+    }
+
+    let mut novas = contas.clone();
+    let origem = novas.entry(transferencia.de.clone()).or_default();
+    // `validar_transferencia` já conferiu débito e saldo
+    origem.saldo -= transferencia.debito().unwrap_or_default();
+    origem.nonce += 1;
+
+    if transferencia.taxa > 0 {
+        let conta_produtor = novas.entry(produtor.to_string()).or_default();
+        conta_produtor.saldo = conta_produtor.saldo.checked_add(transferencia.taxa)
+            .ok_or_else(|| anyhow::anyhow!("Saldo da conta {} excede o limite", produtor))?;
+    }
+
+    *contas = novas;
+    stakes.insert(transferencia.de.clone(), stake);
+    Ok(())
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::tipos::endereco_da_chave;
+
+    const PRODUTOR: &str = "produtor";
+
+    fn conta_com(chaves: &ParChaves, saldo: u64, nonce: u64) -> (String, Contas) {
+        let de = endereco_da_chave(&chaves.chave_publica());
+        (de.clone(), Contas::from([(de, Conta { saldo, nonce })]))
+    }
+
+    fn saldo(contas: &Contas, id: &str) -> u64 {
+        contas.get(id).map_or(0, |conta| conta.saldo)
+    }
+
+    #[test]
+    fn debita_credita_e_paga_a_taxa_ao_produtor() {
+        let chaves = ParChaves::gerar();
+        let (de, mut contas) = conta_com(&chaves, 100, 0);
+
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 60, 5, 0), PRODUTOR).unwrap();
+
+        assert_eq!(contas[&de], Conta { saldo: 35, nonce: 1 });
+        assert_eq!(saldo(&contas, "bob"), 60);
+        assert_eq!(saldo(&contas, PRODUTOR), 5);
+
+        // Sem taxa o produtor não ganha conta
+        let (_, mut sem_taxa) = conta_com(&chaves, 100, 0);
+        aplicar_transferencia(&mut sem_taxa, &Transferencia::nova(&chaves, "bob".to_string(), 10, 0, 0), PRODUTOR).unwrap();
+        assert!(!sem_taxa.contains_key(PRODUTOR));
+    }
+
+    #[test]
+    fn saldo_insuficiente_e_recusado_sem_alterar_contas() {
+        let chaves = ParChaves::gerar();
+        let (_, mut contas) = conta_com(&chaves, 100, 0);
+        let antes = contas.clone();
+
+        // Valor cabe, valor mais taxa não
+        assert!(aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 100, 1, 0), PRODUTOR).is_err());
+        assert_eq!(contas, antes);
+
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 99, 1, 0), PRODUTOR).unwrap();
+    }
+
+    #[test]
+    fn nonce_repetido_ou_adiantado_e_recusado() {
+        let chaves = ParChaves::gerar();
+        let (de, mut contas) = conta_com(&chaves, 100, 3);
+
+        for nonce in [2, 4] {
+            assert!(aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 1, 0, nonce), PRODUTOR).is_err());
+        }
+        assert_eq!(contas[&de].nonce, 3);
+
+        let transferencia = Transferencia::nova(&chaves, "bob".to_string(), 1, 0, 3);
+        aplicar_transferencia(&mut contas, &transferencia, PRODUTOR).unwrap();
+        assert!(aplicar_transferencia(&mut contas, &transferencia, PRODUTOR).is_err());
+        assert_eq!(contas[&de], Conta { saldo: 99, nonce: 4 });
+    }
+
+    #[test]
+    fn transferencia_para_si_mesmo_so_paga_a_taxa() {
+        let chaves = ParChaves::gerar();
+        let (de, mut contas) = conta_com(&chaves, 100, 0);
+
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, de.clone(), 70, 2, 0), PRODUTOR).unwrap();
+
+        assert_eq!(contas[&de], Conta { saldo: 98, nonce: 1 });
+        assert_eq!(saldo(&contas, PRODUTOR), 2);
+
+        // O próprio remetente como produtor recebe a taxa de volta
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, de.clone(), 98, 0, 1), &de).unwrap();
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 90, 8, 2), &de).unwrap();
+        assert_eq!(contas[&de], Conta { saldo: 8, nonce: 3 });
+    }
+
+    #[test]
+    fn estouro_de_saldo_e_recusado_sem_alterar_contas() {
+        let chaves = ParChaves::gerar();
+        let (_, mut contas) = conta_com(&chaves, u64::MAX, 0);
+        contas.insert("cheia".to_string(), Conta { saldo: u64::MAX - 5, nonce: 0 });
+        let antes = contas.clone();
+
+        // Valor mais taxa estoura u64
+        assert!(aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), u64::MAX, 1, 0), PRODUTOR).is_err());
+        // Crédito no destino estoura
+        assert!(aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "cheia".to_string(), 6, 0, 0), PRODUTOR).is_err());
+        // Crédito da taxa no produtor estoura
+        assert!(aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "bob".to_string(), 1, 6, 0), "cheia").is_err());
+        assert_eq!(contas, antes);
+
+        aplicar_transferencia(&mut contas, &Transferencia::nova(&chaves, "cheia".to_string(), 5, 0, 0), PRODUTOR).unwrap();
+        assert_eq!(saldo(&contas, "cheia"), u64::MAX);
+    }
+
+    #[test]
+    fn assinatura_de_outra_chave_e_recusada() {
+        let (dono, intruso) = (ParChaves::gerar(), ParChaves::gerar());
+        let (de, mut contas) = conta_com(&dono, 100, 0);
+
+        let mut transferencia = Transferencia::nova(&intruso, "bob".to_string(), 10, 0, 0);
+        transferencia.de = de;
+        assert!(aplicar_transferencia(&mut contas, &transferencia, PRODUTOR).is_err());
+    }
+}
@@ -10,13 +10,20 @@ impl GeradorCommitment {
         (0..32).map(|_| rng.gen()).collect()
     }
     
-    pub fn criar_commitment(chave_publica: &[u8], nonce: &[u8], no_id: String) -> Commitment {
-        let hash = calcular_commitment(chave_publica, nonce);
+    pub fn criar_commitment(
+        id_cadeia: &str,
+        rodada: u64,
+        chave_publica: &[u8],
+        nonce: &[u8],
+        no_id: NodeId,
+    ) -> Commitment {
+        let hash = calcular_commitment(id_cadeia, rodada, chave_publica, nonce);
         
         Commitment {
             hash,
             timestamp: chrono::Utc::now(),
             no_id,
+            rodada,
         }
     }
 }
\ No newline at end of file
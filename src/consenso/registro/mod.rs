@@ -1,54 +1,110 @@
 mod commitment;
 mod validador;
 
+use crate::consenso::cripto::Assinador;
+use crate::consenso::rodada::MaquinaRodada;
 use crate::consenso::tipos::*;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
 pub use commitment::*;
 pub use validador::*;
 
+/// Commitment deste nó na rodada atual, com o que é preciso para o reveal
+#[derive(Debug, Clone)]
+pub struct CommitmentLocal {
+    pub commitment: Commitment,
+    pub chave_publica: Vec<u8>,
+    pub nonce: Vec<u8>,
+}
+
 pub struct CamadaRegistro {
-    commitments: RwLock<HashMap<String, Commitment>>,
+    commitments: RwLock<HashMap<NodeId, Commitment>>,
+    commitment_local: RwLock<Option<CommitmentLocal>>,
     validador: ValidadorCommitment,
+    rodada: Arc<MaquinaRodada>,
 }
 
 impl CamadaRegistro {
-    pub fn new() -> Self {
+    pub fn new(rodada: Arc<MaquinaRodada>) -> Self {
         Self {
             commitments: RwLock::new(HashMap::new()),
+            commitment_local: RwLock::new(None),
             validador: ValidadorCommitment::new(),
+            rodada,
         }
     }
     
-    pub async fn processar_commitments(&self) -> Result<()> {
-        info!("Processando commitments da fase de registro");
+    /// Abre a fase de registro: gera o commitment deste nó e o registra localmente.
+    ///
+    /// O commitment retornado deve ser enviado aos outros nós; os deles chegam
+    /// pela camada de comunicação via `adicionar_commitment`.
+    pub async fn iniciar_registro(&self, assinador: &Assinador) -> Result<Commitment> {
+        let chaves = assinador.chaves_atuais();
+        let nonce = GeradorCommitment::gerar_nonce();
+        let rodada = self.rodada.rodada_atual().await;
+        let chave_publica = chaves.chave_publica();
+        let commitment = GeradorCommitment::criar_commitment(
+            &self.rodada.configuracao().id_cadeia,
+            rodada,
+            &chave_publica,
+            &nonce,
+            chaves.no_id(),
+        );
         
-        // Simular recebimento de commitments
-        // Em implementação real, isso viria da camada de comunicação
+        *self.commitment_local.write().await = Some(CommitmentLocal {
+            commitment: commitment.clone(),
+            chave_publica,
+            nonce,
+        });
+        self.adicionar_commitment(commitment.clone()).await?;
+        
+        Ok(commitment)
+    }
+    
+    pub async fn processar_commitments(&self) -> Result<()> {
+        let total = self.commitments.read().await.len();
+        info!("Fase de registro: {} commitments coletados", total);
         
         Ok(())
     }
     
     pub async fn adicionar_commitment(&self, commitment: Commitment) -> Result<bool> {
-        if self.validador.validar(&commitment)? {
+        let estado = self.rodada.estado_atual().await;
+        if self.validador.validar(&commitment, &estado)? {
             let mut commitments = self.commitments.write().await;
-            commitments.insert(commitment.no_id.clone(), commitment);
+            
+            // Um commitment por nó e rodada: trocar depois de ver os outros permitiria manipular o seed
+            if commitments.get(&commitment.no_id).is_some_and(|c| c.rodada == commitment.rodada) {
+                warn!("Commitment duplicado de {} na rodada {}", commitment.no_id, commitment.rodada);
+                return Ok(false);
+            }
+            
+            commitments.insert(commitment.no_id, commitment);
             info!("Commitment adicionado com sucesso");
             Ok(true)
         } else {
-            warn!("Commitment inválido rejeitado");
+            warn!(
+                "Commitment de {} rejeitado (rodada {}, atual {} na fase {:?})",
+                commitment.no_id, commitment.rodada, estado.numero, estado.fase
+            );
             Ok(false)
         }
     }
     
-    pub async fn obter_commitments(&self) -> HashMap<String, Commitment> {
+    pub async fn obter_commitments(&self) -> HashMap<NodeId, Commitment> {
         self.commitments.read().await.clone()
     }
     
+    pub async fn obter_commitment_local(&self) -> Option<CommitmentLocal> {
+        self.commitment_local.read().await.clone()
+    }
+    
     pub async fn limpar_commitments(&self) {
         self.commitments.write().await.clear();
+        *self.commitment_local.write().await = None;
     }
 }
\ No newline at end of file
@@ -1,34 +1,27 @@
+use crate::consenso::rodada::{EstadoRodada, FaseRodada};
 use crate::consenso::tipos::*;
 use anyhow::Result;
-use chrono::{Duration, Utc};
 
-pub struct ValidadorCommitment {
-    janela_tempo: Duration,
-}
+pub struct ValidadorCommitment;
 
 impl ValidadorCommitment {
     pub fn new() -> Self {
-        Self {
-            janela_tempo: Duration::minutes(5), // 5 minutos de janela
-        }
+        Self
     }
     
-    pub fn validar(&self, commitment: &Commitment) -> Result<bool> {
-        // Validar timestamp
-        let agora = Utc::now();
-        let tempo_limite = agora - self.janela_tempo;
-        
-        if commitment.timestamp < tempo_limite {
+    pub fn validar(&self, commitment: &Commitment, estado: &EstadoRodada) -> Result<bool> {
+        // Só aceita commitments da rodada atual, durante a fase de commit
+        if !estado.aceita(FaseRodada::Commit, commitment.rodada) {
             return Ok(false);
         }
         
-        // Validar formato do hash
-        if commitment.hash.len() != 32 {
+        // Validar timestamp contra a janela da fase
+        if !estado.dentro_da_janela(&commitment.timestamp) {
             return Ok(false);
         }
         
-        // Validar ID do nó
-        if commitment.no_id.is_empty() {
+        // Validar formato do hash
+        if commitment.hash.len() != 32 {
             return Ok(false);
         }
         
@@ -1,26 +1,51 @@
+use crate::consenso::tipos::NodeId;
 use tokio::sync::RwLock;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub struct FilaAprovados {
-    nos_aprovados: RwLock<HashSet<String>>,
+    nos_aprovados: RwLock<HashSet<NodeId>>,
+    /// Nós suspensos e a última rodada (inclusive) em que ficam fora da fila
+    excluidos: RwLock<HashMap<NodeId, u64>>,
 }
 
 impl FilaAprovados {
     pub fn new() -> Self {
         Self {
             nos_aprovados: RwLock::new(HashSet::new()),
+            excluidos: RwLock::new(HashMap::new()),
         }
     }
     
-    pub async fn adicionar_no_aprovado(&self, no_id: String) {
+    /// Aprova o nó para a rodada, a menos que esteja suspenso nela
+    pub async fn adicionar_no_aprovado(&self, no_id: NodeId, rodada: u64) -> bool {
+        if self.esta_excluido(&no_id, rodada).await {
+            return false;
+        }
+        
         self.nos_aprovados.write().await.insert(no_id);
+        true
     }
     
-    pub async fn obter_nos_aprovados(&self) -> Vec<String> {
+    pub async fn obter_nos_aprovados(&self) -> Vec<NodeId> {
         self.nos_aprovados.read().await.iter().cloned().collect()
     }
     
-    pub async fn limpar(&self) {
+    /// Suspende o nó até a rodada `ate_rodada`, inclusive; suspensões só são estendidas
+    pub async fn excluir(&self, no_id: NodeId, ate_rodada: u64) {
+        let mut excluidos = self.excluidos.write().await;
+        let limite = excluidos.entry(no_id).or_insert(ate_rodada);
+        *limite = (*limite).max(ate_rodada);
+    }
+    
+    pub async fn esta_excluido(&self, no_id: &NodeId, rodada: u64) -> bool {
+        self.excluidos.read().await
+            .get(no_id)
+            .is_some_and(|ate_rodada| rodada <= *ate_rodada)
+    }
+    
+    /// Limpa os aprovados da rodada; suspensões já cumpridas também são descartadas
+    pub async fn limpar(&self, rodada: u64) {
         self.nos_aprovados.write().await.clear();
+        self.excluidos.write().await.retain(|_, ate_rodada| *ate_rodada > rodada);
     }
 }
\ No newline at end of file
@@ -1,9 +1,12 @@
 mod verificador;
 mod fila_aprovados;
 
+use crate::consenso::rodada::{FaseRodada, MaquinaRodada};
 use crate::consenso::tipos::*;
+use crate::consenso::validacao::DetectorMaliciosos;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
@@ -11,50 +14,199 @@ pub use verificador::*;
 pub use fila_aprovados::*;
 
 pub struct CamadaReveal {
-    reveals: RwLock<HashMap<String, Reveal>>,
+    reveals: RwLock<HashMap<NodeId, Reveal>>,
     verificador: VerificadorReveal,
     fila_aprovados: FilaAprovados,
+    rodada: Arc<MaquinaRodada>,
+    detector_maliciosos: Arc<DetectorMaliciosos>,
 }
 
 impl CamadaReveal {
-    pub fn new() -> Self {
+    pub fn new(rodada: Arc<MaquinaRodada>, detector_maliciosos: Arc<DetectorMaliciosos>) -> Self {
         Self {
             reveals: RwLock::new(HashMap::new()),
             verificador: VerificadorReveal::new(),
             fila_aprovados: FilaAprovados::new(),
+            rodada,
+            detector_maliciosos,
         }
     }
     
-    pub async fn processar_reveals(&self) -> Result<()> {
-        info!("Processando reveals da fase de verificação");
+    /// Encerra a janela de reveal da `rodada` e penaliza quem fez commitment e não revelou.
+    ///
+    /// Sem a penalidade, um nó poderia escolher revelar ou não depois de ver os
+    /// outros reveals e assim enviesar a ordenação. Retorna os nós penalizados.
+    pub async fn processar_reveals(&self, rodada: u64, commitments: &HashMap<NodeId, Commitment>) -> Result<Vec<NodeId>> {
+        let reveals = self.reveals.read().await;
+        info!("Fase de reveal: {} reveals verificados", reveals.len());
         
-        // Implementar lógica de processamento
+        let mut omitidos: Vec<NodeId> = commitments
+            .values()
+            .filter(|c| c.rodada == rodada && !reveals.contains_key(&c.no_id))
+            .map(|c| c.no_id)
+            .collect();
+        omitidos.sort();
+        drop(reveals);
         
-        Ok(())
+        if omitidos.is_empty() {
+            return Ok(omitidos);
+        }
+        
+        warn!("{} nós fizeram commitment sem reveal na rodada {}", omitidos.len(), rodada);
+        self.detector_maliciosos.reportar_reveals_omitidos(&omitidos, rodada);
+        
+        let ate_rodada = rodada + self.rodada.configuracao().rodadas_exclusao_reveal;
+        for no_id in &omitidos {
+            self.fila_aprovados.excluir(*no_id, ate_rodada).await;
+        }
+        
+        Ok(omitidos)
     }
     
-    pub async fn adicionar_reveal(&self, reveal: Reveal, commitments: &HashMap<String, Commitment>) -> Result<bool> {
-        if let Some(commitment) = commitments.get(&reveal.no_id) {
-            if self.verificador.verificar(&reveal, commitment)? {
+    pub async fn adicionar_reveal(&self, reveal: Reveal, commitments: &HashMap<NodeId, Commitment>) -> Result<bool> {
+        // O id declarado precisa ser derivado da chave revelada
+        if !reveal.no_id.corresponde(&reveal.chave_publica) {
+            warn!("Reveal com id {} que não corresponde à chave pública", reveal.no_id);
+            return Ok(false);
+        }
+        
+        // Só aceita reveals da rodada atual, durante a fase de reveal
+        let estado = self.rodada.estado_atual().await;
+        if !estado.aceita(FaseRodada::Reveal, reveal.rodada) || !estado.dentro_da_janela(&reveal.timestamp) {
+            warn!(
+                "Reveal de {} rejeitado (rodada {}, atual {} na fase {:?})",
+                reveal.no_id, reveal.rodada, estado.numero, estado.fase
+            );
+            return Ok(false);
+        }
+        
+        if let Some(commitment) = commitments.get(&reveal.no_id).filter(|c| c.rodada == reveal.rodada) {
+            if self.verificador.verificar(&reveal, commitment, &self.rodada.configuracao().id_cadeia)? {
                 let mut reveals = self.reveals.write().await;
-                reveals.insert(reveal.no_id.clone(), reveal.clone());
+                reveals.insert(reveal.no_id, reveal.clone());
                 
-                // Adicionar à fila de aprovados
-                self.fila_aprovados.adicionar_no_aprovado(reveal.no_id.clone()).await;
-                
-                info!("Reveal verificado e nó aprovado");
+                // Adicionar à fila de aprovados, exceto se o nó estiver suspenso
+                if self.fila_aprovados.adicionar_no_aprovado(reveal.no_id, reveal.rodada).await {
+                    info!("Reveal verificado e nó aprovado");
+                } else {
+                    info!("Reveal de {} verificado, mas o nó está suspenso nesta rodada", reveal.no_id);
+                }
                 Ok(true)
             } else {
                 warn!("Reveal não corresponde ao commitment");
                 Ok(false)
             }
         } else {
-            warn!("Commitment não encontrado para o reveal");
+            warn!("Commitment da rodada {} não encontrado para o reveal", reveal.rodada);
             Ok(false)
         }
     }
     
-    pub async fn obter_nos_aprovados(&self) -> Vec<String> {
-        self.fila_aprovados.obter_nos_aprovados().await
+    /// Nós aprovados na rodada, montados a partir das chaves públicas dos seus reveals.
+    ///
+    /// O endereço de rede não faz parte do reveal; quem precisar dele resolve pelo id
+    /// na camada de comunicação.
+    pub async fn obter_nos_aprovados(&self) -> Vec<No> {
+        let aprovados = self.fila_aprovados.obter_nos_aprovados().await;
+        let reveals = self.reveals.read().await;
+        
+        let mut nos: Vec<No> = aprovados
+            .iter()
+            .filter_map(|no_id| reveals.get(no_id))
+            .map(|reveal| No::novo(reveal.chave_publica.clone(), String::new(), 0))
+            .collect();
+        nos.sort_by_key(|no| no.id);
+        
+        nos
+    }
+    
+    pub async fn obter_reveals(&self) -> HashMap<NodeId, Reveal> {
+        self.reveals.read().await.clone()
+    }
+    
+    pub async fn limpar(&self) {
+        self.reveals.write().await.clear();
+        self.fila_aprovados.limpar(self.rodada.rodada_atual().await).await;
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::registro::GeradorCommitment;
+
+    fn reveal(chaves: &ParChaves, nonce: &[u8], rodada: u64) -> Reveal {
+        Reveal {
+            chave_publica: chaves.chave_publica(),
+            nonce: nonce.to_vec(),
+            no_id: chaves.no_id(),
+            timestamp: chrono::Utc::now(),
+            rodada,
+        }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn commitment_ou_reveal_de_outra_rodada_ou_cadeia_e_recusado() {
+        let maquina = Arc::new(MaquinaRodada::new());
+        let rodada = maquina.avancar().await.numero;
+        let camada = CamadaReveal::new(maquina, Arc::new(DetectorMaliciosos::new()));
+
+        let chaves = ParChaves::gerar();
+        let nonce = GeradorCommitment::gerar_nonce();
+        let commitments_com = |commitment: Commitment| HashMap::from([(chaves.no_id(), commitment)]);
+        let commitment = |id_cadeia: &str, rodada_hash: u64| {
+            let mut commitment = GeradorCommitment::criar_commitment(id_cadeia, rodada_hash, &chaves.chave_publica(), &nonce, chaves.no_id());
+            commitment.rodada = rodada;
+            commitment
+        };
+
+        // Mesma chave e nonce, mas o hash foi feito para outra rede ou outra rodada
+        let de_outra_cadeia = commitments_com(commitment("outra-rede", rodada));
+        assert!(!camada.adicionar_reveal(reveal(&chaves, &nonce, rodada), &de_outra_cadeia).await.unwrap());
+        let de_outra_rodada = commitments_com(commitment(ID_CADEIA_PADRAO, rodada - 1));
+        assert!(!camada.adicionar_reveal(reveal(&chaves, &nonce, rodada), &de_outra_rodada).await.unwrap());
+
+        // Reveal antigo reapresentado: não é desta rodada
+        let valido = commitments_com(commitment(ID_CADEIA_PADRAO, rodada));
+        assert!(!camada.adicionar_reveal(reveal(&chaves, &nonce, rodada - 1), &valido).await.unwrap());
+        assert!(camada.obter_nos_aprovados().await.is_empty());
+
+        assert!(camada.adicionar_reveal(reveal(&chaves, &nonce, rodada), &valido).await.unwrap());
+        assert_eq!(camada.obter_nos_aprovados().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn quem_fez_commitment_e_nao_revelou_fica_fora_da_fila() {
+        let maquina = Arc::new(MaquinaRodada::new());
+        let rodada = maquina.avancar().await.numero;
+        let detector = Arc::new(DetectorMaliciosos::new());
+        let camada = CamadaReveal::new(maquina.clone(), detector.clone());
+
+        let chaves = apoio_testes::chaves(2);
+        let nonces = [GeradorCommitment::gerar_nonce(), GeradorCommitment::gerar_nonce()];
+        let commitments_da = |rodada: u64| -> HashMap<NodeId, Commitment> {
+            chaves.iter().zip(&nonces)
+                .map(|(c, nonce)| (c.no_id(), GeradorCommitment::criar_commitment(ID_CADEIA_PADRAO, rodada, &c.chave_publica(), nonce, c.no_id())))
+                .collect()
+        };
+
+        // Os dois fizeram commitment; só o primeiro revela
+        let commitments = commitments_da(rodada);
+        assert!(camada.adicionar_reveal(reveal(&chaves[0], &nonces[0], rodada), &commitments).await.unwrap());
+        assert_eq!(camada.processar_reveals(rodada, &commitments).await.unwrap(), vec![chaves[1].no_id()]);
+
+        let aprovados: Vec<NodeId> = camada.obter_nos_aprovados().await.iter().map(|no| no.id).collect();
+        assert_eq!(aprovados, vec![chaves[0].no_id()]);
+        assert_eq!(detector.obter_historico_suspeitas().len(), 1);
+
+        // Na rodada seguinte o reveal dele confere, mas ele continua suspenso
+        camada.limpar().await;
+        maquina.encerrar_rodada().await;
+        let seguinte = maquina.avancar().await.numero;
+        let commitments = commitments_da(seguinte);
+        assert!(camada.adicionar_reveal(reveal(&chaves[1], &nonces[1], seguinte), &commitments).await.unwrap());
+        assert!(camada.obter_nos_aprovados().await.is_empty());
+    }
+}
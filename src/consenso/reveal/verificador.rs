@@ -8,9 +8,14 @@ impl VerificadorReveal {
         Self
     }
     
-    pub fn verificar(&self, reveal: &Reveal, commitment: &Commitment) -> Result<bool> {
-        // Recalcular o commitment usando os dados revelados
-        let commitment_calculado = calcular_commitment(&reveal.chave_publica, &reveal.nonce);
+    pub fn verificar(&self, reveal: &Reveal, commitment: &Commitment, id_cadeia: &str) -> Result<bool> {
+        // Reveal e commitment precisam ser da mesma rodada
+        if reveal.rodada != commitment.rodada {
+            return Ok(false);
+        }
+        
+        // Recalcular o commitment usando os dados revelados, a rodada e o id desta cadeia
+        let commitment_calculado = calcular_commitment(id_cadeia, reveal.rodada, &reveal.chave_publica, &reveal.nonce);
         
         // Verificar se o commitment calculado corresponde ao original
         Ok(commitment_calculado == commitment.hash)
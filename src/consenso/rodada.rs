@@ -0,0 +1,315 @@
+use crate::consenso::ordenacao::ModoOrdenacao;
+use crate::consenso::tipos::ID_CADEIA_PADRAO;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tokio::time::Duration;
+use tracing::info;
+
+/// Fases de uma rodada de consenso, na ordem em que acontecem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FaseRodada {
+    Commit,
+    Reveal,
+    Ordenacao,
+    Processamento,
+    Validacao,
+    Finalizacao,
+}
+
+impl FaseRodada {
+    /// Próxima fase; depois da finalização começa o commit da rodada seguinte
+    pub fn proxima(&self) -> FaseRodada {
+        match self {
+            FaseRodada::Commit => FaseRodada::Reveal,
+            FaseRodada::Reveal => FaseRodada::Ordenacao,
+            FaseRodada::Ordenacao => FaseRodada::Processamento,
+            FaseRodada::Processamento => FaseRodada::Validacao,
+            FaseRodada::Validacao => FaseRodada::Finalizacao,
+            FaseRodada::Finalizacao => FaseRodada::Commit,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfiguracaoRodada {
+    /// Identifica a rede; entra no hash dos commitments
+    pub id_cadeia: String,
+    pub duracao_commit: Duration,
+    pub duracao_reveal: Duration,
+    pub duracao_ordenacao: Duration,
+    pub duracao_processamento: Duration,
+    /// Prazo de cada processador da fila, dentro do processamento, antes de a fila votar para pulá-lo
+    pub duracao_slot: Duration,
+    pub duracao_validacao: Duration,
+    pub duracao_finalizacao: Duration,
+    /// Diferença de relógio tolerada ao comparar timestamps com a janela da fase
+    pub desvio_relogio: Duration,
+    /// Rodadas seguintes em que um nó que não revelou fica fora da fila de aprovados
+    pub rodadas_exclusao_reveal: u64,
+    /// Como a fila de processadores é sorteada; todos os nós da rede precisam usar o mesmo
+    pub modo_ordenacao: ModoOrdenacao,
+}
+
+impl Default for ConfiguracaoRodada {
+    fn default() -> Self {
+        Self {
+            id_cadeia: ID_CADEIA_PADRAO.to_string(),
+            duracao_commit: Duration::from_secs(10),
+            duracao_reveal: Duration::from_secs(10),
+            duracao_ordenacao: Duration::from_secs(2),
+            duracao_processamento: Duration::from_secs(10),
+            duracao_slot: Duration::from_secs(3),
+            duracao_validacao: Duration::from_secs(5),
+            duracao_finalizacao: Duration::from_secs(2),
+            desvio_relogio: Duration::from_secs(2),
+            rodadas_exclusao_reveal: 3,
+            modo_ordenacao: ModoOrdenacao::default(),
+        }
+    }
+}
+
+impl ConfiguracaoRodada {
+    pub fn duracao(&self, fase: FaseRodada) -> Duration {
+        match fase {
+            FaseRodada::Commit => self.duracao_commit,
+            FaseRodada::Reveal => self.duracao_reveal,
+            FaseRodada::Ordenacao => self.duracao_ordenacao,
+            FaseRodada::Processamento => self.duracao_processamento,
+            FaseRodada::Validacao => self.duracao_validacao,
+            FaseRodada::Finalizacao => self.duracao_finalizacao,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EstadoRodada {
+    pub numero: u64,
+    pub fase: FaseRodada,
+    pub inicio_fase: DateTime<Utc>,
+    pub prazo: DateTime<Utc>,
+    pub desvio_relogio: chrono::Duration,
+}
+
+impl EstadoRodada {
+    /// Confere se um item da `fase` e `rodada` informadas pode ser aceito agora
+    pub fn aceita(&self, fase: FaseRodada, rodada: u64) -> bool {
+        self.fase == fase && self.numero == rodada
+    }
+
+    /// Confere se o timestamp cai na janela da fase atual, com a tolerância de relógio
+    pub fn dentro_da_janela(&self, timestamp: &DateTime<Utc>) -> bool {
+        *timestamp >= self.inicio_fase - self.desvio_relogio
+            && *timestamp <= self.prazo + self.desvio_relogio
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EventoRodada {
+    FaseIniciada {
+        rodada: u64,
+        fase: FaseRodada,
+        prazo: DateTime<Utc>,
+    },
+    RodadaFinalizada {
+        rodada: u64,
+    },
+}
+
+/// Máquina de estados das rodadas: numera as rodadas, controla o prazo de cada
+/// fase e publica um evento a cada transição.
+///
+/// É compartilhada (via `Arc`) com as camadas de registro e reveal, que a
+/// consultam para recusar commitments e reveals fora da fase ou da rodada.
+pub struct MaquinaRodada {
+    configuracao: ConfiguracaoRodada,
+    estado: RwLock<EstadoRodada>,
+    eventos: broadcast::Sender<EventoRodada>,
+}
+
+impl MaquinaRodada {
+    pub fn new() -> Self {
+        Self::new_com_configuracao(ConfiguracaoRodada::default())
+    }
+
+    pub fn new_com_configuracao(configuracao: ConfiguracaoRodada) -> Self {
+        let (eventos, _) = broadcast::channel(64);
+        let estado = Self::novo_estado(&configuracao, 1, FaseRodada::Commit);
+
+        Self {
+            configuracao,
+            estado: RwLock::new(estado),
+            eventos,
+        }
+    }
+
+    pub fn configuracao(&self) -> &ConfiguracaoRodada {
+        &self.configuracao
+    }
+
+    pub async fn estado_atual(&self) -> EstadoRodada {
+        self.estado.read().await.clone()
+    }
+
+    pub async fn rodada_atual(&self) -> u64 {
+        self.estado.read().await.numero
+    }
+
+    pub async fn fase_atual(&self) -> FaseRodada {
+        self.estado.read().await.fase
+    }
+
+    pub fn inscrever_eventos(&self) -> broadcast::Receiver<EventoRodada> {
+        self.eventos.subscribe()
+    }
+
+    /// Reinicia o prazo da fase atual a partir de agora e anuncia seu início
+    pub async fn iniciar_fase(&self) -> EstadoRodada {
+        let mut estado = self.estado.write().await;
+        *estado = Self::novo_estado(&self.configuracao, estado.numero, estado.fase);
+
+        self.publicar_inicio(&estado);
+        estado.clone()
+    }
+
+    /// Passa para a próxima fase; depois da finalização, abre o commit da rodada seguinte
+    pub async fn avancar(&self) -> EstadoRodada {
+        let mut estado = self.estado.write().await;
+
+        let numero = if estado.fase == FaseRodada::Finalizacao {
+            // Ninguém inscrito não é erro
+            let _ = self.eventos.send(EventoRodada::RodadaFinalizada { rodada: estado.numero });
+            info!("🏁 Rodada {} finalizada", estado.numero);
+            estado.numero + 1
+        } else {
+            estado.numero
+        };
+
+        *estado = Self::novo_estado(&self.configuracao, numero, estado.fase.proxima());
+
+        self.publicar_inicio(&estado);
+        estado.clone()
+    }
+
+    /// Abandona a rodada atual e abre o commit da seguinte, sem passar pelas fases restantes
+    pub async fn encerrar_rodada(&self) -> EstadoRodada {
+        let mut estado = self.estado.write().await;
+        let _ = self.eventos.send(EventoRodada::RodadaFinalizada { rodada: estado.numero });
+
+        *estado = Self::novo_estado(&self.configuracao, estado.numero + 1, FaseRodada::Commit);
+
+        self.publicar_inicio(&estado);
+        estado.clone()
+    }
+
+    fn publicar_inicio(&self, estado: &EstadoRodada) {
+        info!("⏱️ Rodada {}: fase {:?} até {}", estado.numero, estado.fase, estado.prazo);
+
+        let _ = self.eventos.send(EventoRodada::FaseIniciada {
+            rodada: estado.numero,
+            fase: estado.fase,
+            prazo: estado.prazo,
+        });
+    }
+
+    fn novo_estado(configuracao: &ConfiguracaoRodada, numero: u64, fase: FaseRodada) -> EstadoRodada {
+        let inicio_fase = Utc::now();
+        let duracao = chrono::Duration::from_std(configuracao.duracao(fase)).unwrap_or_default();
+        let desvio_relogio = chrono::Duration::from_std(configuracao.desvio_relogio).unwrap_or_default();
+
+        EstadoRodada {
+            numero,
+            fase,
+            inicio_fase,
+            prazo: inicio_fase + duracao,
+            desvio_relogio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    #[tokio::test]
+    async fn fases_em_ordem_e_nova_rodada_depois_da_finalizacao() {
+        let maquina = MaquinaRodada::new();
+        let mut eventos = maquina.inscrever_eventos();
+
+        let mut fases = vec![maquina.fase_atual().await];
+        for _ in 0..5 {
+            let estado = maquina.avancar().await;
+            assert_eq!(estado.numero, 1);
+            fases.push(estado.fase);
+        }
+        assert_eq!(
+            fases,
+            [
+                FaseRodada::Commit,
+                FaseRodada::Reveal,
+                FaseRodada::Ordenacao,
+                FaseRodada::Processamento,
+                FaseRodada::Validacao,
+                FaseRodada::Finalizacao,
+            ]
+        );
+
+        let estado = maquina.avancar().await;
+        assert_eq!((estado.numero, estado.fase), (2, FaseRodada::Commit));
+
+        // Cinco inícios de fase, depois o fim da rodada 1 e o commit da 2
+        for _ in 0..5 {
+            assert!(matches!(eventos.recv().await, Ok(EventoRodada::FaseIniciada { rodada: 1, .. })));
+        }
+        assert!(matches!(eventos.recv().await, Ok(EventoRodada::RodadaFinalizada { rodada: 1 })));
+        assert!(matches!(
+            eventos.recv().await,
+            Ok(EventoRodada::FaseIniciada { rodada: 2, fase: FaseRodada::Commit, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn encerrar_rodada_pula_as_fases_restantes() {
+        let maquina = MaquinaRodada::new();
+        maquina.avancar().await;
+
+        let estado = maquina.encerrar_rodada().await;
+        assert_eq!((estado.numero, estado.fase), (2, FaseRodada::Commit));
+    }
+
+    #[tokio::test]
+    async fn prazo_segue_a_duracao_configurada() {
+        let configuracao = ConfiguracaoRodada {
+            duracao_reveal: Duration::from_secs(7),
+            desvio_relogio: Duration::from_secs(1),
+            ..ConfiguracaoRodada::default()
+        };
+        let maquina = MaquinaRodada::new_com_configuracao(configuracao);
+
+        let estado = maquina.avancar().await;
+        assert_eq!(estado.fase, FaseRodada::Reveal);
+        assert_eq!(estado.prazo - estado.inicio_fase, chrono::Duration::seconds(7));
+
+        let um_segundo = chrono::Duration::seconds(1);
+        assert!(estado.dentro_da_janela(&estado.inicio_fase));
+        assert!(estado.dentro_da_janela(&(estado.inicio_fase - um_segundo)));
+        assert!(estado.dentro_da_janela(&(estado.prazo + um_segundo)));
+        assert!(!estado.dentro_da_janela(&(estado.inicio_fase - um_segundo * 2)));
+        assert!(!estado.dentro_da_janela(&(estado.prazo + um_segundo * 2)));
+    }
+
+    #[tokio::test]
+    async fn aceita_somente_a_fase_e_a_rodada_atuais() {
+        let maquina = MaquinaRodada::new();
+        let estado = maquina.estado_atual().await;
+
+        assert!(estado.aceita(FaseRodada::Commit, 1));
+        assert!(!estado.aceita(FaseRodada::Reveal, 1));
+        assert!(!estado.aceita(FaseRodada::Commit, 2));
+        assert!(!estado.aceita(FaseRodada::Commit, 0));
+
+        let estado = maquina.avancar().await;
+        assert!(estado.aceita(FaseRodada::Reveal, 1));
+        assert!(!estado.aceita(FaseRodada::Commit, 1));
+    }
+}
@@ -1,28 +1,98 @@
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use crate::consenso::cripto::{verificar_assinatura, BytesCanonicos, ParChaves};
 
+/// Identidade de um nó: hash da sua chave pública.
+///
+/// Como o id é derivado da chave, um operador não consegue registrar vários ids
+/// com a mesma chave, e qualquer mensagem pode ter o id declarado conferido.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct NodeId(pub [u8; 32]);
+
+impl NodeId {
+    pub fn da_chave_publica(chave_publica: &[u8]) -> Self {
+        let dados = BytesCanonicos::new("NIMBOS_NODE_ID_V1")
+            .campo(chave_publica)
+            .finalizar();
+        
+        let mut id = [0u8; 32];
+        id.copy_from_slice(&calcular_hash(&dados));
+        Self(id)
+    }
+    
+    /// Confere se este id foi derivado da chave pública informada
+    pub fn corresponde(&self, chave_publica: &[u8]) -> bool {
+        *self == Self::da_chave_publica(chave_publica)
+    }
+    
+    pub fn como_bytes(&self) -> &[u8] {
+        &self.0
+    }
+    
+    /// Id a partir do endereço da conta (o hex do id, como em `endereco_da_chave`)
+    pub fn de_endereco(endereco: &str) -> Option<Self> {
+        let bytes = hex::decode(endereco).ok()?;
+        Some(Self(bytes.try_into().ok()?))
+    }
+}
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+/// Nó da rede, compartilhado por consenso (ordenação, merkle) e comunicação
+/// (broadcast, retry, `GerenciadorRede`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct No {
-    pub id: String,
+    pub id: NodeId,
     pub chave_publica: Vec<u8>,
     pub endereco: String,
+    pub porta: u16,
     pub ativo: bool,
+    pub ultima_resposta: chrono::DateTime<chrono::Utc>,
+}
+
+impl No {
+    pub fn novo(chave_publica: Vec<u8>, endereco: String, porta: u16) -> Self {
+        Self {
+            id: NodeId::da_chave_publica(&chave_publica),
+            chave_publica,
+            endereco,
+            porta,
+            ativo: true,
+            ultima_resposta: chrono::Utc::now(),
+        }
+    }
+    
+    /// Endereço de rede no formato `host:porta`
+    pub fn endereco_completo(&self) -> String {
+        format!("{}:{}", self.endereco, self.porta)
+    }
+    
+    /// Verifica que o id declarado corresponde à chave pública do nó
+    pub fn identidade_valida(&self) -> bool {
+        self.id.corresponde(&self.chave_publica)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Commitment {
     pub hash: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub no_id: String,
+    pub no_id: NodeId,
+    pub rodada: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Reveal {
     pub chave_publica: Vec<u8>,
     pub nonce: Vec<u8>,
-    pub no_id: String,
+    pub no_id: NodeId,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub rodada: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,13 +102,6 @@ pub struct FilaOrdenada {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProvaInclusao {
-    pub merkle_proof: Vec<Vec<u8>>,
-    pub posicao: usize,
-    pub merkle_root: Vec<u8>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transacao {
     pub id: String,
@@ -48,27 +111,225 @@ pub struct Transacao {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub nonce: u64,
     pub assinatura: Vec<u8>,
+    /// Presente nas transações de transferência entre contas
+    #[serde(default)]
+    pub transferencia: Option<Transferencia>,
+}
+
+/// Endereço da conta controlada por uma chave: o mesmo hex do `NodeId` da chave
+pub fn endereco_da_chave(chave_publica: &[u8]) -> String {
+    NodeId::da_chave_publica(chave_publica).to_string()
+}
+
+/// Destinos reservados: a transferência para eles muda o stake de quem assina
+pub const DESTINO_VINCULAR_STAKE: &str = "stake:vincular";
+pub const DESTINO_DESVINCULAR_STAKE: &str = "stake:desvincular";
+/// Prefixo das chaves de stake no estado; nenhuma conta comum pode usá-lo
+pub const PREFIXO_STAKE: &str = "stake:";
+
+/// O que uma transferência para um destino reservado faz com o stake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperacaoStake {
+    /// `valor` sai do saldo e passa a contar para a ordenação
+    Vincular,
+    /// `valor` deixa de contar para a ordenação e volta ao saldo depois do período de desvínculo
+    Desvincular,
+}
+
+/// Transferência de saldo assinada pelo dono da conta de origem
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transferencia {
+    pub de: String,
+    pub para: String,
+    pub valor: u64,
+    /// Paga ao produtor do bloco
+    pub taxa: u64,
+    /// Precisa ser igual ao nonce atual da conta de origem
+    pub nonce: u64,
+    pub chave_publica: Vec<u8>,
+    pub assinatura: Vec<u8>,
+}
+
+impl Transferencia {
+    pub fn nova(chaves: &ParChaves, para: String, valor: u64, taxa: u64, nonce: u64) -> Self {
+        let mut transferencia = Self {
+            de: endereco_da_chave(&chaves.chave_publica()),
+            para,
+            valor,
+            taxa,
+            nonce,
+            chave_publica: chaves.chave_publica(),
+            assinatura: Vec::new(),
+        };
+        
+        transferencia.assinatura = chaves.assinar(&transferencia.bytes_para_assinatura());
+        transferencia
+    }
+    
+    /// Vincula `valor` do saldo da conta da chave como stake do nó
+    pub fn vincular_stake(chaves: &ParChaves, valor: u64, taxa: u64, nonce: u64) -> Self {
+        Self::nova(chaves, DESTINO_VINCULAR_STAKE.to_string(), valor, taxa, nonce)
+    }
+    
+    /// Inicia o desvínculo de `valor` do stake do nó
+    pub fn desvincular_stake(chaves: &ParChaves, valor: u64, taxa: u64, nonce: u64) -> Self {
+        Self::nova(chaves, DESTINO_DESVINCULAR_STAKE.to_string(), valor, taxa, nonce)
+    }
+    
+    pub fn operacao_stake(&self) -> Option<OperacaoStake> {
+        match self.para.as_str() {
+            DESTINO_VINCULAR_STAKE => Some(OperacaoStake::Vincular),
+            DESTINO_DESVINCULAR_STAKE => Some(OperacaoStake::Desvincular),
+            _ => None,
+        }
+    }
+    
+    /// Quanto sai do saldo da origem: valor e taxa, ou só a taxa no desvínculo.
+    /// `None` se a soma estoura.
+    pub fn debito(&self) -> Option<u64> {
+        match self.operacao_stake() {
+            Some(OperacaoStake::Desvincular) => Some(self.taxa),
+            _ => self.valor.checked_add(self.taxa),
+        }
+    }
+    
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_TRANSFERENCIA_V1")
+            .campo(self.de.as_bytes())
+            .campo(self.para.as_bytes())
+            .inteiro(self.valor)
+            .inteiro(self.taxa)
+            .inteiro(self.nonce)
+            .campo(&self.chave_publica)
+            .finalizar()
+    }
+    
+    /// A conta de origem é a da chave que assinou
+    pub fn verificar_assinatura(&self) -> bool {
+        self.de == endereco_da_chave(&self.chave_publica)
+            && verificar_assinatura(&self.chave_publica, &self.bytes_para_assinatura(), &self.assinatura)
+    }
+    
+    /// Id da transação que carrega esta transferência: `H(bytes assinados || assinatura)`
+    pub fn id_transacao(&self) -> String {
+        let mut bytes = self.bytes_para_assinatura();
+        bytes.extend_from_slice(&self.assinatura);
+        hex::encode(calcular_hash(&bytes))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidacaoConsenso {
     pub hash_transacao: Vec<u8>,
     pub hash_cadeia: Vec<u8>,
-    pub validador_id: String,
+    pub validador_id: NodeId,
     pub assinatura: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl Transacao {
+    /// Transação que carrega uma transferência; o id é o hash da transferência assinada
+    pub fn de_transferencia(transferencia: Transferencia) -> Self {
+        Self {
+            id: transferencia.id_transacao(),
+            dados: transferencia.bytes_para_assinatura(),
+            estado_anterior: Vec::new(),
+            estado_final: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            nonce: transferencia.nonce,
+            assinatura: Vec::new(),
+            transferencia: Some(transferencia),
+        }
+    }
+    
+    /// Id, dados e nonce são os que `de_transferencia` daria para a transferência
+    /// carregada; sem isso o mesmo pagamento poderia circular com outro id
+    pub fn corresponde_a_transferencia(&self) -> bool {
+        self.transferencia.as_ref().is_some_and(|transferencia| {
+            self.id == transferencia.id_transacao()
+                && self.dados == transferencia.bytes_para_assinatura()
+                && self.nonce == transferencia.nonce
+        })
+    }
+    
+    /// Bytes assinados pelo processador (a própria assinatura fica de fora)
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        let transferencia = self.transferencia.as_ref().map(|t| {
+            let mut bytes = t.bytes_para_assinatura();
+            bytes.extend_from_slice(&t.assinatura);
+            bytes
+        });
+        
+        BytesCanonicos::new("NIMBOS_TRANSACAO_V1")
+            .campo(self.id.as_bytes())
+            .campo(&self.dados)
+            .campo(&self.estado_anterior)
+            .campo(&self.estado_final)
+            .timestamp(&self.timestamp)
+            .inteiro(self.nonce)
+            .campo(&transferencia.unwrap_or_default())
+            .finalizar()
+    }
+    
+    /// Codificação canônica completa: os bytes assinados seguidos da assinatura do processador
+    pub fn codificar(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_TRANSACAO_CODIFICADA_V1")
+            .campo(&self.bytes_para_assinatura())
+            .campo(&self.assinatura)
+            .finalizar()
+    }
+    
+    pub fn hash(&self) -> Vec<u8> {
+        calcular_hash(&self.codificar())
+    }
+}
+
+impl ValidacaoConsenso {
+    /// Bytes assinados pelo validador (a própria assinatura fica de fora)
+    pub fn bytes_para_assinatura(&self) -> Vec<u8> {
+        BytesCanonicos::new("NIMBOS_VALIDACAO_V1")
+            .campo(&self.hash_transacao)
+            .campo(&self.hash_cadeia)
+            .campo(self.validador_id.como_bytes())
+            .timestamp(&self.timestamp)
+            .finalizar()
+    }
+}
+
 pub fn calcular_hash(dados: &[u8]) -> Vec<u8> {
     let mut hasher = Sha256::new();
     hasher.update(dados);
     hasher.finalize().to_vec()
 }
 
-pub fn calcular_commitment(chave_publica: &[u8], nonce: &[u8]) -> Vec<u8> {
-    let mut dados = Vec::new();
-    dados.extend_from_slice(chave_publica);
-    dados.extend_from_slice(nonce);
+/// Hash encadeado das transações processadas: `H(tag || hash_transacao || hash_anterior)`.
+///
+/// O processador e os validadores calculam o mesmo valor a partir de `Transacao::hash`.
+pub fn calcular_hash_cadeia(hash_transacao: &[u8], hash_anterior: &[u8]) -> Vec<u8> {
+    let dados = BytesCanonicos::new("NIMBOS_HASH_CADEIA_V1")
+        .campo(hash_transacao)
+        .campo(hash_anterior)
+        .finalizar();
+    
+    calcular_hash(&dados)
+}
+
+/// Id da cadeia usado quando nenhum é configurado
+pub const ID_CADEIA_PADRAO: &str = "nimbos-local";
+
+/// Hash do commitment de um nó para uma rodada.
+///
+/// A pré-imagem tem tag de domínio versionada e campos com prefixo de tamanho,
+/// e inclui o id da cadeia e a rodada: um commitment não pode ser reaproveitado
+/// em outra rodada nem em outra rede.
+pub fn calcular_commitment(id_cadeia: &str, rodada: u64, chave_publica: &[u8], nonce: &[u8]) -> Vec<u8> {
+    let dados = BytesCanonicos::new("NIMBOS_COMMITMENT_V1")
+        .campo(id_cadeia.as_bytes())
+        .inteiro(rodada)
+        .campo(chave_publica)
+        .campo(nonce)
+        .finalizar();
+    
     calcular_hash(&dados)
 }
 
@@ -77,4 +338,29 @@ pub fn calcular_position_hash(chave_publica: &[u8], seed_global: &[u8]) -> Vec<u
     dados.extend_from_slice(chave_publica);
     dados.extend_from_slice(seed_global);
     calcular_hash(&dados)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    #[test]
+    fn id_precisa_ser_derivado_da_chave_publica() {
+        let chaves = ParChaves::gerar();
+        let outra = ParChaves::gerar();
+
+        let no = No::novo(chaves.chave_publica(), "127.0.0.1".to_string(), 0);
+        assert_eq!(no.id, chaves.no_id());
+        assert!(no.identidade_valida());
+        assert!(!no.id.corresponde(&outra.chave_publica()));
+
+        // Id de um nó com a chave de outro, nos dois sentidos
+        let mut com_outro_id = no.clone();
+        com_outro_id.id = outra.no_id();
+        assert!(!com_outro_id.identidade_valida());
+
+        let mut com_outra_chave = no;
+        com_outra_chave.chave_publica = outra.chave_publica();
+        assert!(!com_outra_chave.identidade_valida());
+    }
+}
@@ -5,7 +5,7 @@ use tracing::{warn, info};
 
 #[derive(Debug, Clone)]
 pub struct ComportamentoSuspeito {
-    pub no_id: String,
+    pub no_id: NodeId,
     pub tipo_suspeita: TipoSuspeita,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub detalhes: String,
@@ -17,6 +17,10 @@ pub enum TipoSuspeita {
     AssinaturaInvalida,
     TempoResposta,
     ComportamentoAnomalo,
+    /// Enviou commitment e não revelou dentro da janela de reveal
+    RevealOmitido,
+    /// Era o processador da vez e a fila o pulou por perder o prazo do slot
+    ProcessadorAusente,
 }
 
 pub struct DetectorMaliciosos {
@@ -32,7 +36,7 @@ impl DetectorMaliciosos {
         }
     }
     
-    pub async fn analisar_validacoes(&self, validacoes: &[ValidacaoConsenso]) -> Result<Vec<String>> {
+    pub async fn analisar_validacoes(&self, validacoes: &[ValidacaoConsenso]) -> Result<Vec<NodeId>> {
         let mut nos_maliciosos = Vec::new();
         
         // Analisar inconsistências nos hashes
@@ -40,7 +44,7 @@ impl DetectorMaliciosos {
         
         for (no_id, _) in inconsistencias {
             self.registrar_suspeita(ComportamentoSuspeito {
-                no_id: no_id.clone(),
+                no_id,
                 tipo_suspeita: TipoSuspeita::HashInconsistente,
                 timestamp: chrono::Utc::now(),
                 detalhes: "Hash inconsistente com maioria".to_string(),
@@ -58,15 +62,60 @@ impl DetectorMaliciosos {
         Ok(nos_maliciosos)
     }
     
-    fn detectar_inconsistencias_hash(&self, validacoes: &[ValidacaoConsenso]) -> HashMap<String, Vec<u8>> {
-        let mut contadores: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+    /// Registra nós que enviaram commitment na rodada e não revelaram.
+    ///
+    /// Retorna os que, somando as suspeitas anteriores, passaram do limite.
+    pub fn reportar_reveals_omitidos(&self, nos: &[NodeId], rodada: u64) -> Vec<NodeId> {
+        let mut nos_maliciosos = Vec::new();
+        
+        for no_id in nos {
+            self.registrar_suspeita(ComportamentoSuspeito {
+                no_id: *no_id,
+                tipo_suspeita: TipoSuspeita::RevealOmitido,
+                timestamp: chrono::Utc::now(),
+                detalhes: format!("Commitment sem reveal na rodada {}", rodada),
+            });
+            
+            if self.contar_suspeitas(no_id) >= self.threshold_suspeita {
+                nos_maliciosos.push(*no_id);
+            }
+        }
+        
+        if !nos_maliciosos.is_empty() {
+            warn!("Nós maliciosos detectados por reveals omitidos: {:?}", nos_maliciosos);
+        }
+        
+        nos_maliciosos
+    }
+    
+    /// Registra o processador pulado por quorum da fila.
+    ///
+    /// Retorna `true` se, somando as suspeitas anteriores, ele passou do limite.
+    pub fn reportar_processador_ausente(&self, no_id: &NodeId, rodada: u64, slot: u64) -> bool {
+        self.registrar_suspeita(ComportamentoSuspeito {
+            no_id: *no_id,
+            tipo_suspeita: TipoSuspeita::ProcessadorAusente,
+            timestamp: chrono::Utc::now(),
+            detalhes: format!("Processador pulado no slot {} da rodada {}", slot, rodada),
+        });
+        
+        let malicioso = self.contar_suspeitas(no_id) >= self.threshold_suspeita;
+        if malicioso {
+            warn!("Nó malicioso detectado por ausência como processador: {}", no_id);
+        }
+        
+        malicioso
+    }
+    
+    fn detectar_inconsistencias_hash(&self, validacoes: &[ValidacaoConsenso]) -> HashMap<NodeId, Vec<u8>> {
+        let mut contadores: HashMap<Vec<u8>, Vec<NodeId>> = HashMap::new();
         
         // Agrupar validações por hash
         for validacao in validacoes {
             contadores
                 .entry(validacao.hash_transacao.clone())
                 .or_insert_with(Vec::new)
-                .push(validacao.validador_id.clone());
+                .push(validacao.validador_id);
         }
         
         // Encontrar hash majoritário
@@ -96,9 +145,9 @@ impl DetectorMaliciosos {
         }
     }
     
-    fn contar_suspeitas(&self, no_id: &str) -> usize {
+    fn contar_suspeitas(&self, no_id: &NodeId) -> usize {
         if let Ok(historico) = self.historico_suspeitas.read() {
-            historico.iter().filter(|s| s.no_id == no_id).count()
+            historico.iter().filter(|s| s.no_id == *no_id).count()
         } else {
             0
         }
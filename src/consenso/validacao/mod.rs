@@ -3,8 +3,11 @@ mod quorum;
 mod deteccao_maliciosos;
 
 use crate::consenso::tipos::*;
+use crate::consenso::cripto::Assinador;
+use crate::consenso::processamento::{LoteConfirmado, MaquinaCompartilhada};
 use anyhow::Result;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{info, warn};
 
@@ -15,38 +18,54 @@ pub use deteccao_maliciosos::*;
 pub struct CamadaValidacao {
     validador: ValidadorDistribuido,
     quorum: GerenciadorQuorum,
-    detector_maliciosos: DetectorMaliciosos,
+    detector_maliciosos: Arc<DetectorMaliciosos>,
     validacoes: RwLock<HashMap<String, Vec<ValidacaoConsenso>>>,
 }
 
 impl CamadaValidacao {
-    pub fn new() -> Self {
+    /// O detector é compartilhado com a camada de reveal, que reporta reveals omitidos;
+    /// a máquina de estado, com o processamento
+    pub fn new(assinador: Assinador, detector_maliciosos: Arc<DetectorMaliciosos>, maquina: MaquinaCompartilhada) -> Self {
         Self {
-            validador: ValidadorDistribuido::new(),
+            validador: ValidadorDistribuido::new(assinador, maquina),
             quorum: GerenciadorQuorum::new(0.7), // 70% de quórum
-            detector_maliciosos: DetectorMaliciosos::new(),
+            detector_maliciosos,
             validacoes: RwLock::new(HashMap::new()),
         }
     }
     
-    pub async fn validar_consenso(&self) -> Result<()> {
-        info!("Iniciando validação distribuída");
+    /// Reconfere as transações do bloco anexado na rodada: encadeamento dos
+    /// hashes e cada transição pela máquina de estado. As validações deste nó
+    /// contam para o quórum de cada transação.
+    pub async fn validar_consenso(&self, lote: &LoteConfirmado) -> Result<Vec<ValidacaoConsenso>> {
+        info!("Iniciando validação distribuída de {} transações", lote.transacoes.len());
         
-        // Simular validação de transação
-        // Em implementação real, receberia dados da camada de processamento
+        let mut hash_cadeia = lote.hash_cadeia_anterior.clone();
+        let mut validacoes = Vec::with_capacity(lote.transacoes.len());
+        for transacao in &lote.transacoes {
+            let validacao = self.validador.validar_transacao(transacao, &hash_cadeia).await
+                .map_err(|e| anyhow::anyhow!("Transação {} do bloco não confere: {}", transacao.id, e))?;
+            hash_cadeia = validacao.hash_cadeia.clone();
+            
+            self.validacoes.write().await
+                .entry(hex::encode(&validacao.hash_transacao))
+                .or_default()
+                .push(validacao.clone());
+            validacoes.push(validacao);
+        }
         
-        Ok(())
+        Ok(validacoes)
     }
     
-    pub async fn adicionar_validacao(&self, validacao: ValidacaoConsenso) -> Result<bool> {
+    pub async fn adicionar_validacao(&self, validacao: ValidacaoConsenso, validador: &No) -> Result<bool> {
         // Verificar se a validação é válida
-        if !self.validador.verificar_validacao(&validacao).await? {
+        if !self.validador.verificar_validacao(&validacao, validador).await? {
             warn!("Validação inválida rejeitada");
             return Ok(false);
         }
         
-        // Adicionar à lista de validações
-        let transacao_id = "tx_exemplo".to_string(); // Em implementação real, extrair do contexto
+        // Adicionar à lista de validações da transação
+        let transacao_id = hex::encode(&validacao.hash_transacao);
         self.validacoes.write().await
             .entry(transacao_id.clone())
             .or_insert_with(Vec::new)
@@ -73,10 +92,79 @@ impl CamadaValidacao {
         Ok(false)
     }
     
-    pub async fn obter_validacoes(&self, transacao_id: &str) -> Vec<ValidacaoConsenso> {
+    /// Validações recebidas para a transação de hash `hash_transacao`
+    pub async fn obter_validacoes(&self, hash_transacao: &[u8]) -> Vec<ValidacaoConsenso> {
         self.validacoes.read().await
-            .get(transacao_id)
+            .get(&hex::encode(hash_transacao))
             .cloned()
             .unwrap_or_default()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+    use crate::consenso::apoio_testes;
+    use crate::consenso::cripto::ParChaves;
+    use crate::consenso::processamento::{MaquinaEstado, MaquinaEstadoHash};
+
+    fn transacao(id: &str, dados: &[u8], estado_anterior: &[u8]) -> Transacao {
+        Transacao {
+            id: id.to_string(),
+            dados: dados.to_vec(),
+            estado_anterior: estado_anterior.to_vec(),
+            estado_final: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            nonce: 0,
+            assinatura: Vec::new(),
+            transferencia: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn valida_o_lote_da_rodada_pela_maquina_de_estado() {
+        let mut referencia = MaquinaEstadoHash::new();
+        let primeira = referencia.aplicar(&transacao("tx1", b"a", &referencia.raiz_estado()), "produtor").unwrap();
+        let segunda = referencia.aplicar(&transacao("tx2", b"b", &primeira.estado_final), "produtor").unwrap();
+        let lote = LoteConfirmado {
+            transacoes: vec![primeira.clone(), segunda.clone()],
+            hash_cadeia_anterior: vec![0; 32],
+        };
+
+        let maquina: MaquinaCompartilhada = Arc::new(tokio::sync::Mutex::new(Box::new(MaquinaEstadoHash::new())));
+        let validacao = CamadaValidacao::new(Assinador::new(ParChaves::gerar()), Arc::new(DetectorMaliciosos::new()), maquina);
+
+        let validacoes = validacao.validar_consenso(&lote).await.unwrap();
+        assert_eq!(validacoes.len(), 2);
+        let encadeado = calcular_hash_cadeia(&segunda.hash(), &calcular_hash_cadeia(&primeira.hash(), &[0; 32]));
+        assert_eq!(validacoes[1].hash_cadeia, encadeado);
+        assert_eq!(validacao.obter_validacoes(&primeira.hash()).await.len(), 1);
+
+        // Estado final que não deriva do anterior: a máquina recusa o lote
+        let mut adulterado = lote.clone();
+        adulterado.transacoes[1].estado_final = vec![9; 32];
+        assert!(validacao.validar_consenso(&adulterado).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn validacao_de_no_com_id_de_outra_chave_e_recusada() {
+        let validador = ParChaves::gerar();
+        let outra = ParChaves::gerar();
+        let maquina: MaquinaCompartilhada = Arc::new(tokio::sync::Mutex::new(Box::new(MaquinaEstadoHash::new())));
+        let camada = CamadaValidacao::new(Assinador::new(ParChaves::gerar()), Arc::new(DetectorMaliciosos::new()), maquina.clone());
+
+        let transacao = MaquinaEstadoHash::new().aplicar(&transacao("tx1", b"a", &[0; 32]), "produtor").unwrap();
+        let validacao = ValidadorDistribuido::new(Assinador::new(validador), maquina)
+            .validar_transacao(&transacao, &[0; 32]).await.unwrap();
+        let no = apoio_testes::no(&outra);
+
+        // O id assinado é o do validador, mas a chave apresentada é outra
+        let mut com_chave_trocada = no.clone();
+        com_chave_trocada.id = validacao.validador_id;
+        assert!(!camada.adicionar_validacao(validacao.clone(), &com_chave_trocada).await.unwrap());
+
+        // Nó coerente, mas não é quem assinou
+        assert!(!camada.adicionar_validacao(validacao.clone(), &no).await.unwrap());
+        assert!(camada.obter_validacoes(&transacao.hash()).await.is_empty());
+    }
+}
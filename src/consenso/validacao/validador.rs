@@ -1,16 +1,18 @@
 use crate::consenso::tipos::*;
+use crate::consenso::cripto::{Assinador, verificar_assinatura};
+use crate::consenso::processamento::MaquinaCompartilhada;
 use anyhow::Result;
 use tracing::{info, warn};
 
 pub struct ValidadorDistribuido {
-    id_validador: String,
+    assinador: Assinador,
+    /// A mesma máquina de estado do processamento, que confere cada transição
+    maquina: MaquinaCompartilhada,
 }
 
 impl ValidadorDistribuido {
-    pub fn new() -> Self {
-        Self {
-            id_validador: "validador_local".to_string(),
-        }
+    pub fn new(assinador: Assinador, maquina: MaquinaCompartilhada) -> Self {
+        Self { assinador, maquina }
     }
     
     pub async fn validar_transacao(&self, transacao: &Transacao, hash_cadeia_anterior: &[u8]) -> Result<ValidacaoConsenso> {
@@ -23,23 +25,25 @@ impl ValidadorDistribuido {
         let hash_cadeia = self.calcular_hash_cadeia(&hash_transacao, hash_cadeia_anterior)?;
         
         // Validar estado anterior/final
-        self.validar_estados(transacao)?;
+        self.validar_estados(transacao).await?;
         
-        // Criar validação
-        let validacao = ValidacaoConsenso {
+        // Criar validação (id e assinatura do mesmo par de chaves, mesmo durante rotação)
+        let chaves = self.assinador.chaves_atuais();
+        let mut validacao = ValidacaoConsenso {
             hash_transacao,
             hash_cadeia,
-            validador_id: self.id_validador.clone(),
-            assinatura: self.assinar_validacao(&hash_transacao, &hash_cadeia)?,
+            validador_id: chaves.no_id(),
+            assinatura: Vec::new(),
             timestamp: chrono::Utc::now(),
         };
+        validacao.assinatura = chaves.assinar(&validacao.bytes_para_assinatura());
         
         Ok(validacao)
     }
     
-    pub async fn verificar_validacao(&self, validacao: &ValidacaoConsenso) -> Result<bool> {
-        // Verificar assinatura
-        if !self.verificar_assinatura(validacao)? {
+    pub async fn verificar_validacao(&self, validacao: &ValidacaoConsenso, validador: &No) -> Result<bool> {
+        // Verificar assinatura contra a chave do validador declarado
+        if !self.verificar_assinatura(validacao, validador) {
             return Ok(false);
         }
         
@@ -59,19 +63,14 @@ impl ValidadorDistribuido {
     }
     
     fn calcular_hash_transacao(&self, transacao: &Transacao) -> Result<Vec<u8>> {
-        let dados = serde_json::to_vec(transacao)?;
-        Ok(calcular_hash(&dados))
+        Ok(transacao.hash())
     }
     
     fn calcular_hash_cadeia(&self, hash_transacao: &[u8], hash_anterior: &[u8]) -> Result<Vec<u8>> {
-        let mut dados = Vec::new();
-        dados.extend_from_slice(hash_transacao);
-        dados.extend_from_slice(hash_anterior);
-        Ok(calcular_hash(&dados))
+        Ok(calcular_hash_cadeia(hash_transacao, hash_anterior))
     }
     
-    fn validar_estados(&self, transacao: &Transacao) -> Result<()> {
-        // Validar que o estado final é derivado corretamente do anterior
+    async fn validar_estados(&self, transacao: &Transacao) -> Result<()> {
         if transacao.estado_anterior.is_empty() {
             return Err(anyhow::anyhow!("Estado anterior não pode estar vazio"));
         }
@@ -80,25 +79,25 @@ impl ValidadorDistribuido {
             return Err(anyhow::anyhow!("Estado final não pode estar vazio"));
         }
         
-        // Em implementação real, validaria a transição de estado
-        Ok(())
-    }
-    
-    fn assinar_validacao(&self, hash_transacao: &[u8], hash_cadeia: &[u8]) -> Result<Vec<u8>> {
-        let mut dados = Vec::new();
-        dados.extend_from_slice(hash_transacao);
-        dados.extend_from_slice(hash_cadeia);
-        dados.extend_from_slice(self.id_validador.as_bytes());
-        Ok(calcular_hash(&dados))
+        // A transição em si é regra da aplicação
+        self.maquina.lock().await.validar(transacao)
     }
     
-    fn verificar_assinatura(&self, validacao: &ValidacaoConsenso) -> Result<bool> {
-        let mut dados = Vec::new();
-        dados.extend_from_slice(&validacao.hash_transacao);
-        dados.extend_from_slice(&validacao.hash_cadeia);
-        dados.extend_from_slice(validacao.validador_id.as_bytes());
+    fn verificar_assinatura(&self, validacao: &ValidacaoConsenso, validador: &No) -> bool {
+        if !validador.identidade_valida() {
+            warn!("Nó {} não corresponde à sua chave pública", validador.id);
+            return false;
+        }
+        
+        if validacao.validador_id != validador.id {
+            warn!("Validação assinada por {} apresentada como {}", validador.id, validacao.validador_id);
+            return false;
+        }
         
-        let assinatura_esperada = calcular_hash(&dados);
-        Ok(assinatura_esperada == validacao.assinatura)
+        verificar_assinatura(
+            &validador.chave_publica,
+            &validacao.bytes_para_assinatura(),
+            &validacao.assinatura,
+        )
     }
 }
\ No newline at end of file
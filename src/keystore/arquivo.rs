@@ -0,0 +1,244 @@
+use crate::consenso::cripto::ParChaves;
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Versão atual do formato do arquivo; arquivos de outras versões são recusados
+pub const VERSAO_KEYSTORE: u32 = 1;
+
+const ALGORITMO_KDF: &str = "argon2id";
+const ALGORITMO_CIFRA: &str = "xchacha20poly1305";
+const TAMANHO_SAL: usize = 16;
+const TAMANHO_NONCE: usize = 24;
+const TAMANHO_CHAVE_CIFRA: usize = 32;
+
+// Limites dos parâmetros do Argon2 lidos do arquivo: um keystore adulterado não
+// pode pedir memória ou tempo arbitrários na abertura
+const MEMORIA_MAXIMA_KIB: u32 = 1024 * 1024;
+const ITERACOES_MAXIMAS: u32 = 16;
+const PARALELISMO_MAXIMO: u32 = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParametrosKdf {
+    pub algoritmo: String,
+    pub sal: String,
+    pub memoria_kib: u32,
+    pub iteracoes: u32,
+    pub paralelismo: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DadosCifrados {
+    pub algoritmo: String,
+    pub nonce: String,
+    pub texto_cifrado: String,
+}
+
+/// Formato JSON do keystore em disco.
+///
+/// A chave privada é cifrada com XChaCha20-Poly1305 usando uma chave derivada da
+/// senha por Argon2id. A chave pública vai como dado associado, então trocar o
+/// campo `chave_publica` do arquivo faz a decifragem falhar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArquivoKeystore {
+    pub versao: u32,
+    pub no_id: String,
+    pub chave_publica: String,
+    pub kdf: ParametrosKdf,
+    pub cifra: DadosCifrados,
+    pub criado_em: DateTime<Utc>,
+}
+
+impl ArquivoKeystore {
+    pub fn cifrar(chaves: &ParChaves, senha: &str) -> Result<Self> {
+        let mut rng = rand::thread_rng();
+
+        let mut sal = [0u8; TAMANHO_SAL];
+        rng.fill_bytes(&mut sal);
+        let mut nonce = [0u8; TAMANHO_NONCE];
+        rng.fill_bytes(&mut nonce);
+
+        let kdf = ParametrosKdf {
+            algoritmo: ALGORITMO_KDF.to_string(),
+            sal: hex::encode(sal),
+            memoria_kib: 19 * 1024, // Recomendação OWASP para Argon2id
+            iteracoes: 2,
+            paralelismo: 1,
+        };
+
+        let chave_publica = chaves.chave_publica();
+        let chave_cifra = Self::derivar_chave(senha, &kdf)?;
+        let cifra = XChaCha20Poly1305::new_from_slice(chave_cifra.as_slice())
+            .map_err(|e| anyhow::anyhow!("Chave de cifragem inválida: {}", e))?;
+        let chave_privada = Zeroizing::new(chaves.exportar_chave_privada());
+
+        let texto_cifrado = cifra
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &chave_privada,
+                    aad: &chave_publica,
+                },
+            )
+            .map_err(|e| anyhow::anyhow!("Erro ao cifrar chave privada: {}", e))?;
+
+        Ok(Self {
+            versao: VERSAO_KEYSTORE,
+            no_id: chaves.no_id().to_string(),
+            chave_publica: hex::encode(&chave_publica),
+            kdf,
+            cifra: DadosCifrados {
+                algoritmo: ALGORITMO_CIFRA.to_string(),
+                nonce: hex::encode(nonce),
+                texto_cifrado: hex::encode(texto_cifrado),
+            },
+            criado_em: Utc::now(),
+        })
+    }
+
+    pub fn decifrar(&self, senha: &str) -> Result<ParChaves> {
+        if self.versao != VERSAO_KEYSTORE {
+            return Err(anyhow::anyhow!("Versão de keystore não suportada: {}", self.versao));
+        }
+
+        if self.kdf.algoritmo != ALGORITMO_KDF || self.cifra.algoritmo != ALGORITMO_CIFRA {
+            return Err(anyhow::anyhow!(
+                "Algoritmos não suportados: {} / {}",
+                self.kdf.algoritmo,
+                self.cifra.algoritmo
+            ));
+        }
+
+        let chave_publica = hex::decode(&self.chave_publica)?;
+        let nonce = hex::decode(&self.cifra.nonce)?;
+        if nonce.len() != TAMANHO_NONCE {
+            return Err(anyhow::anyhow!("Nonce do keystore deve ter {} bytes", TAMANHO_NONCE));
+        }
+        let texto_cifrado = hex::decode(&self.cifra.texto_cifrado)?;
+
+        let chave_cifra = Self::derivar_chave(senha, &self.kdf)?;
+        let cifra = XChaCha20Poly1305::new_from_slice(chave_cifra.as_slice())
+            .map_err(|e| anyhow::anyhow!("Chave de cifragem inválida: {}", e))?;
+
+        let chave_privada = cifra
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &texto_cifrado,
+                    aad: &chave_publica,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| anyhow::anyhow!("Senha incorreta ou keystore corrompido"))?;
+
+        let chaves = ParChaves::de_bytes(&chave_privada)?;
+        if chaves.chave_publica() != chave_publica {
+            return Err(anyhow::anyhow!("Chave pública do keystore não corresponde à chave privada"));
+        }
+
+        Ok(chaves)
+    }
+
+    pub fn ler<P: AsRef<Path>>(caminho: P) -> Result<Self> {
+        let conteudo = std::fs::read(caminho.as_ref())
+            .map_err(|e| anyhow::anyhow!("Erro ao ler keystore {:?}: {}", caminho.as_ref(), e))?;
+
+        serde_json::from_slice(&conteudo)
+            .map_err(|e| anyhow::anyhow!("Keystore em formato inválido: {}", e))
+    }
+
+    /// Grava em arquivo temporário e renomeia, para nunca deixar um keystore pela metade
+    pub fn salvar<P: AsRef<Path>>(&self, caminho: P) -> Result<()> {
+        let caminho = caminho.as_ref();
+        let temporario = caminho.with_extension("tmp");
+
+        std::fs::write(&temporario, serde_json::to_vec_pretty(self)?)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&temporario, std::fs::Permissions::from_mode(0o600))?;
+        }
+
+        std::fs::rename(&temporario, caminho)?;
+        Ok(())
+    }
+
+    fn derivar_chave(senha: &str, kdf: &ParametrosKdf) -> Result<Zeroizing<[u8; TAMANHO_CHAVE_CIFRA]>> {
+        if kdf.memoria_kib > MEMORIA_MAXIMA_KIB
+            || kdf.iteracoes > ITERACOES_MAXIMAS
+            || kdf.paralelismo > PARALELISMO_MAXIMO
+        {
+            return Err(anyhow::anyhow!(
+                "Parâmetros de KDF acima do limite: {} KiB, {} iterações, paralelismo {}",
+                kdf.memoria_kib, kdf.iteracoes, kdf.paralelismo
+            ));
+        }
+
+        let sal = hex::decode(&kdf.sal)?;
+        let parametros = Params::new(
+            kdf.memoria_kib,
+            kdf.iteracoes,
+            kdf.paralelismo,
+            Some(TAMANHO_CHAVE_CIFRA),
+        )
+        .map_err(|e| anyhow::anyhow!("Parâmetros de KDF inválidos: {}", e))?;
+
+        let mut chave = Zeroizing::new([0u8; TAMANHO_CHAVE_CIFRA]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, parametros)
+            .hash_password_into(senha.as_bytes(), &sal, chave.as_mut_slice())
+            .map_err(|e| anyhow::anyhow!("Erro na derivação da chave: {}", e))?;
+
+        Ok(chave)
+    }
+}
+
+#[cfg(test)]
+mod testes {
+    use super::*;
+
+    #[test]
+    fn cifrar_e_decifrar_devolve_a_mesma_chave() {
+        let chaves = ParChaves::gerar();
+        let arquivo = ArquivoKeystore::cifrar(&chaves, "senha correta").unwrap();
+
+        let decifradas = arquivo.decifrar("senha correta").unwrap();
+        assert_eq!(decifradas.exportar_chave_privada(), chaves.exportar_chave_privada());
+        assert_eq!(arquivo.no_id, chaves.no_id().to_string());
+    }
+
+    #[test]
+    fn senha_errada_e_recusada() {
+        let arquivo = ArquivoKeystore::cifrar(&ParChaves::gerar(), "senha correta").unwrap();
+        assert!(arquivo.decifrar("outra senha").is_err());
+    }
+
+    #[test]
+    fn texto_cifrado_ou_chave_publica_adulterados_sao_recusados() {
+        let chaves = ParChaves::gerar();
+        let arquivo = ArquivoKeystore::cifrar(&chaves, "senha").unwrap();
+
+        let mut adulterado = arquivo.clone();
+        let mut texto_cifrado = hex::decode(&adulterado.cifra.texto_cifrado).unwrap();
+        texto_cifrado[0] ^= 1;
+        adulterado.cifra.texto_cifrado = hex::encode(texto_cifrado);
+        assert!(adulterado.decifrar("senha").is_err());
+
+        let mut outra_chave = arquivo.clone();
+        outra_chave.chave_publica = hex::encode(ParChaves::gerar().chave_publica());
+        assert!(outra_chave.decifrar("senha").is_err());
+    }
+
+    #[test]
+    fn parametros_de_kdf_acima_do_limite_sao_recusados() {
+        let mut arquivo = ArquivoKeystore::cifrar(&ParChaves::gerar(), "senha").unwrap();
+        arquivo.kdf.memoria_kib = u32::MAX;
+        assert!(arquivo.decifrar("senha").is_err());
+    }
+}
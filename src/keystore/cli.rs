@@ -0,0 +1,127 @@
+use crate::consenso::cripto::ParChaves;
+use crate::keystore::{ArquivoKeystore, Keystore};
+use anyhow::Result;
+use std::io::BufRead;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Variável de ambiente com o caminho do keystore
+pub const VARIAVEL_CAMINHO_KEYSTORE: &str = "NIMBOS_KEYSTORE";
+/// Variável de ambiente com a senha do keystore (se ausente, lida da entrada padrão)
+pub const VARIAVEL_SENHA_KEYSTORE: &str = "NIMBOS_SENHA_KEYSTORE";
+pub const CAMINHO_PADRAO_KEYSTORE: &str = "nimbos_keystore.json";
+
+pub fn caminho_keystore() -> PathBuf {
+    std::env::var(VARIAVEL_CAMINHO_KEYSTORE)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(CAMINHO_PADRAO_KEYSTORE))
+}
+
+pub fn ler_senha() -> Result<Zeroizing<String>> {
+    if let Ok(senha) = std::env::var(VARIAVEL_SENHA_KEYSTORE) {
+        return Ok(Zeroizing::new(senha));
+    }
+
+    eprintln!("Senha do keystore:");
+    let linha = ler_linha()?;
+    let senha = Zeroizing::new(linha.trim_end_matches(['\r', '\n']).to_string());
+
+    if senha.is_empty() {
+        return Err(anyhow::anyhow!("Senha do keystore não pode ser vazia"));
+    }
+
+    Ok(senha)
+}
+
+fn ler_linha() -> Result<Zeroizing<String>> {
+    let mut linha = Zeroizing::new(String::new());
+    std::io::stdin().lock().read_line(&mut linha)?;
+    Ok(linha)
+}
+
+/// Chave a importar: um keystore, um arquivo com a chave privada em hex ou, com
+/// `-`, a chave em hex lida da entrada padrão. A chave nunca vem na linha de
+/// comando, que fica no histórico do shell e visível no `ps`.
+fn ler_chave_importada(origem: &str) -> Result<ParChaves> {
+    let conteudo = if origem == "-" {
+        eprintln!("Chave privada (hex):");
+        ler_linha()?
+    } else {
+        let conteudo = Zeroizing::new(std::fs::read(origem)
+            .map_err(|e| anyhow::anyhow!("Erro ao ler {}: {}", origem, e))?);
+
+        if let Ok(arquivo) = serde_json::from_slice::<ArquivoKeystore>(&conteudo) {
+            eprintln!("Senha do keystore de origem:");
+            return arquivo.decifrar(&ler_senha()?);
+        }
+
+        Zeroizing::new(String::from_utf8(conteudo.to_vec())
+            .map_err(|_| anyhow::anyhow!("{} não é um keystore nem uma chave em hex", origem))?)
+    };
+
+    let bytes = Zeroizing::new(hex::decode(conteudo.trim())
+        .map_err(|_| anyhow::anyhow!("Chave privada em hex inválida"))?);
+    ParChaves::de_bytes(&bytes)
+}
+
+/// Executa o subcomando `chaves`:
+///
+/// - `chaves gerar`
+/// - `chaves mostrar`
+/// - `chaves importar <arquivo_keystore | arquivo_chave_hex | ->`
+/// - `chaves exportar <destino>`
+/// - `chaves rotacionar`
+pub fn executar_comando_chaves(argumentos: &[String]) -> Result<()> {
+    let caminho = caminho_keystore();
+
+    match argumentos.first().map(String::as_str) {
+        Some("gerar") => {
+            let keystore = Keystore::criar(&caminho, &ler_senha()?)?;
+            println!("Keystore criado em {:?}", keystore.caminho());
+            println!("Nó: {}", keystore.assinador().no_id());
+        }
+        Some("mostrar") => {
+            // Só os dados públicos; não precisa de senha
+            let arquivo = ArquivoKeystore::ler(&caminho)?;
+            println!("Keystore: {:?}", caminho);
+            println!("Versão: {}", arquivo.versao);
+            println!("Nó: {}", arquivo.no_id);
+            println!("Chave pública: {}", arquivo.chave_publica);
+            println!("Criado em: {}", arquivo.criado_em);
+        }
+        Some("importar") => {
+            let origem = argumentos
+                .get(1)
+                .ok_or_else(|| anyhow::anyhow!("Uso: chaves importar <arquivo_keystore | arquivo_chave_hex | ->"))?;
+
+            let chaves = ler_chave_importada(origem)?;
+
+            let keystore = Keystore::importar(&caminho, chaves, &ler_senha()?)?;
+            println!("Chave importada para {:?}", keystore.caminho());
+            println!("Nó: {}", keystore.assinador().no_id());
+        }
+        Some("exportar") => {
+            let destino = argumentos
+                .get(1)
+                .ok_or_else(|| anyhow::anyhow!("Uso: chaves exportar <destino>"))?;
+
+            let senha = ler_senha()?;
+            Keystore::carregar(&caminho, &senha)?.exportar(destino, &senha)?;
+            println!("Keystore exportado para {}", destino);
+        }
+        Some("rotacionar") => {
+            let senha = ler_senha()?;
+            let keystore = Keystore::carregar(&caminho, &senha)?;
+            let backup = keystore.rotacionar(&senha)?;
+            println!("Chave anterior preservada em {:?}", backup);
+            println!("Novo nó: {}", keystore.assinador().no_id());
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Uso: chaves <gerar | mostrar | importar <origem> | exportar <destino> | rotacionar>"
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod arquivo;
+mod cli;
+
+pub use arquivo::*;
+pub use cli::*;
+
+use crate::consenso::cripto::{Assinador, ParChaves};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Keystore cifrado em disco com as chaves do nó.
+///
+/// O `Assinador` exposto é compartilhado pelas camadas; rotacionar a chave troca
+/// o par dentro dele, sem precisar reconstruir as camadas.
+pub struct Keystore {
+    caminho: PathBuf,
+    assinador: Assinador,
+}
+
+impl Keystore {
+    /// Abre um keystore existente
+    pub fn carregar<P: AsRef<Path>>(caminho: P, senha: &str) -> Result<Self> {
+        let caminho = caminho.as_ref().to_path_buf();
+        let chaves = ArquivoKeystore::ler(&caminho)?.decifrar(senha)?;
+
+        info!("🔐 Keystore carregado: nó {}", chaves.no_id());
+
+        Ok(Self {
+            caminho,
+            assinador: Assinador::new(chaves),
+        })
+    }
+
+    /// Gera um novo par de chaves e grava o keystore; falha se o arquivo já existir
+    pub fn criar<P: AsRef<Path>>(caminho: P, senha: &str) -> Result<Self> {
+        Self::importar(caminho, ParChaves::gerar(), senha)
+    }
+
+    pub fn carregar_ou_criar<P: AsRef<Path>>(caminho: P, senha: &str) -> Result<Self> {
+        if caminho.as_ref().exists() {
+            Self::carregar(caminho, senha)
+        } else {
+            Self::criar(caminho, senha)
+        }
+    }
+
+    /// Grava um par de chaves já existente em um novo keystore
+    pub fn importar<P: AsRef<Path>>(caminho: P, chaves: ParChaves, senha: &str) -> Result<Self> {
+        let caminho = caminho.as_ref().to_path_buf();
+        if caminho.exists() {
+            return Err(anyhow::anyhow!("Keystore já existe em {:?}", caminho));
+        }
+
+        ArquivoKeystore::cifrar(&chaves, senha)?.salvar(&caminho)?;
+        info!("🔐 Keystore criado em {:?}: nó {}", caminho, chaves.no_id());
+
+        Ok(Self {
+            caminho,
+            assinador: Assinador::new(chaves),
+        })
+    }
+
+    /// Copia as chaves atuais para outro keystore, cifrado com a senha informada
+    pub fn exportar<P: AsRef<Path>>(&self, destino: P, senha: &str) -> Result<()> {
+        let destino = destino.as_ref();
+        if destino.exists() {
+            return Err(anyhow::anyhow!("Destino {:?} já existe", destino));
+        }
+
+        ArquivoKeystore::cifrar(&self.assinador.chaves_atuais(), senha)?.salvar(destino)
+    }
+
+    /// Gera um novo par de chaves, preservando o arquivo anterior como backup.
+    ///
+    /// Retorna o caminho do backup. Todas as camadas que compartilham o
+    /// `Assinador` passam a assinar com a nova chave imediatamente.
+    pub fn rotacionar(&self, senha: &str) -> Result<PathBuf> {
+        // Confirma a senha antes de mexer no arquivo
+        ArquivoKeystore::ler(&self.caminho)?.decifrar(senha)?;
+
+        let antigo_id = self.assinador.no_id();
+        let backup = self.caminho.with_extension(format!(
+            "{}.bak",
+            chrono::Utc::now().format("%Y%m%d%H%M%S")
+        ));
+        std::fs::copy(&self.caminho, &backup)?;
+
+        let novas = ParChaves::gerar();
+        ArquivoKeystore::cifrar(&novas, senha)?.salvar(&self.caminho)?;
+
+        info!("🔄 Chave rotacionada: {} → {}", antigo_id, novas.no_id());
+        self.assinador.substituir(novas);
+
+        Ok(backup)
+    }
+
+    pub fn assinador(&self) -> Assinador {
+        self.assinador.clone()
+    }
+
+    pub fn caminho(&self) -> &Path {
+        &self.caminho
+    }
+}
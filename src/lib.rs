@@ -3,9 +3,11 @@ pub mod comunicacao;
 pub mod blockchain;
 pub mod recompensas;
 pub mod deteccao_falhas;
+pub mod keystore;
 
 pub use consenso::*;
 pub use comunicacao::*;
 pub use blockchain::*;
 pub use recompensas::*;
-pub use deteccao_falhas::*;
\ No newline at end of file
+pub use deteccao_falhas::*;
+pub use keystore::*;
\ No newline at end of file
@@ -3,13 +3,80 @@ mod comunicacao;
 mod blockchain;
 mod recompensas;
 mod deteccao_falhas;
+mod keystore;
 
 use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{info, Level};
 use tracing_subscriber;
 
+/// Variável de ambiente com o arquivo JSON dos saldos iniciais (`{"<endereço>": saldo}`)
+const VARIAVEL_GENESIS: &str = "NIMBOS_GENESIS";
+
+/// Saldos das contas no genesis; todos os nós da rede precisam usar o mesmo arquivo.
+///
+/// Sem `NIMBOS_GENESIS` a rede começa sem saldo algum.
+fn carregar_saldos_genesis() -> Result<HashMap<String, u64>> {
+    let Ok(caminho) = std::env::var(VARIAVEL_GENESIS) else {
+        return Ok(HashMap::new());
+    };
+    
+    let conteudo = std::fs::read(&caminho)
+        .map_err(|e| anyhow::anyhow!("Erro ao ler o genesis {}: {}", caminho, e))?;
+    serde_json::from_slice(&conteudo)
+        .map_err(|e| anyhow::anyhow!("Genesis {} mal formado: {}", caminho, e))
+}
+
+/// Variável de ambiente com a máquina de estado da aplicação (`transferencias` ou `hash`)
+const VARIAVEL_MAQUINA: &str = "NIMBOS_MAQUINA";
+
+/// Fábrica da máquina de estado escolhida em `NIMBOS_MAQUINA`; todos os nós da
+/// rede precisam usar a mesma.
+///
+/// Sem a variável, as transferências de tokens a partir dos `saldos_genesis`.
+fn criar_fabrica_maquina(saldos_genesis: HashMap<String, u64>) -> Result<consenso::processamento::FabricaMaquinaEstado> {
+    let nome = std::env::var(VARIAVEL_MAQUINA).unwrap_or_else(|_| "transferencias".to_string());
+    
+    match nome.as_str() {
+        "transferencias" => Ok(Arc::new(move || {
+            Box::new(consenso::processamento::MaquinaTransferencias::com_saldos(&saldos_genesis))
+        })),
+        "hash" => Ok(Arc::new(|| Box::new(consenso::processamento::MaquinaEstadoHash::new()))),
+        outro => Err(anyhow::anyhow!("Máquina de estado {} desconhecida em {}", outro, VARIAVEL_MAQUINA)),
+    }
+}
+
+/// Variável de ambiente com o modo de ordenação da fila (`uniforme`, `stake` ou `vrf`)
+const VARIAVEL_ORDENACAO: &str = "NIMBOS_ORDENACAO";
+
+/// Configuração das rodadas, com o modo de ordenação de `NIMBOS_ORDENACAO`;
+/// todos os nós da rede precisam usar o mesmo.
+///
+/// Sem a variável, a ordenação uniforme.
+fn carregar_configuracao_rodada() -> Result<consenso::ConfiguracaoRodada> {
+    let modo_ordenacao = match std::env::var(VARIAVEL_ORDENACAO).as_deref() {
+        Err(_) | Ok("uniforme") => consenso::ordenacao::ModoOrdenacao::Uniforme,
+        Ok("stake") => consenso::ordenacao::ModoOrdenacao::PonderadoPorStake,
+        Ok("vrf") => consenso::ordenacao::ModoOrdenacao::Vrf,
+        Ok(outro) => return Err(anyhow::anyhow!("Modo de ordenação {} desconhecido em {}", outro, VARIAVEL_ORDENACAO)),
+    };
+    info!("🎲 Modo de ordenação: {:?}", modo_ordenacao);
+    
+    Ok(consenso::ConfiguracaoRodada {
+        modo_ordenacao,
+        ..consenso::ConfiguracaoRodada::default()
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Subcomando de gerenciamento de chaves: `nimbos-blockchain chaves <acao>`
+    let argumentos: Vec<String> = std::env::args().skip(1).collect();
+    if argumentos.first().map(String::as_str) == Some("chaves") {
+        return keystore::executar_comando_chaves(&argumentos[1..]);
+    }
+    
     // Configurar logging
     tracing_subscriber::fmt()
         .with_max_level(Level::INFO)
@@ -17,15 +84,51 @@ async fn main() -> Result<()> {
     
     info!("🚀 Iniciando Nimbos Blockchain");
     
+    // Chaves do nó (assinam transações, validações, blocos e mensagens)
+    let keystore = keystore::Keystore::carregar_ou_criar(
+        keystore::caminho_keystore(),
+        &keystore::ler_senha()?,
+    )?;
+    let assinador = keystore.assinador();
+    info!("🔑 Chave pública do nó: {}", hex::encode(assinador.chave_publica()));
+    
+    // Mesma máquina, a partir dos mesmos saldos iniciais, no processamento e na
+    // reexecução dos checkpoints
+    let saldos_genesis = carregar_saldos_genesis()?;
+    info!("💰 {} contas com saldo no genesis", saldos_genesis.len());
+    let fabrica_maquina = criar_fabrica_maquina(saldos_genesis)?;
+    info!("⚙️ Máquina de estado: {}", fabrica_maquina().nome());
+    
     // Inicializar todas as camadas
-    let mut sistema_consenso = consenso::SistemaConsenso::new().await?;
-    let comunicacao = comunicacao::CamadaComunicacao::new().await?;
-    let mut blockchain = blockchain::CamadaBlockchain::new();
-    let recompensas = recompensas::CamadaRecompensas::new();
+    let (saida_consenso, mut rx_saida_consenso) = tokio::sync::mpsc::unbounded_channel();
+    let mut sistema_consenso = consenso::SistemaConsenso::new_com_maquina(
+        assinador.clone(),
+        saida_consenso,
+        carregar_configuracao_rodada()?,
+        fabrica_maquina(),
+    ).await?;
+    let recompensas = Arc::new(recompensas::CamadaRecompensas::new());
+    let blockchain = Arc::new(blockchain::CamadaBlockchain::new(assinador.clone()));
+    blockchain.definir_maquina_estado(fabrica_maquina).await;
+    sistema_consenso.definir_recompensas(recompensas).await;
+    blockchain.inicializar().await?;
+    let comunicacao = Arc::new(
+        comunicacao::CamadaComunicacao::new(assinador, sistema_consenso.entrada(), blockchain).await?,
+    );
     let deteccao_falhas = deteccao_falhas::CamadaDeteccaoFalhas::new();
     
     info!("✅ Todas as camadas inicializadas com sucesso");
     
+    // Encaminhar à rede o que o consenso produz (commitments, reveals e blocos)
+    let comunicacao_saida = comunicacao.clone();
+    tokio::spawn(async move {
+        while let Some(saida) = rx_saida_consenso.recv().await {
+            if let Err(e) = comunicacao_saida.transmitir_saida_consenso(saida).await {
+                tracing::error!("Erro ao transmitir saída do consenso: {}", e);
+            }
+        }
+    });
+    
     // Loop principal da blockchain
     loop {
         // Executar ciclo de consenso
@@ -13,6 +13,9 @@ pub struct ContaNo {
     pub saldo: u64,
     pub total_recebido: u64,
     pub numero_transacoes: u64,
+    /// Slots em que o nó era o processador da vez e foi pulado pela fila
+    #[serde(default)]
+    pub slots_perdidos: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -106,6 +109,7 @@ impl DistribuidorRecompensas {
             saldo: 0,
             total_recebido: 0,
             numero_transacoes: 0,
+            slots_perdidos: 0,
         });
         
         conta.saldo += valor;
@@ -194,6 +198,27 @@ impl DistribuidorRecompensas {
         }
     }
     
+    /// Desconta `penalidade` do saldo do processador pulado, até zerá-lo.
+    ///
+    /// Retorna o valor efetivamente descontado.
+    pub async fn penalizar_ausencia(&self, no_id: &str, penalidade: u64) -> u64 {
+        let mut contas = self.contas.write().await;
+        let conta = contas.entry(no_id.to_string()).or_insert(ContaNo {
+            no_id: no_id.to_string(),
+            saldo: 0,
+            total_recebido: 0,
+            numero_transacoes: 0,
+            slots_perdidos: 0,
+        });
+        
+        let descontado = penalidade.min(conta.saldo);
+        conta.saldo -= descontado;
+        conta.slots_perdidos += 1;
+        
+        warn!("Nó {} penalizado em {} por slot perdido", no_id, descontado);
+        descontado
+    }
+    
     pub fn definir_taxa_minima(&mut self, nova_taxa: u64) {
         self.taxa_minima = nova_taxa;
         info!("Taxa mínima atualizada para: {}", nova_taxa);
@@ -2,17 +2,24 @@ mod distribuidor;
 mod calculadora;
 mod ledger;
 
+use crate::consenso::processamento::EvidenciaSalto;
+use crate::consenso::tipos::NodeId;
 use anyhow::Result;
-use tracing::info;
+use std::collections::HashSet;
+use tracing::{info, warn};
 
 pub use distribuidor::*;
 pub use calculadora::*;
 pub use ledger::*;
 
+/// Descontado do processador a cada slot em que a fila precisou pulá-lo
+pub const PENALIDADE_SALTO_PADRAO: u64 = 1000;
+
 pub struct CamadaRecompensas {
     distribuidor: DistribuidorRecompensas,
     calculadora: CalculadoraTaxas,
     ledger: LedgerRecompensas,
+    penalidade_salto: u64,
 }
 
 impl CamadaRecompensas {
@@ -21,6 +28,7 @@ impl CamadaRecompensas {
             distribuidor: DistribuidorRecompensas::new(),
             calculadora: CalculadoraTaxas::new(),
             ledger: LedgerRecompensas::new(),
+            penalidade_salto: PENALIDADE_SALTO_PADRAO,
         }
     }
     
@@ -38,4 +46,25 @@ impl CamadaRecompensas {
         
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Penaliza os processadores pulados registrados num bloco já anexado; devolve o total descontado.
+    ///
+    /// Só conta a evidência que confere com a `fila` certificada do bloco, uma vez por slot.
+    pub async fn aplicar_saltos(&self, saltos: &[EvidenciaSalto], fila: &[NodeId]) -> u64 {
+        let mut aplicados = HashSet::new();
+        let mut total = 0;
+        
+        for salto in saltos {
+            if !salto.confere_com_fila(fila) || !aplicados.insert((salto.rodada, salto.slot)) {
+                warn!("Evidência de salto do slot {} da rodada {} ignorada", salto.slot, salto.rodada);
+                continue;
+            }
+            
+            total += self.distribuidor
+                .penalizar_ausencia(&salto.processador.to_string(), self.penalidade_salto)
+                .await;
+        }
+        
+        total
+    }
+}